@@ -0,0 +1,78 @@
+//! Rough timing of the owned `JsonValue` parse against the zero-copy `BorrowedValue` parse
+//!
+//! run with `cargo run --release --example borrowed_bench`
+use parser_util::json::{ast::JsonAst, borrowed::BorrowedValue, value::JsonValue};
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 200;
+
+/// a few thousand records that look like a typical api response, a handful of strings have escapes
+fn make_document() -> String {
+    let mut doc = String::from("[");
+    for k in 0..5000 {
+        if k > 0 {
+            doc.push(',');
+        }
+        doc.push_str(&format!(
+            "{{\"id\":{},\"name\":\"user_{}\",\"email\":\"user{}@example.com\",\
+             \"score\":{}.25,\"active\":{},\"tags\":[\"alpha\",\"beta\",\"gamma\"],\
+             \"bio\":\"{}\"}}",
+            k,
+            k,
+            k,
+            k % 100,
+            k % 2 == 0,
+            if k % 10 == 0 { "line one\\nline two" } else { "nothing to escape here" }
+        ));
+    }
+    doc.push(']');
+    doc
+}
+
+fn time(label: &str, mut f: impl FnMut()) -> Duration {
+    // warm up
+    for _ in 0..10 {
+        f();
+    }
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let elapsed = start.elapsed();
+    println!("{:<28} {:>10.3?} per parse", label, elapsed / ITERATIONS);
+    elapsed
+}
+
+fn main() {
+    let doc = make_document();
+    println!("document is {} bytes, {} iterations\n", doc.len(), ITERATIONS);
+
+    let owned = time("JsonValue::parse", || {
+        let value = JsonValue::parse(&doc).unwrap();
+        std::hint::black_box(value);
+    });
+    let borrowed = time("BorrowedValue::parse", || {
+        let value = BorrowedValue::parse(&doc).unwrap();
+        std::hint::black_box(value);
+    });
+
+    // the tree and token stream get reused, so only the value itself is built each time
+    let mut ast = JsonAst::new();
+    let owned_reused = time("JsonValue::from_ast", || {
+        ast.parse(&doc).unwrap();
+        let value = JsonValue::from_ast(&ast, &doc);
+        std::hint::black_box(value);
+    });
+    let borrowed_reused = time("BorrowedValue::from_ast", || {
+        ast.parse(&doc).unwrap();
+        let value = BorrowedValue::from_ast(&ast, &doc);
+        std::hint::black_box(value);
+    });
+
+    println!();
+    println!(
+        "borrowed is {:.2}x the speed of owned ({:.2}x with a reused JsonAst)",
+        owned.as_secs_f64() / borrowed.as_secs_f64(),
+        owned_reused.as_secs_f64() / borrowed_reused.as_secs_f64()
+    );
+}
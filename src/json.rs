@@ -1,2 +1,24 @@
 pub mod lexer; 
-pub mod ast; 
\ No newline at end of file
+pub mod ast; 
+pub mod borrowed;
+pub mod canonical;
+#[cfg(feature = "serde")]
+pub mod de;
+pub mod diff;
+pub mod ndjson;
+pub mod number;
+pub mod patch;
+pub mod path;
+pub mod pointer;
+pub mod reader;
+pub mod schema;
+#[cfg(feature = "serde")]
+pub mod ser;
+pub mod value;
+pub mod writer;
+
+#[cfg(feature = "serde")]
+pub use self::{
+    de::{from_str, from_value, SerdeError},
+    ser::{to_string, to_string_pretty, to_value},
+};
@@ -3,24 +3,151 @@ use sandboxed_collections::{
 }; 
 
 use super::{lexer::*};
+use crate::limits::{Limit, Limits};
+use std::{collections::HashMap, fmt};
 
+/// Every variant (except `LexFailed` which wraps one) carries a `Diagnostic` with
+/// the position of the offending token and what the parser expected to find there
+#[derive(Debug)]
 pub enum ParseError {
     LexFailed(LexerError),
-    MissingCloseBracket,
-    BracketMismatch,
-    MissingProperty,
-    InvalidToken(&'static str),
+    MissingCloseBracket(Diagnostic),
+    BracketMismatch(Diagnostic),
+    MissingProperty(Diagnostic),
+    InvalidToken(Diagnostic),
+    /// A property name showed up twice in the same object, only with `DuplicateKeys::Reject`.
+    /// `diag` points at the second one and `first` is where the name was first defined
+    DuplicateKey { diag: Diagnostic, first: Position },
+    /// The document went over one of `ParseOptions::limits`
+    LimitExceeded { limit: Limit, diag: Diagnostic },
+}
+
+impl ParseError {
+    pub fn diagnostic(&self) -> &Diagnostic {
+        match self {
+            Self::LexFailed(err) => err.diagnostic(),
+            Self::MissingCloseBracket(diag)
+            | Self::BracketMismatch(diag)
+            | Self::MissingProperty(diag)
+            | Self::InvalidToken(diag)
+            | Self::DuplicateKey { diag, .. }
+            | Self::LimitExceeded { diag, .. } => diag,
+        }
+    }
+
+    /// Renders the error with the offending line of `raw_text` and a caret under the problem
+    pub fn render(&self, raw_text: &str) -> String {
+        self.diagnostic().render(raw_text)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let diag = self.diagnostic();
+        write!(
+            f,
+            "{} at line {}, column {}",
+            diag, diag.pos.line, diag.pos.column
+        )
+    }
 }
 
 impl From<LexerError> for ParseError {
     fn from(err: LexerError) -> Self {
-        Self::LexFailed(err)
+        match err {
+            LexerError::LimitExceeded { limit, diag } => Self::LimitExceeded { limit, diag },
+            err => Self::LexFailed(err),
+        }
+    }
+}
+
+/// States of the parser's PDA, describes what the next token is allowed to be
+#[derive(Copy, Clone, PartialEq)]
+pub(crate) enum Expect {
+    Value,
+    ValueOrClose,
+    Key,
+    KeyOrClose,
+    Colon,
+    CommaOrClose,
+    End,
+}
+
+impl Expect {
+    /// What the PDA wants to see in this state, `in_object` decides which close bracket is expected
+    pub(crate) fn describe(self, in_object: bool) -> &'static str {
+        match self {
+            Expect::Value => "a value",
+            Expect::ValueOrClose => "a value or `]`",
+            Expect::Key => "a property name",
+            Expect::KeyOrClose => "a property name or `}`",
+            Expect::Colon => "`:`",
+            Expect::CommaOrClose if in_object => "`,` or `}`",
+            Expect::CommaOrClose => "`,` or `]`",
+            Expect::End => "end of input",
+        }
+    }
+}
+
+/// # Description
+/// Knobs for `JsonAst::parse_with(..)`/`JsonValue::parse_with(..)`
+/// # Comments
+/// - `ParseOptions::default()` is strict RFC 8259 json, exactly what `parse(..)` accepts
+#[derive(Clone, Debug, Default)]
+pub struct ParseOptions {
+    pub dialect: Dialect,
+    pub duplicate_keys: DuplicateKeys,
+    /// No limits by default, set this to `Limits::untrusted()` for input off the network
+    pub limits: Limits,
+    /// JSON5's `Infinity`, `-Infinity` and `NaN` have no json representation. They are rejected with
+    /// `ParseError::InvalidToken` unless this is set, in which case `JsonValue` and the writer turn them into `null`
+    pub non_finite_as_null: bool,
+}
+
+impl ParseOptions {
+    /// JSON with comments, the format of VS Code's settings files: `//` and `/* */` comments plus trailing commas
+    pub fn jsonc() -> Self {
+        Self {
+            dialect: Dialect::Jsonc,
+            ..Self::default()
+        }
+    }
+
+    /// Full JSON5, see https://spec.json5.org
+    pub fn json5() -> Self {
+        Self {
+            dialect: Dialect::Json5,
+            ..Self::default()
+        }
+    }
+}
+
+/// # Description
+/// What the parser does when an object has the same property name more than once
+/// # Comments
+/// - Names are compared after decoding, so `"a"` and `"\u0061"` are the same key
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum DuplicateKeys {
+    /// Every property is kept in document order, `JsonValue::get_all(..)` finds all of them
+    KeepAll,
+    /// Fail with `ParseError::DuplicateKey`, use this for anything security sensitive
+    /// since two parsers that disagree on which duplicate wins can be played against each other
+    Reject,
+    /// Later duplicates are parsed (so they still have to be valid) and then dropped
+    FirstWins,
+    /// The last value wins, it takes the place of the first property so key order doesn't change
+    LastWins,
+}
+
+impl Default for DuplicateKeys {
+    fn default() -> Self {
+        Self::KeepAll
     }
 }
 
 pub struct JsonAst {
-    lexer: JsonLexer,
-    ast: NaryTree<JsToken>,
+    pub(crate) lexer: JsonLexer,
+    pub(crate) ast: NaryTree<JsToken>,
 }
 
 impl JsonAst {
@@ -39,165 +166,470 @@ impl JsonAst {
     /// stack/PDA based parser instead. The main idea is simple:
     ///     - Create a stack of parent nodes
     ///     - If I see and open bracket push node onto parent stack
-    ///     - If I see a close bracket keep poping off parent stack until top of the stack is a bracket node (pop atleast one)
-    ///     - If I see a comma token keep popping off parent stack until top of stack is a bracket node (pop 0 or more)
-    ///     - For any other token I add it as the child of the parent (roughly though, there are a bunch of sub cases to this)
-    pub fn parse(&mut self, raw_text: &String) -> Result<(), ParseError> {
-        self.lexer.lex(raw_text)?;
-
-        //predicate the checks if something is comma close_bracket
-        let is_comma_or_close = |tok| match tok {
-            JsToken::Comma | JsToken::Close(_) => true,
-            _ => false,
-        };
+    ///     - If I see a property name push it onto the parent stack, it gets popped as soon as its value is attached
+    ///     - If I see a close bracket pop the parent stack, the top MUST be the matching open bracket
+    ///     - Any other value gets added as the child of the top of the stack (or becomes the root if the stack is empty)
+    /// - The `expect` variable is the state of the PDA, it's what makes the parser reject things like `[1 2]` or `{"a":1,}`
+    /// - Any value is allowed at the top level, but once it's finished only whitespace may follow
+    pub fn parse(&mut self, raw_text: &str) -> Result<(), ParseError> {
+        self.parse_with(raw_text, &ParseOptions::default())
+    }
+
+    /// # Description
+    /// Like `parse(..)` but `options` decide which dialect of json is accepted
+    /// # Comments
+    /// - Trailing commas are handled by letting the PDA go to `KeyOrClose`/`ValueOrClose` after a comma
+    /// - JSON5 identifiers in value position are swapped for the literal token they spell, so the tree never
+    /// holds an `Identifier` that isn't a property name. `Infinity` and `NaN` become number tokens,
+    /// which are only accepted with `options.non_finite_as_null`
+    pub fn parse_with(&mut self, raw_text: &str, options: &ParseOptions) -> Result<(), ParseError> {
+        self.lexer.lex_with(raw_text, options.dialect, &options.limits)?;
+        self.parse_tokens(raw_text, options, &mut Vec::new(), false)
+    }
+
+    /// # Description
+    /// Error tolerant version of `parse_with(..)`, meant for editors that want to point out every mistake at once
+    /// # Returns
+    /// Every problem found, sorted by position. The tree is always filled with a best-effort parse,
+    /// even when the list isn't empty
+    /// # Comments
+    /// - The lexer skips over text it can't make sense of and leaves a `JsToken::Error` in its place
+    /// - The PDA resynchronises instead of bailing:
+    ///     - A missing value (`[1,,2]`, `{"a":}`) or an unusable token in value position becomes a `JsToken::Error`
+    ///     placeholder node, so object properties always keep their key/value shape
+    ///     - A missing `,` or `:` is reported and then assumed to be there
+    ///     - A close bracket that matches a container further down the stack closes everything above it,
+    ///     one that matches nothing is reported and skipped
+    ///     - Anything after the top level value is reported once and ignored
+    pub fn parse_recovering(&mut self, raw_text: &str, options: &ParseOptions) -> Vec<ParseError> {
+        let mut errors: Vec<ParseError> = self
+            .lexer
+            .lex_recovering(raw_text, options.dialect, &options.limits)
+            .into_iter()
+            .map(ParseError::from)
+            .collect();
+        self.parse_tokens(raw_text, options, &mut errors, true)
+            .expect("a recovering parse never bails");
+        errors.sort_by_key(|err| err.diagnostic().pos.offset);
+        errors
+    }
+
+    /// # Description
+    /// Runs the PDA over the lexer's token stream
+    /// # Arguments
+    /// - `errors` : where problems go when `recover` is set
+    /// - `recover` : if `false` the first problem is returned as an `Err`
+    fn parse_tokens(
+        &mut self,
+        raw_text: &str,
+        options: &ParseOptions,
+        errors: &mut Vec<ParseError>,
+        recover: bool,
+    ) -> Result<(), ParseError> {
+        self.ast = NaryTree::new();
 
         let mut parent_stack: Vec<NodeAddr> = Vec::new();
-        let mut token_stream = self.lexer.get_tok_stream().iter().peekable();
+        let mut expect = Expect::Value;
         let mut first_alloc = true;
+        // object -> (decoded key -> key node, offset), stays empty with `DuplicateKeys::KeepAll`
+        let mut seen_keys: HashMap<NodeAddr, HashMap<String, (NodeAddr, usize)>> = HashMap::new();
 
-        while let Some(&tok) = token_stream.next() {
+        let token_stream = self.lexer.get_tok_stream();
+        let token_offsets = self.lexer.get_tok_offsets();
+        let mut k = 0;
+        while k < token_stream.len() {
+            let tok = token_stream[k];
             // print_token(tok, "current token", raw_text);
-            match tok {
-                JsToken::Open('[') | JsToken::Open('{') => {
-                    let addr = Self::parse_allocate(&mut self.ast, Some(tok), &mut first_alloc);
-                    if let Some(&parent_addr) = parent_stack.last() {
-                        self.ast[parent_addr].add_child(addr, parent_addr);
+            let offset = token_offsets[k];
+            let tok = match (expect, tok) {
+                (Expect::Value, JsToken::Identifier { lbound, ubound })
+                | (Expect::ValueOrClose, JsToken::Identifier { lbound, ubound }) => {
+                    match &raw_text[lbound as usize..ubound as usize] {
+                        "true" => JsToken::Boolean(true),
+                        "false" => JsToken::Boolean(false),
+                        "null" => JsToken::Null,
+                        "Infinity" | "NaN" => JsToken::Number { lbound, ubound },
+                        _ => tok,
                     }
-                    parent_stack.push(addr);
                 }
+                _ => tok,
+            };
+            let diagnostic = |expected: &str| {
+                Diagnostic::new(raw_text, offset, expected, describe_token(tok, raw_text))
+            };
+            let mut report = |err: ParseError| {
+                if recover {
+                    errors.push(err);
+                    Ok(())
+                } else {
+                    Err(err)
+                }
+            };
+            // a zero width placeholder for something that's missing right before this token
+            let missing = JsToken::Error {
+                lbound: offset as u32,
+                ubound: offset as u32,
+            };
+            match (expect, tok) {
+                // the lexer already reported whatever this was, it stands in for a value
+                (Expect::Value, JsToken::Error { .. })
+                | (Expect::ValueOrClose, JsToken::Error { .. }) => {
+                    let addr = Self::parse_allocate(&mut self.ast, Some(tok), &mut first_alloc);
+                    Self::attach_value(&mut self.ast, &mut parent_stack, addr);
+                    expect = Self::after_value(&parent_stack);
+                }
+                (_, JsToken::Error { .. }) => (),
 
-                JsToken::Number(_) | JsToken::String { .. } | JsToken::Boolean(_) => {
-                    if let Some(&parent_addr) = parent_stack.last() {
-                        let &parent_token = self.ast[parent_addr].data.as_ref().unwrap();
-                        // print_token(parent_token, "parent token", raw_text);
-
-                        if JsToken::Open('[') == parent_token {
-                            let child_addr =
-                                Self::parse_allocate(&mut self.ast, Some(tok), &mut first_alloc);
-
-                            self.ast[parent_addr].add_child(child_addr, parent_addr);
-
-                            if lookahead(token_stream.clone(), is_comma_or_close) == false {
-                                return Err(ParseError::InvalidToken(
-                                    "missing colon or close bracket",
-                                ));
-                            }
-                        } else if JsToken::Open('{') == parent_token {
-                            if let JsToken::String { .. } = tok {
-                                let child_addr = Self::parse_allocate(
-                                    &mut self.ast,
-                                    Some(tok),
-                                    &mut first_alloc,
-                                );
-                                self.ast[parent_addr].add_child(child_addr, parent_addr);
-
-                                if let Some(&&lookahead) = token_stream.peek() {
-                                    match lookahead {
-                                        JsToken::Colon => {
-                                            token_stream.next();
-                                        }
-                                        JsToken::Close(_) => (),
-                                        _ => {
-                                            return Err(ParseError::InvalidToken("missing colon"));
-                                        }
-                                    }
-                                }
-                                parent_stack.push(child_addr);
-                            } else {
-                                return Err(ParseError::MissingProperty);
-                            }
-                        } else if let JsToken::String { .. } = parent_token {
-                            let child_addr =
-                                Self::parse_allocate(&mut self.ast, Some(tok), &mut first_alloc);
-                            self.ast[parent_addr].add_child(child_addr, parent_addr);
-
-                            if lookahead(token_stream.clone(), is_comma_or_close) == false {
-                                return Err(ParseError::InvalidToken(
-                                    "missing colon or close bracket",
-                                ));
-                            }
+                (Expect::Value, JsToken::Open(ob_char))
+                | (Expect::ValueOrClose, JsToken::Open(ob_char)) => {
+                    let addr = Self::parse_allocate(&mut self.ast, Some(tok), &mut first_alloc);
+                    Self::attach_value(&mut self.ast, &mut parent_stack, addr);
+                    parent_stack.push(addr);
+                    expect = if ob_char == '{' {
+                        Expect::KeyOrClose
+                    } else {
+                        Expect::ValueOrClose
+                    };
+                }
 
-                            match tok {
-                                JsToken::Number(_) | JsToken::Boolean(_) => {
-                                    // pop because numbers and booleans should NEVER have children.
-                                    // only strings and open brackets should have children.
-                                    parent_stack.pop();
-                                }
-                                _ => parent_stack.push(child_addr),
-                            }
+                (Expect::Value, JsToken::Number { .. })
+                | (Expect::Value, JsToken::String { .. })
+                | (Expect::Value, JsToken::Boolean(_))
+                | (Expect::Value, JsToken::Null)
+                | (Expect::ValueOrClose, JsToken::Number { .. })
+                | (Expect::ValueOrClose, JsToken::String { .. })
+                | (Expect::ValueOrClose, JsToken::Boolean(_))
+                | (Expect::ValueOrClose, JsToken::Null) => {
+                    if let JsToken::Number { lbound, ubound } = tok {
+                        let lexeme = &raw_text[lbound as usize..ubound as usize];
+                        if options.non_finite_as_null == false && is_non_finite(lexeme) {
+                            report(ParseError::InvalidToken(diagnostic("a finite number")))?;
                         }
                     }
+                    let addr = Self::parse_allocate(&mut self.ast, Some(tok), &mut first_alloc);
+                    Self::attach_value(&mut self.ast, &mut parent_stack, addr);
+                    expect = Self::after_value(&parent_stack);
                 }
-                JsToken::Comma => {
-                    if let Some(&parent_addr) = parent_stack.last() {
-                        let mut parent_token = self.ast[parent_addr].data.unwrap();
-                        // print_token(parent_token, "parent token", raw_text);
-
-                        if parent_token != JsToken::Open('{') && parent_token != JsToken::Open('[')
-                        {
-                            while let Some(parent_addr) = parent_stack.pop() {
-                                parent_token = self.ast[parent_addr].data.unwrap();
-                                if let JsToken::Open(_) = parent_token {
-                                    parent_stack.push(parent_addr);
-                                    break;
-                                }
-                            }
+
+                (Expect::Key, JsToken::String { .. })
+                | (Expect::KeyOrClose, JsToken::String { .. })
+                | (Expect::Key, JsToken::Identifier { .. })
+                | (Expect::KeyOrClose, JsToken::Identifier { .. }) => {
+                    let parent_addr = *parent_stack.last().unwrap();
+                    let ast = &mut self.ast;
+                    let key_addr = Self::push_key(ast, &mut parent_stack, tok, &mut first_alloc);
+                    expect = Expect::Colon;
+                    if options.duplicate_keys != DuplicateKeys::KeepAll {
+                        let seen = seen_keys.entry(parent_addr).or_default();
+                        let duplicate = Self::apply_duplicate_policy(
+                            &mut self.ast,
+                            seen,
+                            (parent_addr, key_addr),
+                            offset,
+                            raw_text,
+                            options.duplicate_keys,
+                        );
+                        if let Some(err) = duplicate {
+                            report(err)?;
                         }
                     }
                 }
 
-                JsToken::Close(cb_char) => {
+                (Expect::Colon, JsToken::Colon) => {
+                    expect = Expect::Value;
+                }
+
+                (Expect::CommaOrClose, JsToken::Comma) => {
+                    let parent_addr = *parent_stack.last().unwrap();
+                    let in_object = self.ast[parent_addr].data == Some(JsToken::Open('{'));
+                    let trailing_comma = options.dialect != Dialect::Strict;
+                    expect = match (in_object, trailing_comma) {
+                        (true, false) => Expect::Key,
+                        (true, true) => Expect::KeyOrClose,
+                        (false, false) => Expect::Value,
+                        (false, true) => Expect::ValueOrClose,
+                    };
+                }
+
+                (Expect::KeyOrClose, JsToken::Close(cb_char))
+                | (Expect::ValueOrClose, JsToken::Close(cb_char))
+                | (Expect::CommaOrClose, JsToken::Close(cb_char)) => {
                     let ob_char = match cb_char {
                         '}' => '{',
                         ']' => '[',
                         _ => panic!("invalid close bracket type detected in token. The lexer is likely bugged. Review lexer.rs"),
                     };
-
-                    if parent_stack.is_empty() {
-                        return Err(ParseError::MissingCloseBracket);
-                    }
-                    let mut parent_tok = JsToken::Unknown;
-
-                    // keep popping until I reach the nearest array or object in the parent stack.
-                    // this should make it such that String token only ever has at most ONE child specifically im talking
-                    // about the  properties in json Object
-                    while let Some(parent_addr) = parent_stack.pop() {
-                        parent_tok = self.ast[parent_addr]
-                            .data
-                            .as_ref()
-                            .map(|&parent| parent)
-                            .unwrap();
-                        if let JsToken::Open(_) = parent_tok {
-                            break;
+                    // keys get popped as soon as their value is attached so the top
+                    // of the stack is always the nearest array or object
+                    let parent_addr = parent_stack.pop().unwrap();
+                    if self.ast[parent_addr].data != Some(JsToken::Open(ob_char)) {
+                        let expected = Self::expected_text(expect, &self.ast, parent_addr);
+                        report(ParseError::BracketMismatch(diagnostic(expected)))?;
+                        let opened = Some(JsToken::Open(ob_char));
+                        if parent_stack.iter().any(|&addr| self.ast[addr].data == opened) {
+                            // close everything up to the matching bracket
+                            while self.ast[parent_stack.pop().unwrap()].data != opened {}
+                        } else {
+                            // a stray bracket, pretend it isn't there
+                            parent_stack.push(parent_addr);
+                            k += 1;
+                            continue;
                         }
                     }
+                    expect = Self::after_value(&parent_stack);
+                }
 
-                    if JsToken::Open(ob_char) != parent_tok {
-                        return Err(ParseError::BracketMismatch);
+                (Expect::Key, _) | (Expect::KeyOrClose, _) => {
+                    let expected = Self::expected_text(expect, &self.ast, NULL);
+                    report(ParseError::MissingProperty(diagnostic(expected)))?;
+                    match tok {
+                        // trailing comma, let the close arm deal with the bracket
+                        JsToken::Close(_) => {
+                            expect = Expect::KeyOrClose;
+                            continue;
+                        }
+                        JsToken::Comma => (),
+                        // the property name is missing, fill it in and read the value
+                        JsToken::Colon => {
+                            let ast = &mut self.ast;
+                            Self::push_key(ast, &mut parent_stack, missing, &mut first_alloc);
+                            expect = Expect::Value;
+                        }
+                        // a container used as a key, treat it as the value of a nameless property
+                        JsToken::Open(_) => {
+                            let ast = &mut self.ast;
+                            Self::push_key(ast, &mut parent_stack, missing, &mut first_alloc);
+                            expect = Expect::Value;
+                            continue;
+                        }
+                        // some other value used as a key, it's replaced by a placeholder key
+                        _ => {
+                            let key = match tok {
+                                JsToken::Number { lbound, ubound } => {
+                                    JsToken::Error { lbound, ubound }
+                                }
+                                _ => missing,
+                            };
+                            let ast = &mut self.ast;
+                            Self::push_key(ast, &mut parent_stack, key, &mut first_alloc);
+                            expect = Expect::Colon;
+                        }
+                    }
+                }
+                (_, _) => {
+                    let parent_addr = parent_stack.last().copied().unwrap_or(NULL);
+                    let expected = Self::expected_text(expect, &self.ast, parent_addr);
+                    report(ParseError::InvalidToken(diagnostic(expected)))?;
+                    let top_is_key = match parent_stack.last() {
+                        Some(&addr) => is_key_node(&self.ast, addr),
+                        None => false,
+                    };
+                    match (expect, tok) {
+                        // only the first problem past the top level value is worth reporting
+                        (Expect::End, _) => break,
+                        // missing `:`, assume it was there
+                        (Expect::Colon, JsToken::Comma) | (Expect::Colon, JsToken::Close(_)) => {
+                            let ast = &mut self.ast;
+                            let addr = Self::parse_allocate(ast, Some(missing), &mut first_alloc);
+                            Self::attach_value(ast, &mut parent_stack, addr);
+                            expect = Expect::CommaOrClose;
+                            continue;
+                        }
+                        (Expect::Colon, _) => {
+                            expect = Expect::Value;
+                            continue;
+                        }
+                        // missing `,` between two values or properties
+                        (Expect::CommaOrClose, JsToken::Colon) => (),
+                        (Expect::CommaOrClose, _) => {
+                            let in_object = self.ast[parent_addr].data == Some(JsToken::Open('{'));
+                            expect = if in_object { Expect::Key } else { Expect::Value };
+                            continue;
+                        }
+                        // missing value
+                        (_, JsToken::Comma) | (_, JsToken::Close(_)) if parent_addr != NULL => {
+                            // a trailing comma doesn't get an invented array item,
+                            // but a property always needs its value
+                            if top_is_key || tok == JsToken::Comma {
+                                let addr = Self::parse_allocate(
+                                    &mut self.ast,
+                                    Some(missing),
+                                    &mut first_alloc,
+                                );
+                                Self::attach_value(&mut self.ast, &mut parent_stack, addr);
+                            }
+                            expect = Expect::CommaOrClose;
+                            continue;
+                        }
+                        // an unknown JSON5 word, it stands in for a value
+                        (_, JsToken::Identifier { lbound, ubound }) => {
+                            let placeholder = JsToken::Error { lbound, ubound };
+                            let ast = &mut self.ast;
+                            let addr =
+                                Self::parse_allocate(ast, Some(placeholder), &mut first_alloc);
+                            Self::attach_value(ast, &mut parent_stack, addr);
+                            expect = Self::after_value(&parent_stack);
+                        }
+                        _ => (),
                     }
                 }
-                _ => (),
+            }
+            k += 1;
+        }
+
+        let parent_addr = parent_stack.last().copied().unwrap_or(NULL);
+        let expected = Self::expected_text(expect, &self.ast, parent_addr);
+        let diagnostic = Diagnostic::new(
+            raw_text,
+            raw_text.len(),
+            expected,
+            String::from("end of input"),
+        );
+        let err = match expect {
+            Expect::End => return Ok(()),
+            Expect::Value if parent_stack.is_empty() => ParseError::InvalidToken(diagnostic),
+            _ => ParseError::MissingCloseBracket(diagnostic),
+        };
+        if recover == false {
+            return Err(err);
+        }
+        errors.push(err);
+        // a property cut off by the end of the text still gets a value
+        if let Some(&top_addr) = parent_stack.last() {
+            if is_key_node(&self.ast, top_addr) {
+                let missing = JsToken::Error {
+                    lbound: raw_text.len() as u32,
+                    ubound: raw_text.len() as u32,
+                };
+                let addr = Self::parse_allocate(&mut self.ast, Some(missing), &mut first_alloc);
+                Self::attach_value(&mut self.ast, &mut parent_stack, addr);
             }
         }
         Ok(())
     }
 
-    pub fn print_token_stream(&mut self, raw_text: &String) {
+    /// Describes what the PDA wanted to see while in state `expect`.
+    /// `parent_addr` is the innermost open container (or `NULL`), it decides which close bracket is expected
+    fn expected_text(
+        expect: Expect,
+        ast: &NaryTree<JsToken>,
+        parent_addr: NodeAddr,
+    ) -> &'static str {
+        let in_object = parent_addr != NULL && ast[parent_addr].data == Some(JsToken::Open('{'));
+        expect.describe(in_object)
+    }
+
+    /// Adds `addr` to the top of the parent stack.
+    /// If the top is a property name, the property is complete so it gets popped
+    fn attach_value(ast: &mut NaryTree<JsToken>, parent_stack: &mut Vec<NodeAddr>, addr: NodeAddr) {
+        if let Some(&parent_addr) = parent_stack.last() {
+            ast[parent_addr].add_child(addr, parent_addr);
+            if is_key_node(ast, parent_addr) {
+                parent_stack.pop();
+            }
+        }
+    }
+
+    /// # Description
+    /// Checks the key that was just pushed against the keys its object already has
+    /// # Arguments
+    /// - `seen` : decoded name -> (key node, offset of its token) for every key of the object so far
+    /// - `(parent_addr, key_addr)` : the object and the key that was just added to it
+    /// # Returns
+    /// The error to report if `policy` is `Reject` and the key is a duplicate
+    /// # Comments
+    /// - For `FirstWins` the new key is cut out of the object but stays on the parent stack to soak up its value
+    /// - For `LastWins` the new key takes the old one's slot, so the property keeps its original position
+    fn apply_duplicate_policy(
+        ast: &mut NaryTree<JsToken>,
+        seen: &mut HashMap<String, (NodeAddr, usize)>,
+        (parent_addr, key_addr): (NodeAddr, NodeAddr),
+        offset: usize,
+        raw_text: &str,
+        policy: DuplicateKeys,
+    ) -> Option<ParseError> {
+        let tok = ast[key_addr].data.unwrap();
+        let key = decode_key(tok, raw_text);
+        let (first_addr, first_offset) = match seen.get(&key) {
+            Some(&first) => first,
+            None => {
+                seen.insert(key, (key_addr, offset));
+                return None;
+            }
+        };
+        let children = &mut ast[parent_addr].children;
+        match policy {
+            DuplicateKeys::Reject => {
+                let first = Position::locate(raw_text, first_offset);
+                let found = format!(
+                    "{} again (first defined at line {}, column {})",
+                    describe_token(tok, raw_text),
+                    first.line,
+                    first.column
+                );
+                let diag = Diagnostic::new(raw_text, offset, "a unique property name", found);
+                return Some(ParseError::DuplicateKey { diag, first });
+            }
+            DuplicateKeys::FirstWins => {
+                children.pop();
+            }
+            DuplicateKeys::LastWins => {
+                children.pop();
+                let position = children.iter().position(|&addr| addr == first_addr);
+                children[position.unwrap()] = key_addr;
+                seen.insert(key, (key_addr, first_offset));
+            }
+            DuplicateKeys::KeepAll => (),
+        }
+        None
+    }
+
+    /// Adds a property name node to the object on top of the parent stack and pushes it,
+    /// so the next value gets attached to it
+    fn push_key(
+        ast: &mut NaryTree<JsToken>,
+        parent_stack: &mut Vec<NodeAddr>,
+        key: JsToken,
+        first_alloc: &mut bool,
+    ) -> NodeAddr {
+        let parent_addr = *parent_stack.last().unwrap();
+        let child_addr = Self::parse_allocate(ast, Some(key), first_alloc);
+        ast[parent_addr].add_child(child_addr, parent_addr);
+        parent_stack.push(child_addr);
+        child_addr
+    }
+
+    fn after_value(parent_stack: &[NodeAddr]) -> Expect {
+        if parent_stack.is_empty() {
+            Expect::End
+        } else {
+            Expect::CommaOrClose
+        }
+    }
+
+    pub fn print_token_stream(&mut self, raw_text: &str) {
         for tok in self.lexer.get_tok_stream() {
-            if let &JsToken::String { lbound, ubound } = tok {
-                println!("String:{}", &raw_text[1 + lbound as usize..ubound as usize]);
-            } else {
-                println!("tok:{}", tok);
+            match tok {
+                &JsToken::String { lbound, ubound } => {
+                    println!("String:{}", &raw_text[1 + lbound as usize..ubound as usize])
+                }
+                &JsToken::Number { lbound, ubound } => {
+                    println!("Number:{}", &raw_text[lbound as usize..ubound as usize])
+                }
+                &JsToken::Identifier { lbound, ubound } => {
+                    println!("Identifier:{}", &raw_text[lbound as usize..ubound as usize])
+                }
+                _ => println!("tok:{}", tok),
             }
         }
     }
 
-    pub fn print_tree(&self, raw_text: &String) {
+    pub fn print_tree(&self, raw_text: &str) {
         let mut space_stack = String::new();
         self.print_tree_helper(self.ast.root, &mut space_stack, raw_text);
     }
 
-    fn print_tree_helper(&self, root: NodeAddr, space_stack: &mut String, raw_text: &String) {
+    fn print_tree_helper(&self, root: NodeAddr, space_stack: &mut String, raw_text: &str) {
         if root == NULL {
             return;
         }
@@ -205,14 +637,23 @@ impl JsonAst {
         let ast_node = &self.ast[root];
         let token = ast_node.data.as_ref().unwrap();
 
-        if let &JsToken::String { lbound, ubound } = token {
-            println!(
+        match token {
+            &JsToken::String { lbound, ubound } => println!(
                 "{}{:?}",
                 space_stack,
                 &raw_text[1 + lbound as usize..ubound as usize]
-            )
-        } else {
-            println!("{}{}", space_stack, token)
+            ),
+            &JsToken::Number { lbound, ubound } => println!(
+                "{}Number({})",
+                space_stack,
+                &raw_text[lbound as usize..ubound as usize]
+            ),
+            &JsToken::Identifier { lbound, ubound } => println!(
+                "{}{}",
+                space_stack,
+                &raw_text[lbound as usize..ubound as usize]
+            ),
+            _ => println!("{}{}", space_stack, token),
         }
 
         for &children in ast_node.children.iter() {
@@ -236,8 +677,62 @@ impl JsonAst {
     }
 }
 
+/// The decoded text of a property name token
+fn decode_key(tok: JsToken, raw_text: &str) -> String {
+    match tok {
+        JsToken::String { lbound, ubound } => {
+            unescape(&raw_text[1 + lbound as usize..ubound as usize])
+        }
+        JsToken::Identifier { lbound, ubound } => {
+            unescape(&raw_text[lbound as usize..ubound as usize])
+        }
+        _ => String::new(),
+    }
+}
+
+/// Property names are the only nodes on the parent stack that aren't containers
+fn is_key_node(ast: &NaryTree<JsToken>, addr: NodeAddr) -> bool {
+    matches!(ast[addr].data, Some(JsToken::Open(_))) == false
+}
+
+/// `Infinity` and `NaN` with or without a sign, the only JSON5 numbers json can't write
+fn is_non_finite(lexeme: &str) -> bool {
+    let unsigned = lexeme.trim_start_matches(|c| c == '+' || c == '-');
+    unsigned == "Infinity" || unsigned == "NaN"
+}
+
+/// Describes `tok` for a diagnostic's 'found' section
+fn describe_token(tok: JsToken, raw_text: &str) -> String {
+    match tok {
+        JsToken::Open(c) | JsToken::Close(c) => format!("`{}`", c),
+        JsToken::Colon => String::from("`:`"),
+        JsToken::Comma => String::from("`,`"),
+        JsToken::String { lbound, ubound } => {
+            let text = &raw_text[lbound as usize..=ubound as usize];
+            if text.chars().count() > 32 {
+                let prefix: String = text.chars().take(32).collect();
+                format!("string {}...", prefix)
+            } else {
+                format!("string {}", text)
+            }
+        }
+        JsToken::Number { lbound, ubound } => {
+            format!("number `{}`", &raw_text[lbound as usize..ubound as usize])
+        }
+        JsToken::Boolean(val) => format!("`{}`", val),
+        JsToken::Null => String::from("`null`"),
+        JsToken::Identifier { lbound, ubound } => {
+            format!("identifier `{}`", &raw_text[lbound as usize..ubound as usize])
+        }
+        JsToken::Error { lbound, ubound } => {
+            format!("`{}`", &raw_text[lbound as usize..ubound as usize])
+        }
+        JsToken::Unknown => String::from("unknown token"),
+    }
+}
+
 #[allow(dead_code)]
-fn print_token(tok: JsToken, caption: &'static str, raw_text: &String) {
+fn print_token(tok: JsToken, caption: &'static str, raw_text: &str) {
     if let JsToken::String { lbound, ubound } = tok {
         println!(
             "{}={}",
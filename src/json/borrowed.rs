@@ -0,0 +1,182 @@
+use sandboxed_collections::narytree::*;
+use std::borrow::Cow;
+
+use super::{ast::*, lexer::*, number::JsonNumber, value::JsonValue};
+
+/// # Description
+/// A json value that borrows its text from the document it was parsed from.
+/// # Comments
+/// - Strings and property names without escapes are slices of the input, only the ones with escapes
+/// (which have to be decoded) are allocated. Same goes for numbers, only JSON5 numbers that had to be
+/// normalized are allocated
+/// - Meant for hot paths that read a document and throw it away. Use `into_owned()` to get a `JsonValue`
+/// when the value has to outlive the text, or for patching, pointers, schemas and so on
+#[derive(Clone, PartialEq, Debug)]
+pub enum BorrowedValue<'a> {
+    Null,
+    Bool(bool),
+    /// The number exactly as it appeared in the document, always valid RFC 8259 number text
+    Number(Cow<'a, str>),
+    String(Cow<'a, str>),
+    Array(Vec<BorrowedValue<'a>>),
+    Object(Vec<(Cow<'a, str>, BorrowedValue<'a>)>),
+}
+
+impl<'a> BorrowedValue<'a> {
+    /// Lexes and parses `raw_text`, the result borrows from it
+    pub fn parse(raw_text: &'a str) -> Result<Self, ParseError> {
+        Self::parse_with(raw_text, &ParseOptions::default())
+    }
+
+    pub fn parse_with(raw_text: &'a str, options: &ParseOptions) -> Result<Self, ParseError> {
+        let mut ast = JsonAst::new();
+        ast.parse_with(raw_text, options)?;
+        Ok(Self::from_ast(&ast, raw_text))
+    }
+
+    /// # Description
+    /// Converts a parsed `JsonAst` into a `BorrowedValue`
+    /// # Comments
+    /// - Reusing one `JsonAst` across documents saves reallocating the token stream and the tree every time
+    pub fn from_ast(ast: &JsonAst, raw_text: &'a str) -> Self {
+        Self::from_ast_helper(&ast.ast, ast.ast.root, raw_text)
+    }
+
+    fn from_ast_helper(tree: &NaryTree<JsToken>, node: NodeAddr, raw_text: &'a str) -> Self {
+        if node == NULL {
+            return Self::Null;
+        }
+        let ast_node = &tree[node];
+        let token = ast_node.data.unwrap();
+        match token {
+            JsToken::Open('{') => Self::Object(
+                ast_node
+                    .children
+                    .iter()
+                    .map(|&key_node| {
+                        let key = tree[key_node].data.unwrap().decode_text(raw_text);
+                        let value_node = tree[key_node].children.first().copied().unwrap_or(NULL);
+                        (key, Self::from_ast_helper(tree, value_node, raw_text))
+                    })
+                    .collect(),
+            ),
+            JsToken::Open(_) => Self::Array(
+                ast_node
+                    .children
+                    .iter()
+                    .map(|&child| Self::from_ast_helper(tree, child, raw_text))
+                    .collect(),
+            ),
+            JsToken::String { .. } => Self::String(token.decode_text(raw_text)),
+            JsToken::Number { lbound, ubound } => {
+                match JsonNumber::normalize(&raw_text[lbound as usize..ubound as usize]) {
+                    Some(lexeme) => Self::Number(lexeme),
+                    None => Self::Null,
+                }
+            }
+            JsToken::Boolean(val) => Self::Bool(val),
+            _ => Self::Null,
+        }
+    }
+
+    /// Copies everything that's still borrowed, so the value no longer depends on the input text
+    pub fn into_owned(self) -> JsonValue {
+        match self {
+            Self::Null => JsonValue::Null,
+            Self::Bool(val) => JsonValue::Bool(val),
+            Self::Number(lexeme) => match JsonNumber::parse(&lexeme) {
+                Some(number) => JsonValue::Number(number),
+                None => JsonValue::Null,
+            },
+            Self::String(val) => JsonValue::String(val.into_owned()),
+            Self::Array(items) => {
+                JsonValue::Array(items.into_iter().map(Self::into_owned).collect())
+            }
+            Self::Object(props) => JsonValue::Object(
+                props
+                    .into_iter()
+                    .map(|(key, val)| (key.into_owned(), val.into_owned()))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// # Description
+    /// Looks up `key` in an object
+    /// # Returns
+    /// `None` if `self` isn't an object or the key is missing. If the key is duplicated the first one wins.
+    pub fn get(&self, key: &str) -> Option<&BorrowedValue<'a>> {
+        self.as_object()?
+            .iter()
+            .filter(|(k, _)| k.as_ref() == key)
+            .next()
+            .map(|(_, v)| v)
+    }
+
+    pub fn is_null(&self) -> bool {
+        if let Self::Null = self {
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            &Self::Bool(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// The number's text, which is a slice of the input unless it had to be normalized
+    pub fn as_number_str(&self) -> Option<&str> {
+        match self {
+            Self::Number(lexeme) => Some(lexeme.as_ref()),
+            _ => None,
+        }
+    }
+
+    /// `None` if `self` isn't a number or the number doesn't fit
+    pub fn as_i64(&self) -> Option<i64> {
+        JsonNumber::parse(self.as_number_str()?)?.as_i64().ok()
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        JsonNumber::parse(self.as_number_str()?)?.as_u64().ok()
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.as_number_str()?.parse::<f64>() {
+            Ok(val) if val.is_finite() => Some(val),
+            _ => None,
+        }
+    }
+
+    /// The decoded string, borrowed from the input if it had no escapes
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(val) => Some(val.as_ref()),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<BorrowedValue<'a>>> {
+        match self {
+            Self::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&Vec<(Cow<'a, str>, BorrowedValue<'a>)>> {
+        match self {
+            Self::Object(props) => Some(props),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> From<BorrowedValue<'a>> for JsonValue {
+    fn from(value: BorrowedValue<'a>) -> Self {
+        value.into_owned()
+    }
+}
@@ -0,0 +1,107 @@
+use std::{cmp::Ordering, fmt};
+
+use super::{ast::JsonAst, lexer::escape, value::JsonValue};
+
+/// Why a value has no canonical form
+#[derive(Clone, PartialEq, Debug)]
+pub enum CanonicalError {
+    /// JCS numbers are IEEE doubles, `lexeme` is too large to be one
+    NumberOutOfRange { lexeme: String },
+    /// An object has `key` more than once, JCS only covers I-JSON which forbids that
+    DuplicateKey { key: String },
+}
+
+impl fmt::Display for CanonicalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NumberOutOfRange { lexeme } => {
+                write!(f, "{} can't be represented as a double", lexeme)
+            }
+            Self::DuplicateKey { key } => write!(f, "duplicate property {:?}", key),
+        }
+    }
+}
+
+/// property names are ordered by their UTF-16 code units, not by bytes or chars
+fn compare_keys(a: &str, b: &str) -> Ordering {
+    a.encode_utf16().cmp(b.encode_utf16())
+}
+
+impl JsonValue {
+    /// # Description
+    /// Serializes the value following the JSON Canonicalization Scheme (RFC 8785), so two
+    /// equivalent documents always produce the same bytes. Handy for hashing and signing
+    /// # Comments
+    /// - No whitespace, properties sorted by UTF-16 code units, numbers formatted like javascript
+    /// and strings with only the escapes json requires
+    /// - Numbers are rounded to doubles first, e.g. `9007199254740993` comes out as `9007199254740992`
+    /// # Returns
+    /// An error for numbers beyond the range of a double and for objects with duplicate keys
+    pub fn to_canonical_json(&self) -> Result<String, CanonicalError> {
+        let mut out = String::new();
+        self.canonical_helper(&mut out)?;
+        Ok(out)
+    }
+
+    fn canonical_helper(&self, out: &mut String) -> Result<(), CanonicalError> {
+        match self {
+            Self::Null => out.push_str("null"),
+            Self::Bool(true) => out.push_str("true"),
+            Self::Bool(false) => out.push_str("false"),
+            Self::Number(val) => {
+                let text = val.to_ecmascript_string().map_err(|_| {
+                    CanonicalError::NumberOutOfRange {
+                        lexeme: val.as_str().to_string(),
+                    }
+                })?;
+                out.push_str(&text);
+            }
+            Self::String(val) => {
+                out.push('\"');
+                escape(val, out);
+                out.push('\"');
+            }
+            Self::Array(items) => {
+                out.push('[');
+                for (k, item) in items.iter().enumerate() {
+                    if k > 0 {
+                        out.push(',');
+                    }
+                    item.canonical_helper(out)?;
+                }
+                out.push(']');
+            }
+            Self::Object(props) => {
+                let mut sorted: Vec<&(String, JsonValue)> = props.iter().collect();
+                sorted.sort_by(|(a, _), (b, _)| compare_keys(a, b));
+                for pair in sorted.windows(2) {
+                    if pair[0].0 == pair[1].0 {
+                        return Err(CanonicalError::DuplicateKey {
+                            key: pair[0].0.clone(),
+                        });
+                    }
+                }
+
+                out.push('{');
+                for (k, (key, val)) in sorted.into_iter().enumerate() {
+                    if k > 0 {
+                        out.push(',');
+                    }
+                    out.push('\"');
+                    escape(key, out);
+                    out.push_str("\":");
+                    val.canonical_helper(out)?;
+                }
+                out.push('}');
+            }
+        }
+        Ok(())
+    }
+}
+
+impl JsonAst {
+    /// Same as `JsonValue::to_canonical_json()`, `raw_text` is the text the tree was parsed from
+    pub fn to_canonical_json(&self, raw_text: &str) -> Result<String, CanonicalError> {
+        JsonValue::from_ast(self, raw_text).to_canonical_json()
+    }
+}
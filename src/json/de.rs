@@ -0,0 +1,506 @@
+use serde::{
+    de::{
+        self, value::BorrowedStrDeserializer, DeserializeOwned, DeserializeSeed, EnumAccess,
+        IntoDeserializer, MapAccess, SeqAccess, Unexpected, VariantAccess, Visitor,
+    },
+    forward_to_deserialize_any, Deserialize, Deserializer,
+};
+use std::{error, fmt, slice};
+
+use super::{
+    ast::ParseError,
+    number::{JsonNumber, NumberError},
+    value::JsonValue,
+};
+
+/// Everything that can go wrong while converting between rust types and json
+#[derive(Debug)]
+pub enum SerdeError {
+    /// the text wasn't valid json to begin with
+    Parse(ParseError),
+    /// anything reported by serde or by a `Serialize`/`Deserialize` impl
+    Message(String),
+}
+
+impl fmt::Display for SerdeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse(err) => write!(f, "{}", err),
+            Self::Message(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl error::Error for SerdeError {}
+
+impl de::Error for SerdeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Message(msg.to_string())
+    }
+}
+
+impl serde::ser::Error for SerdeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::Message(msg.to_string())
+    }
+}
+
+impl From<ParseError> for SerdeError {
+    fn from(err: ParseError) -> Self {
+        Self::Parse(err)
+    }
+}
+
+/// # Description
+/// Parses `raw_text` and deserializes it into a `T`
+/// # Comments
+/// - The text is parsed into a `JsonValue` first, see `from_value(..)` to skip that step
+pub fn from_str<T: DeserializeOwned>(raw_text: &str) -> Result<T, SerdeError> {
+    let value = JsonValue::parse(raw_text)?;
+    from_value(&value)
+}
+
+/// Deserializes a `T` out of an already parsed value, `T` may borrow strings from `value`
+pub fn from_value<'a, T: Deserialize<'a>>(value: &'a JsonValue) -> Result<T, SerdeError> {
+    T::deserialize(ValueDeserializer { value })
+}
+
+/// A `serde::Deserializer` reading from a `JsonValue`
+pub struct ValueDeserializer<'a> {
+    value: &'a JsonValue,
+}
+
+impl<'a> ValueDeserializer<'a> {
+    pub fn new(value: &'a JsonValue) -> Self {
+        Self { value }
+    }
+}
+
+fn number_error(err: NumberError) -> SerdeError {
+    SerdeError::Message(format!("can't deserialize number: {}", err))
+}
+
+/// hands the visitor the most exact primitive available, which may round the number
+fn visit_number<'de, V: Visitor<'de>>(
+    number: &JsonNumber,
+    visitor: V,
+) -> Result<V::Value, SerdeError> {
+    if let Ok(val) = number.as_u64() {
+        visitor.visit_u64(val)
+    } else if let Ok(val) = number.as_i64() {
+        visitor.visit_i64(val)
+    } else {
+        visitor.visit_f64(number.as_f64().map_err(number_error)?)
+    }
+}
+
+/// whether the primitive `visit_number(..)` picks writes back out as the same lexeme,
+/// `-0`, `1e2` and `1.10` don't
+fn visits_exactly(number: &JsonNumber) -> bool {
+    let lexeme = number.as_str();
+    if let Ok(val) = number.as_u64() {
+        val.to_string() == lexeme
+    } else if let Ok(val) = number.as_i64() {
+        val.to_string() == lexeme
+    } else {
+        number
+            .as_f64()
+            .ok()
+            .and_then(JsonNumber::from_f64)
+            .filter(|float| float == number)
+            .is_some()
+    }
+}
+
+/// typed requests get a primitive even when it rounds, like `1.10` read into an `f64`
+macro_rules! deserialize_number {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+                match self.value {
+                    JsonValue::Number(number) => visit_number(number, visitor),
+                    _ => self.deserialize_any(visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.value {
+            JsonValue::Null => visitor.visit_unit(),
+            JsonValue::Bool(val) => visitor.visit_bool(*val),
+            // a number no primitive reproduces is handed over as its lexeme, wrapped in a
+            // newtype so `JsonValue` can tell it apart from a string
+            JsonValue::Number(number) if visits_exactly(number) => visit_number(number, visitor),
+            JsonValue::Number(number) => {
+                let lexeme = BorrowedStrDeserializer::<SerdeError>::new(number.as_str());
+                visitor.visit_newtype_struct(lexeme)
+            }
+            JsonValue::String(val) => visitor.visit_borrowed_str(val),
+            JsonValue::Array(items) => visitor.visit_seq(SeqDeserializer {
+                item_stream: items.iter(),
+            }),
+            JsonValue::Object(props) => visitor.visit_map(MapDeserializer {
+                prop_stream: props.iter(),
+                value: None,
+            }),
+        }
+    }
+
+    deserialize_number! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_f32 deserialize_f64
+    }
+
+    fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.value {
+            JsonValue::Number(number) => match number.to_decimal_string().map(|val| val.parse()) {
+                Ok(Ok(val)) => visitor.visit_i128(val),
+                _ => Err(de::Error::invalid_value(
+                    Unexpected::Other(number.as_str()),
+                    &visitor,
+                )),
+            },
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.value {
+            JsonValue::Number(number) => match number.to_decimal_string().map(|val| val.parse()) {
+                Ok(Ok(val)) => visitor.visit_u128(val),
+                _ => Err(de::Error::invalid_value(
+                    Unexpected::Other(number.as_str()),
+                    &visitor,
+                )),
+            },
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.value {
+            JsonValue::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// # Comments
+    /// Enums use the same externally tagged layout as serde_json: `"Variant"` for unit variants
+    /// and `{"Variant": ...}` for everything else
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        match self.value {
+            JsonValue::String(variant) => visitor.visit_enum(variant.as_str().into_deserializer()),
+            JsonValue::Object(props) if props.len() == 1 => visitor.visit_enum(EnumDeserializer {
+                variant: &props[0].0,
+                value: &props[0].1,
+            }),
+            _ => Err(de::Error::invalid_type(
+                unexpected(self.value),
+                &"a string or an object with a single property",
+            )),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool char str string
+        bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier
+    }
+}
+
+fn unexpected(value: &JsonValue) -> Unexpected<'_> {
+    match value {
+        JsonValue::Null => Unexpected::Unit,
+        JsonValue::Bool(val) => Unexpected::Bool(*val),
+        JsonValue::Number(number) => Unexpected::Other(number.as_str()),
+        JsonValue::String(val) => Unexpected::Str(val),
+        JsonValue::Array(_) => Unexpected::Seq,
+        JsonValue::Object(_) => Unexpected::Map,
+    }
+}
+
+struct SeqDeserializer<'a> {
+    item_stream: slice::Iter<'a, JsonValue>,
+}
+
+impl<'de> SeqAccess<'de> for SeqDeserializer<'de> {
+    type Error = SerdeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, SerdeError> {
+        match self.item_stream.next() {
+            Some(value) => seed.deserialize(ValueDeserializer { value }).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.item_stream.len())
+    }
+}
+
+struct MapDeserializer<'a> {
+    prop_stream: slice::Iter<'a, (String, JsonValue)>,
+    /// value of the property whose key was just handed out
+    value: Option<&'a JsonValue>,
+}
+
+impl<'de> MapAccess<'de> for MapDeserializer<'de> {
+    type Error = SerdeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, SerdeError> {
+        match self.prop_stream.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(KeyDeserializer { key }).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, SerdeError> {
+        match self.value.take() {
+            Some(value) => seed.deserialize(ValueDeserializer { value }),
+            None => Err(de::Error::custom("value requested before its key")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.prop_stream.len())
+    }
+}
+
+/// Object keys are always strings in json, this lets maps like `HashMap<u32, T>` read them back as numbers
+struct KeyDeserializer<'a> {
+    key: &'a str,
+}
+
+macro_rules! deserialize_numeric_key {
+    ($($method:ident => $visit:ident : $ty:ty),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+                match self.key.parse::<$ty>() {
+                    Ok(val) => visitor.$visit(val),
+                    Err(_) => Err(de::Error::invalid_type(Unexpected::Str(self.key), &visitor)),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for KeyDeserializer<'de> {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_borrowed_str(self.key)
+    }
+
+    deserialize_numeric_key! {
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+        deserialize_bool => visit_bool: bool
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        visitor.visit_enum(BorrowedStrDeserializer::<SerdeError>::new(self.key))
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 f32 f64 char str string bytes byte_buf option unit unit_struct
+        seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct EnumDeserializer<'a> {
+    variant: &'a str,
+    value: &'a JsonValue,
+}
+
+impl<'de> EnumAccess<'de> for EnumDeserializer<'de> {
+    type Error = SerdeError;
+    type Variant = ValueDeserializer<'de>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), SerdeError> {
+        let variant = seed.deserialize(BorrowedStrDeserializer::<SerdeError>::new(self.variant))?;
+        Ok((variant, ValueDeserializer { value: self.value }))
+    }
+}
+
+impl<'de> VariantAccess<'de> for ValueDeserializer<'de> {
+    type Error = SerdeError;
+
+    fn unit_variant(self) -> Result<(), SerdeError> {
+        match self.value {
+            JsonValue::Null => Ok(()),
+            value => Err(de::Error::invalid_type(unexpected(value), &"unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, SerdeError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        self.deserialize_map(visitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(JsonValueVisitor)
+    }
+}
+
+struct JsonValueVisitor;
+
+impl<'de> Visitor<'de> for JsonValueVisitor {
+    type Value = JsonValue;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any json value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<JsonValue, D::Error> {
+        JsonValue::deserialize(deserializer)
+    }
+
+    fn visit_bool<E: de::Error>(self, val: bool) -> Result<JsonValue, E> {
+        Ok(JsonValue::Bool(val))
+    }
+
+    fn visit_i64<E: de::Error>(self, val: i64) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(val.into()))
+    }
+
+    fn visit_u64<E: de::Error>(self, val: u64) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(val.into()))
+    }
+
+    fn visit_i128<E: de::Error>(self, val: i128) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(JsonNumber::parse(&val.to_string()).unwrap()))
+    }
+
+    fn visit_u128<E: de::Error>(self, val: u128) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(JsonNumber::parse(&val.to_string()).unwrap()))
+    }
+
+    fn visit_f64<E: de::Error>(self, val: f64) -> Result<JsonValue, E> {
+        match JsonNumber::from_f64(val) {
+            Some(number) => Ok(JsonValue::Number(number)),
+            None => Err(de::Error::invalid_value(Unexpected::Float(val), &self)),
+        }
+    }
+
+    fn visit_str<E: de::Error>(self, val: &str) -> Result<JsonValue, E> {
+        Ok(JsonValue::String(val.to_string()))
+    }
+
+    /// `ValueDeserializer` wraps the lexemes of numbers it can't hand over as a primitive in a newtype,
+    /// any other newtype is transparent
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<JsonValue, D::Error> {
+        let inner = deserializer.deserialize_any(JsonValueVisitor)?;
+        match inner.as_str().and_then(JsonNumber::parse) {
+            Some(number) => Ok(JsonValue::Number(number)),
+            None => Ok(inner),
+        }
+    }
+
+    fn visit_string<E: de::Error>(self, val: String) -> Result<JsonValue, E> {
+        Ok(JsonValue::String(val))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<JsonValue, A::Error> {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(JsonValue::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<JsonValue, A::Error> {
+        let mut props = Vec::new();
+        while let Some(prop) = map.next_entry()? {
+            props.push(prop);
+        }
+        Ok(JsonValue::Object(props))
+    }
+}
@@ -0,0 +1,298 @@
+use std::{collections::HashSet, fmt};
+
+use super::{
+    patch::PatchOperation,
+    path::values_equal,
+    pointer::push_token,
+    value::JsonValue,
+};
+
+/// # Description
+/// One difference between two json documents, `path` is a JSON Pointer
+/// # Comments
+/// - The paths of a list of changes are meant to be applied in order, like the operations of a json patch.
+/// So after a `Removed` at `/items/1` the element that used to be `/items/2` is referred to as `/items/1`
+/// - `Moved` only shows up when arrays are matched by a key field (see `DiffOptions`) and an element
+/// changed position
+#[derive(Clone, PartialEq, Debug)]
+pub enum Change {
+    Added { path: String, value: JsonValue },
+    Removed { path: String, old: JsonValue },
+    Changed { path: String, old: JsonValue, new: JsonValue },
+    Moved { from: String, path: String },
+}
+
+impl Change {
+    pub fn path(&self) -> &str {
+        match self {
+            Self::Added { path, .. }
+            | Self::Removed { path, .. }
+            | Self::Changed { path, .. }
+            | Self::Moved { path, .. } => path,
+        }
+    }
+
+    /// The json patch operation that makes this change
+    pub fn to_operation(&self) -> PatchOperation {
+        match self {
+            Self::Added { path, value } => PatchOperation::Add {
+                path: path.clone(),
+                value: value.clone(),
+            },
+            Self::Removed { path, .. } => PatchOperation::Remove { path: path.clone() },
+            Self::Changed { path, new, .. } => PatchOperation::Replace {
+                path: path.clone(),
+                value: new.clone(),
+            },
+            Self::Moved { from, path } => PatchOperation::Move {
+                from: from.clone(),
+                path: path.clone(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    /// one line per change, `+` added, `-` removed, `~` changed and `>` moved
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Added { path, value } => write!(f, "+ {:?}: {}", path, value.to_json()),
+            Self::Removed { path, old } => write!(f, "- {:?}: {}", path, old.to_json()),
+            Self::Changed { path, old, new } => {
+                write!(f, "~ {:?}: {} -> {}", path, old.to_json(), new.to_json())
+            }
+            Self::Moved { from, path } => write!(f, "> {:?} -> {:?}", from, path),
+        }
+    }
+}
+
+/// # Description
+/// Tweaks how `diff_with(..)` compares documents
+/// # Comments
+/// - With `array_key` set, arrays whose elements are all objects with a unique value for that property
+/// are compared element by element by that value instead of by position. So inserting an element at the
+/// front of a list of `{"name": ..}` objects is one `Added` instead of a change to every element
+/// - Arrays that don't qualify (a missing or duplicated key, non-object elements) are compared by position
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct DiffOptions {
+    pub array_key: Option<String>,
+}
+
+impl DiffOptions {
+    /// Matches array elements by the `key` property, see the comments on `DiffOptions`
+    pub fn array_key(key: &str) -> Self {
+        Self {
+            array_key: Some(key.to_string()),
+        }
+    }
+}
+
+/// Lists the differences between two documents, arrays are compared by position
+pub fn diff(old: &JsonValue, new: &JsonValue) -> Vec<Change> {
+    diff_with(old, new, &DiffOptions::default())
+}
+
+/// # Description
+/// Lists the differences between `old` and `new`
+/// # Comments
+/// - Numbers compare by value (`1` and `1.0` are equal) and object member order is ignored
+/// - When a property has duplicates only the first one is looked at
+pub fn diff_with(old: &JsonValue, new: &JsonValue, options: &DiffOptions) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let mut path = String::new();
+    diff_helper(old, new, options, &mut path, &mut changes);
+    changes
+}
+
+/// Turns a list of changes into the operations of an RFC 6902 patch that takes `old` to `new`
+pub fn to_patch(changes: &[Change]) -> Vec<PatchOperation> {
+    changes.iter().map(Change::to_operation).collect()
+}
+
+/// `path` is the pointer to `old`/`new`, children are appended to it and then truncated off again
+fn diff_helper(
+    old: &JsonValue,
+    new: &JsonValue,
+    options: &DiffOptions,
+    path: &mut String,
+    changes: &mut Vec<Change>,
+) {
+    match (old, new) {
+        (JsonValue::Object(old_props), JsonValue::Object(new_props)) => {
+            let path_len = path.len();
+            for (k, (key, old_val)) in old_props.iter().enumerate() {
+                if is_first(old_props, k) == false {
+                    continue;
+                }
+                push_token(path, key);
+                match new.get(key) {
+                    Some(new_val) => diff_helper(old_val, new_val, options, path, changes),
+                    None => changes.push(Change::Removed {
+                        path: path.clone(),
+                        old: old_val.clone(),
+                    }),
+                }
+                path.truncate(path_len);
+            }
+            for (k, (key, new_val)) in new_props.iter().enumerate() {
+                if is_first(new_props, k) && old.get(key).is_none() {
+                    push_token(path, key);
+                    changes.push(Change::Added {
+                        path: path.clone(),
+                        value: new_val.clone(),
+                    });
+                    path.truncate(path_len);
+                }
+            }
+        }
+        (JsonValue::Array(old_items), JsonValue::Array(new_items)) => {
+            let keys = options.array_key.as_ref().and_then(|key_name| {
+                Some((
+                    element_keys(old_items, key_name)?,
+                    element_keys(new_items, key_name)?,
+                ))
+            });
+            match keys {
+                Some((old_keys, new_keys)) => diff_keyed_arrays(
+                    (old_items, &old_keys),
+                    (new_items, &new_keys),
+                    options,
+                    path,
+                    changes,
+                ),
+                None => diff_arrays(old_items, new_items, options, path, changes),
+            }
+        }
+        (old, new) => {
+            if values_equal(old, new) == false {
+                changes.push(Change::Changed {
+                    path: path.clone(),
+                    old: old.clone(),
+                    new: new.clone(),
+                });
+            }
+        }
+    }
+}
+
+/// positional comparison, extra elements are removed back to front so the indices stay valid
+fn diff_arrays(
+    old_items: &[JsonValue],
+    new_items: &[JsonValue],
+    options: &DiffOptions,
+    path: &mut String,
+    changes: &mut Vec<Change>,
+) {
+    let path_len = path.len();
+    let element = |path: &mut String, index: usize| {
+        push_token(path, &index.to_string());
+        let pointer = path.clone();
+        path.truncate(path_len);
+        pointer
+    };
+    for (index, (old_val, new_val)) in old_items.iter().zip(new_items.iter()).enumerate() {
+        push_token(path, &index.to_string());
+        diff_helper(old_val, new_val, options, path, changes);
+        path.truncate(path_len);
+    }
+    for index in (new_items.len()..old_items.len()).rev() {
+        changes.push(Change::Removed {
+            path: element(path, index),
+            old: old_items[index].clone(),
+        });
+    }
+    for index in old_items.len()..new_items.len() {
+        changes.push(Change::Added {
+            path: element(path, index),
+            value: new_items[index].clone(),
+        });
+    }
+}
+
+/// # Description
+/// Compares two arrays whose elements have been matched up by `DiffOptions::array_key`
+/// # Comments
+/// - Goes in three steps: remove the elements `new` doesn't have (back to front), then walk `new`
+/// in order moving every surviving element into place or adding the missing ones, and compare each
+/// survivor with its counterpart once it's in its final position
+fn diff_keyed_arrays(
+    (old_items, old_keys): (&[JsonValue], &[String]),
+    (new_items, new_keys): (&[JsonValue], &[String]),
+    options: &DiffOptions,
+    path: &mut String,
+    changes: &mut Vec<Change>,
+) {
+    let path_len = path.len();
+    let element = |path: &mut String, index: usize| {
+        push_token(path, &index.to_string());
+        let pointer = path.clone();
+        path.truncate(path_len);
+        pointer
+    };
+
+    // the array as it looks after the changes pushed so far, as indices into `old_items`
+    let kept: HashSet<&String> = new_keys.iter().collect();
+    let mut current: Vec<Option<usize>> = Vec::with_capacity(new_items.len());
+    for index in (0..old_items.len()).rev() {
+        if kept.contains(&old_keys[index]) == false {
+            changes.push(Change::Removed {
+                path: element(path, index),
+                old: old_items[index].clone(),
+            });
+        }
+    }
+    current.extend(
+        (0..old_items.len())
+            .filter(|&index| kept.contains(&old_keys[index]))
+            .map(Some),
+    );
+
+    for (target, new_val) in new_items.iter().enumerate() {
+        let found = current[target..]
+            .iter()
+            .position(|&slot| slot.map(|index| &old_keys[index]) == Some(&new_keys[target]));
+        match found {
+            Some(offset) => {
+                let from = target + offset;
+                if from != target {
+                    let moved = current.remove(from);
+                    current.insert(target, moved);
+                    changes.push(Change::Moved {
+                        from: element(path, from),
+                        path: element(path, target),
+                    });
+                }
+                let old_val = &old_items[current[target].unwrap()];
+                push_token(path, &target.to_string());
+                diff_helper(old_val, new_val, options, path, changes);
+                path.truncate(path_len);
+            }
+            None => {
+                current.insert(target, None);
+                changes.push(Change::Added {
+                    path: element(path, target),
+                    value: new_val.clone(),
+                });
+            }
+        }
+    }
+}
+
+/// the key of every element, `None` if any element lacks it or two elements share one
+fn element_keys(items: &[JsonValue], key_name: &str) -> Option<Vec<String>> {
+    let mut keys: Vec<String> = Vec::with_capacity(items.len());
+    let mut seen = HashSet::new();
+    for item in items.iter() {
+        let key = item.get(key_name)?;
+        if item.as_object().is_none() || seen.insert(key.to_json()) == false {
+            return None;
+        }
+        keys.push(key.to_json());
+    }
+    Some(keys)
+}
+
+/// whether property `k` is the first one with its name, later duplicates are ignored by the diff
+fn is_first(props: &[(String, JsonValue)], k: usize) -> bool {
+    props[..k].iter().all(|(key, _)| *key != props[k].0)
+}
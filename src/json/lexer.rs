@@ -1,4 +1,6 @@
-use std::fmt;
+use std::{borrow::Cow, fmt};
+
+use crate::limits::{Limit, Limits};
 
 #[derive(Copy, Clone)]
 pub enum LexerState {
@@ -15,9 +17,19 @@ pub enum JsToken {
     Close(char),
     Colon,
     Comma,
+    /// `lbound` is the opening quote and `ubound` the closing quote (both byte offsets)
     String { lbound: u32, ubound: u32 },
-    Number(f32),
+    /// The digits are kept as-is, the number is the byte range `raw_text[lbound..ubound]`.
+    /// Converting is left to `JsonNumber` so nothing gets rounded off
+    Number { lbound: u32, ubound: u32 },
     Boolean(bool),
+    Null,
+    /// An unquoted JSON5 word, the byte range `raw_text[lbound..ubound]`.
+    /// The parser turns it into a literal (`true`, `NaN`, ...) or uses it as a property name
+    Identifier { lbound: u32, ubound: u32 },
+    /// Text the lexer couldn't make sense of, `raw_text[lbound..ubound]`.
+    /// Only `lex_recovering(..)` emits these, they become placeholder nodes in the tree
+    Error { lbound: u32, ubound: u32 },
     Unknown,
 }
 impl JsToken {
@@ -28,6 +40,25 @@ impl JsToken {
             false
         }
     }
+
+    /// # Description
+    /// The decoded text of a string token, or of an unquoted JSON5 property name
+    /// # Returns
+    /// An empty string for any other token
+    /// # Comments
+    /// - `JsonValue` and `BorrowedValue` both decode through here, so they can't disagree
+    pub(crate) fn decode_text<'a>(&self, raw_text: &'a str) -> Cow<'a, str> {
+        match *self {
+            Self::String { lbound, ubound } => {
+                unescape_cow(&raw_text[1 + lbound as usize..ubound as usize])
+            }
+            // unquoted JSON5 key, which may still contain `\u` escapes
+            Self::Identifier { lbound, ubound } => {
+                unescape_cow(&raw_text[lbound as usize..ubound as usize])
+            }
+            _ => Cow::Borrowed(""),
+        }
+    }
 }
 impl fmt::Display for JsToken {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -37,29 +68,174 @@ impl fmt::Display for JsToken {
             Self::Colon => write!(f, "colon"),
             Self::Comma => write!(f, "comma"),
             Self::String { lbound, ubound } => write!(f, "String(l:{},u:{})", lbound, ubound),
-            Self::Number(val) => write!(f, "Number({})", val),
+            Self::Number { lbound, ubound } => write!(f, "Number(l:{},u:{})", lbound, ubound),
             Self::Boolean(val) => write!(f, "Boolean({})", val),
+            Self::Null => write!(f, "null"),
+            Self::Identifier { lbound, ubound } => {
+                write!(f, "Identifier(l:{},u:{})", lbound, ubound)
+            }
+            Self::Error { lbound, ubound } => write!(f, "Error(l:{},u:{})", lbound, ubound),
             Self::Unknown => write!(f, "unknown"),
         }?;
         Ok(())
     }
 }
 
+/// Which flavour of json the lexer and parser accept
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Dialect {
+    /// Plain RFC 8259 json, nothing extra
+    Strict,
+    /// JSON with comments: `//` and `/* */` comments plus trailing commas
+    Jsonc,
+    /// JSON5 (https://spec.json5.org): everything `Jsonc` allows plus unquoted keys, single quoted strings,
+    /// hex numbers, leading/trailing decimal points, `+` signs, `Infinity`, `NaN`, extra escapes and whitespace
+    Json5,
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Self::Strict
+    }
+}
+
+/// A location in the source text
+/// - `offset` : byte offset into the text
+/// - `line`, `column` : both start at 1, `column` is counted in chars
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    /// # Description
+    /// Works out the line and column of the byte `offset` in `raw_text`
+    /// # Comments
+    /// Lines and columns are only needed when something goes wrong, so they are computed on demand
+    /// instead of being tracked for every token
+    pub fn locate(raw_text: &str, offset: usize) -> Self {
+        let offset = offset.min(raw_text.len());
+        let before = raw_text.get(..offset).unwrap_or(raw_text);
+        let line_start = before.rfind('\n').map(|k| k + 1).unwrap_or(0);
+        Self {
+            offset,
+            line: 1 + before.matches('\n').count(),
+            column: 1 + before[line_start..].chars().count(),
+        }
+    }
+}
+
+/// What went wrong, and where
+#[derive(Clone, PartialEq, Debug)]
+pub struct Diagnostic {
+    pub pos: Position,
+    pub expected: String,
+    pub found: String,
+}
+
+impl Diagnostic {
+    pub fn new(raw_text: &str, offset: usize, expected: &str, found: String) -> Self {
+        Self {
+            pos: Position::locate(raw_text, offset),
+            expected: expected.to_string(),
+            found,
+        }
+    }
+
+    /// # Description
+    /// Renders the diagnostic along with the offending line of `raw_text`, for example:
+    /// ```text
+    /// error: expected `:`, found string
+    ///  --> line 2, column 7
+    ///   |
+    /// 2 |   "a" "b"
+    ///   |       ^
+    /// ```
+    pub fn render(&self, raw_text: &str) -> String {
+        let source_line = raw_text.lines().nth(self.pos.line - 1).unwrap_or("");
+        let line_label = self.pos.line.to_string();
+        let gutter = " ".repeat(line_label.len());
+        // tabs are kept so the caret lines up the same way the source line does
+        let padding: String = source_line
+            .chars()
+            .take(self.pos.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        format!(
+            "error: {}\n{}--> line {}, column {}\n{} |\n{} | {}\n{} | {}^\n",
+            self,
+            gutter,
+            self.pos.line,
+            self.pos.column,
+            gutter,
+            line_label,
+            source_line,
+            gutter,
+            padding
+        )
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected {}, found {}", self.expected, self.found)
+    }
+}
+
+#[derive(Debug)]
 pub enum LexerError {
-    InvalidIdentifier(String),
-    InvalidNumber(String),
+    InvalidIdentifier(Diagnostic),
+    InvalidNumber(Diagnostic),
+    InvalidString(Diagnostic),
+    InvalidComment(Diagnostic),
+    /// The document went over one of the `Limits`, `diag` points at where it happened
+    LimitExceeded { limit: Limit, diag: Diagnostic },
+}
+
+impl LexerError {
+    pub fn diagnostic(&self) -> &Diagnostic {
+        match self {
+            Self::InvalidIdentifier(diag)
+            | Self::InvalidNumber(diag)
+            | Self::InvalidString(diag)
+            | Self::InvalidComment(diag)
+            | Self::LimitExceeded { diag, .. } => diag,
+        }
+    }
+}
+
+impl fmt::Display for LexerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let diag = self.diagnostic();
+        write!(
+            f,
+            "{} at line {}, column {}",
+            diag, diag.pos.line, diag.pos.column
+        )
+    }
 }
 
 pub struct JsonLexer {
     token_stream: Vec<JsToken>,
+    token_offsets: Vec<usize>,
     state: LexerState,
+    /// where the token currently being scanned starts, tells `lex_recovering(..)` what to skip
+    token_start: usize,
+    /// how many brackets are open, only tracked to enforce `Limits::max_depth`
+    depth: usize,
 }
 
 impl JsonLexer {
     pub fn new() -> Self {
         Self {
             token_stream: Vec::new(),
+            token_offsets: Vec::new(),
             state: LexerState::Start,
+            token_start: 0,
+            depth: 0,
         }
     }
 
@@ -67,111 +243,272 @@ impl JsonLexer {
         &self.token_stream
     }
 
-    pub fn lex(&mut self, raw_text: &String) -> Result<(), LexerError> {
+    /// byte offset of where each token in `get_tok_stream()` starts
+    pub fn get_tok_offsets(&self) -> &Vec<usize> {
+        &self.token_offsets
+    }
+
+    /// # Description
+    /// Tokenizes `raw_text` according to the RFC 8259 grammar
+    /// # Comments
+    /// - Works on bytes instead of chars. Every structural character in json is ascii so
+    /// this is safe, and it means `lbound`/`ubound` of a string token can be used to slice `raw_text` directly.
+    /// - Every bound/offset the lexer hands out is a byte offset that lands on a char boundary, even when
+    /// the text before it is full of multi-byte characters
+    /// - String tokens are validated here(escapes, control characters) but not decoded, use `unescape(..)` for that.
+    pub fn lex(&mut self, raw_text: &str) -> Result<(), LexerError> {
+        self.lex_with(raw_text, Dialect::Strict, &Limits::none())
+    }
+
+    /// # Description
+    /// Like `lex(..)` but accepts the extra syntax of `dialect`
+    /// # Comments
+    /// - Comments and the extra JSON5 whitespace are skipped here, they never make it into the token stream
+    /// - JSON5 words (`foo`, `true`, `Infinity`, ...) all come out as `Identifier` tokens,
+    /// only the parser knows whether a word is a key or a value
+    /// - `limits` are checked as tokens are produced, the nesting depth is tracked by counting brackets
+    pub fn lex_with(
+        &mut self,
+        raw_text: &str,
+        dialect: Dialect,
+        limits: &Limits,
+    ) -> Result<(), LexerError> {
+        self.token_stream.clear();
+        self.token_offsets.clear();
+        self.depth = 0;
+        self.lex_from(raw_text, dialect, limits, 0)
+    }
+
+    /// # Description
+    /// Like `lex_with(..)` but keeps going after a bad token
+    /// # Returns
+    /// Every error that was found, in the order they appear in `raw_text`
+    /// # Comments
+    /// - The bad text is replaced by a `JsToken::Error` and lexing picks up again right after it.
+    /// A broken string is skipped up to its closing quote (or the end of the line), anything else up to
+    /// the next whitespace or structural character
+    /// - Going over one of the `limits` isn't recovered from, lexing stops right there
+    pub fn lex_recovering(
+        &mut self,
+        raw_text: &str,
+        dialect: Dialect,
+        limits: &Limits,
+    ) -> Vec<LexerError> {
+        self.token_stream.clear();
+        self.token_offsets.clear();
+        self.depth = 0;
+        let mut errors = Vec::new();
+        let mut cursor = 0;
+        while let Err(err) = self.lex_from(raw_text, dialect, limits, cursor) {
+            if let LexerError::LimitExceeded { .. } = err {
+                errors.push(err);
+                break;
+            }
+            let lbound = self.token_start;
+            let ubound = resync_offset(raw_text, lbound, &err);
+            self.token_offsets.push(lbound);
+            self.token_stream.push(JsToken::Error {
+                lbound: lbound as u32,
+                ubound: ubound as u32,
+            });
+            errors.push(err);
+            cursor = ubound;
+        }
+        errors
+    }
+
+    /// lexes `raw_text[cursor..]`, appending to the token stream
+    fn lex_from(
+        &mut self,
+        raw_text: &str,
+        dialect: Dialect,
+        limits: &Limits,
+        mut cursor: usize,
+    ) -> Result<(), LexerError> {
         let token_stream = &mut self.token_stream;
-        let mut char_stream = raw_text.chars().enumerate().peekable();
+        let token_offsets = &mut self.token_offsets;
+        let bytes = raw_text.as_bytes();
+        self.state = LexerState::Start;
 
-        let mut accum: Option<usize> = None;
+        let limit_error = |limit: Limit, offset: usize, expected: &str, found: String| {
+            let diag = Diagnostic::new(raw_text, offset, expected, found);
+            LexerError::LimitExceeded { limit, diag }
+        };
+        if raw_text.len() > limits.max_document_bytes {
+            let expected = format!("at most {} bytes", limits.max_document_bytes);
+            let found = format!("a document of {} bytes", raw_text.len());
+            let offset = limits.max_document_bytes;
+            return Err(limit_error(Limit::DocumentBytes, offset, &expected, found));
+        }
 
+        // tokens before this index went through the limit checks already
+        let mut checked = token_stream.len();
         loop {
-            let cur = char_stream.next();
-            let peek = char_stream.peek().map(|&a| a);
-
+            while checked < token_stream.len() {
+                let offset = token_offsets[checked];
+                if checked >= limits.max_tokens {
+                    let expected = format!("at most {} tokens", limits.max_tokens);
+                    let found = describe_text_at(raw_text, offset);
+                    return Err(limit_error(Limit::Tokens, offset, &expected, found));
+                }
+                let len = match token_stream[checked] {
+                    JsToken::Open(_) => {
+                        self.depth += 1;
+                        0
+                    }
+                    JsToken::Close(_) => {
+                        self.depth = self.depth.saturating_sub(1);
+                        0
+                    }
+                    JsToken::String { lbound, ubound } => (ubound - lbound - 1) as usize,
+                    JsToken::Number { lbound, ubound } | JsToken::Identifier { lbound, ubound } => {
+                        (ubound - lbound) as usize
+                    }
+                    _ => 0,
+                };
+                if self.depth > limits.max_depth {
+                    let expected = format!("at most {} levels of nesting", limits.max_depth);
+                    let found = describe_text_at(raw_text, offset);
+                    return Err(limit_error(Limit::Depth, offset, &expected, found));
+                }
+                if len > limits.max_string_len {
+                    let expected = format!("tokens of at most {} bytes", limits.max_string_len);
+                    let found = format!("a token of {} bytes", len);
+                    return Err(limit_error(Limit::StringLength, offset, &expected, found));
+                }
+                checked += 1;
+            }
+            self.token_start = cursor;
             let state = self.state;
-
             match state {
-                LexerState::Start => match cur {
-                    Some((k, c)) => {
-                        let token = match c {
-                            '{' => JsToken::Open('{'),
-                            '[' => JsToken::Open('['),
-                            '}' => JsToken::Close('}'),
-                            ']' => JsToken::Close(']'),
-                            ':' => JsToken::Colon,
-                            ',' => JsToken::Comma,
-                            't' | 'f' => {
-                                if let Some(val) = Self::is_boolean(char_stream.clone()) {
-                                    let ignore_count = if c == 't' { 3 } else { 4 };
-                                    Self::flush_chars(&mut char_stream, ignore_count);
-                                    JsToken::Boolean(val)
-                                } else {
-                                    let err = format!("starts at pos ={}", k);
-                                    return Err(LexerError::InvalidIdentifier(err));
-                                }
-                            }
-                            '0'..='9' | '.' | '+' | '-' => {
-                                accum = Some(k);
-                                self.state = LexerState::Numeric;
-                                JsToken::Unknown
+                LexerState::Start => {
+                    let c = match bytes.get(cursor) {
+                        Some(&c) => c,
+                        None => {
+                            self.state = LexerState::Finished;
+                            continue;
+                        }
+                    };
+                    let json5 = dialect == Dialect::Json5;
+                    let token = match c {
+                        b'{' => JsToken::Open('{'),
+                        b'[' => JsToken::Open('['),
+                        b'}' => JsToken::Close('}'),
+                        b']' => JsToken::Close(']'),
+                        b':' => JsToken::Colon,
+                        b',' => JsToken::Comma,
+                        b' ' | b'\t' | b'\n' | b'\r' => JsToken::Unknown,
+                        b'\x0B' | b'\x0C' if json5 => JsToken::Unknown,
+                        b'/' if dialect != Dialect::Strict => {
+                            cursor = Self::skip_comment(raw_text, cursor)?;
+                            continue;
+                        }
+                        b'\'' if json5 => {
+                            self.state = LexerState::String;
+                            continue;
+                        }
+                        b'+' | b'.' if json5 => {
+                            self.state = LexerState::Numeric;
+                            continue;
+                        }
+                        c if json5
+                            && (c.is_ascii_alphabetic() || c >= 0x80 || b"$_\\".contains(&c)) =>
+                        {
+                            let ch = raw_text[cursor..].chars().next().unwrap();
+                            if is_json5_whitespace(ch) {
+                                cursor += ch.len_utf8();
+                                continue;
                             }
-                            '\"' => {
-                                accum = Some(k);
-                                self.state = LexerState::String;
-                                JsToken::Unknown
+                            let ubound = match scan_identifier(raw_text, cursor) {
+                                Some(ubound) => ubound,
+                                None => {
+                                    let found = describe_text_at(raw_text, cursor);
+                                    let diag = Diagnostic::new(raw_text, cursor, "a value", found);
+                                    return Err(LexerError::InvalidIdentifier(diag));
+                                }
+                            };
+                            token_offsets.push(cursor);
+                            token_stream.push(JsToken::Identifier {
+                                lbound: cursor as u32,
+                                ubound: ubound as u32,
+                            });
+                            cursor = ubound;
+                            continue;
+                        }
+                        b't' | b'f' | b'n' => {
+                            let (literal, token) = match c {
+                                b't' => ("true", JsToken::Boolean(true)),
+                                b'f' => ("false", JsToken::Boolean(false)),
+                                _ => ("null", JsToken::Null),
+                            };
+                            if Self::is_literal(&bytes[cursor..], literal) == false {
+                                let found = describe_text_at(raw_text, cursor);
+                                let diag = Diagnostic::new(raw_text, cursor, "a value", found);
+                                return Err(LexerError::InvalidIdentifier(diag));
                             }
-                            _ => JsToken::Unknown,
-                        };
-                        if token.is_unknown() == false {
+                            token_offsets.push(cursor);
                             token_stream.push(token);
+                            cursor += literal.len();
+                            continue;
                         }
-                        if cur.is_some() {
-                            let (_, c) = cur.unwrap();
-                            if c.is_alphabetic() && c != 't' && c != 'f' {
-                                let err = format!("starts at pos ={}", k);
-                                return Err(LexerError::InvalidIdentifier(err));
-                            }
-                        } else {
-                            self.state = LexerState::Finished;
+                        b'-' | b'0'..=b'9' => {
+                            self.state = LexerState::Numeric;
+                            continue;
+                        }
+                        b'\"' => {
+                            self.state = LexerState::String;
+                            continue;
+                        }
+                        _ => {
+                            let found = describe_text_at(raw_text, cursor);
+                            let diag = Diagnostic::new(raw_text, cursor, "a value", found);
+                            return Err(LexerError::InvalidIdentifier(diag));
                         }
+                    };
+                    if token.is_unknown() == false {
+                        token_offsets.push(cursor);
+                        token_stream.push(token);
                     }
-                    _ => self.state = LexerState::Finished,
-                },
+                    cursor += 1;
+                }
                 LexerState::String => {
-                    if cur.is_none() {
-                        self.state = LexerState::Finished;
-                    }
-                    if let (Some((_, '\\')), Some((_, '\"'))) = (cur, peek) {
-                        Self::flush_chars(&mut char_stream, 1);
-                    }
-                    if let Some((k, '\"')) = cur {
-                        token_stream.push(JsToken::String {
-                            lbound: accum.unwrap() as u32,
-                            ubound: k as u32,
-                        });
-                        accum = None;
-                        self.state = LexerState::Start;
-                    }
+                    let lbound = cursor;
+                    let ubound = Self::scan_string(raw_text, lbound, dialect)?;
+                    token_offsets.push(lbound);
+                    token_stream.push(JsToken::String {
+                        lbound: lbound as u32,
+                        ubound: ubound as u32,
+                    });
+                    cursor = ubound + 1;
+                    self.state = LexerState::Start;
                 }
                 LexerState::Numeric => {
-                    if cur.is_none() {
-                        self.state = LexerState::Finished;
-                    }
-
-                    if let Some((ubound, c)) = cur {
-                        if c == ',' || c == ']' || c == '}' {
-                            let lbound = accum.unwrap();
-                            let num_slice = &raw_text[lbound..ubound];
-                            let num_res = num_slice.trim().parse::<f32>();
-                            if let Ok(num) = num_res {
-                                token_stream.push(JsToken::Number(num));
-
-                                let tok = match c {
-                                    ',' => JsToken::Comma,
-                                    ']' => JsToken::Close(']'),
-                                    '}' => JsToken::Close('}'),
-                                    _ => JsToken::Unknown,
-                                };
-
-                                token_stream.push(tok);
-
-                                self.state = LexerState::Start;
-                                accum = None;
-                            } else {
-                                let err = format!("at pos = {}", num_slice);
-                                return Err(LexerError::InvalidNumber(err));
-                            }
+                    let lbound = cursor;
+                    let scanned = if dialect == Dialect::Json5 {
+                        scan_json5_number(bytes, lbound)
+                    } else {
+                        scan_number(bytes, lbound)
+                    };
+                    // `01` or `1x` isn't a number followed by something else, the whole run is one bad number
+                    let run_on = |ubound: usize| match bytes.get(ubound) {
+                        Some(&c) => c.is_ascii_alphanumeric() || c == b'.',
+                        None => false,
+                    };
+                    let ubound = match scanned {
+                        Some(ubound) if run_on(ubound) == false => ubound,
+                        _ => {
+                            let found = describe_text_at(raw_text, lbound);
+                            let diag = Diagnostic::new(raw_text, lbound, "a number", found);
+                            return Err(LexerError::InvalidNumber(diag));
                         }
-                    }
+                    };
+                    token_offsets.push(lbound);
+                    token_stream.push(JsToken::Number {
+                        lbound: lbound as u32,
+                        ubound: ubound as u32,
+                    });
+                    cursor = ubound;
+                    self.state = LexerState::Start;
                 }
                 LexerState::Finished => {
                     break;
@@ -181,35 +518,463 @@ impl JsonLexer {
         Ok(())
     }
 
-    fn flush_chars(mut stream: impl Iterator<Item = (usize, char)>, num_chars: u32) {
-        for _ in 0..num_chars {
-            stream.next();
+    fn is_literal(stream: &[u8], literal: &str) -> bool {
+        stream.starts_with(literal.as_bytes())
+    }
+
+    /// scans a string starting at the opening quote located at `lbound`
+    /// returns the position of the closing quote
+    /// # Comments
+    /// - In JSON5 mode the string may be single quoted, may hold unescaped control characters
+    /// (except line breaks) and accepts the extra JSON5 escapes
+    fn scan_string(raw_text: &str, lbound: usize, dialect: Dialect) -> Result<usize, LexerError> {
+        let bytes = raw_text.as_bytes();
+        let string_error = |offset: usize, expected: &str, found: String| {
+            LexerError::InvalidString(Diagnostic::new(raw_text, offset, expected, found))
+        };
+        let quote = bytes[lbound];
+        let close_text = if quote == b'\'' {
+            "`'` to close the string"
+        } else {
+            "`\"` to close the string"
+        };
+
+        let mut cursor = lbound + 1;
+        loop {
+            match bytes.get(cursor) {
+                Some(&c) if c == quote => return Ok(cursor),
+                Some(b'\\') if dialect == Dialect::Json5 => {
+                    cursor += Self::json5_escape_len(raw_text, cursor)?;
+                }
+                Some(b'\n') | Some(b'\r') if dialect == Dialect::Json5 => {
+                    let found = String::from("a line break, end the line with `\\` to continue");
+                    return Err(string_error(cursor, close_text, found));
+                }
+                Some(_) if dialect == Dialect::Json5 => cursor += 1,
+                Some(b'\\') => {
+                    let escape_len = match bytes.get(cursor + 1) {
+                        Some(b'\"') | Some(b'\\') | Some(b'/') | Some(b'b') | Some(b'f')
+                        | Some(b'n') | Some(b'r') | Some(b't') => 2,
+                        Some(b'u') => {
+                            let hex = bytes.get(cursor + 2..cursor + 6).unwrap_or(&[]);
+                            if hex.len() == 4 && hex.iter().all(u8::is_ascii_hexdigit) {
+                                6
+                            } else {
+                                let found = describe_text_at(raw_text, cursor);
+                                return Err(string_error(
+                                    cursor,
+                                    "four hex digits after `\\u`",
+                                    found,
+                                ));
+                            }
+                        }
+                        _ => {
+                            let found = describe_text_at(raw_text, cursor);
+                            return Err(string_error(cursor, "a valid escape sequence", found));
+                        }
+                    };
+                    cursor += escape_len;
+                }
+                Some(&c) if c < 0x20 => {
+                    let found = format!("unescaped control character U+{:04X}", c);
+                    return Err(string_error(cursor, close_text, found));
+                }
+                Some(_) => cursor += 1,
+                None => {
+                    let found = String::from("end of input");
+                    return Err(string_error(lbound, close_text, found));
+                }
+            }
+        }
+    }
+
+    /// length in bytes of the JSON5 escape sequence starting at the backslash at `cursor`
+    fn json5_escape_len(raw_text: &str, cursor: usize) -> Result<usize, LexerError> {
+        let bytes = raw_text.as_bytes();
+        let hex_run = |len: usize| {
+            let hex = bytes.get(cursor + 2..cursor + 2 + len).unwrap_or(&[]);
+            hex.len() == len && hex.iter().all(u8::is_ascii_hexdigit)
+        };
+        let escape_error = |expected: &str| {
+            let found = describe_text_at(raw_text, cursor);
+            LexerError::InvalidString(Diagnostic::new(raw_text, cursor, expected, found))
+        };
+        match bytes.get(cursor + 1) {
+            Some(b'x') if hex_run(2) => Ok(4),
+            Some(b'x') => Err(escape_error("two hex digits after `\\x`")),
+            Some(b'u') if hex_run(4) => Ok(6),
+            Some(b'u') => Err(escape_error("four hex digits after `\\u`")),
+            Some(b'0') if matches!(bytes.get(cursor + 2), Some(b'0'..=b'9')) == false => Ok(2),
+            Some(b'0'..=b'9') => Err(escape_error("a valid escape sequence")),
+            // line continuation, `\r\n` counts as a single line break
+            Some(b'\r') if bytes.get(cursor + 2) == Some(&b'\n') => Ok(3),
+            Some(_) => {
+                // any other character escapes to itself, which might be multi-byte
+                let escaped = raw_text[cursor + 1..].chars().next().unwrap();
+                Ok(1 + escaped.len_utf8())
+            }
+            None => Err(escape_error("a valid escape sequence")),
         }
     }
 
-    fn is_boolean<It>(stream: It) -> Option<bool>
-    where
-        It: Iterator<Item = (usize, char)> + Clone,
-    {
-        let case1 = "true";
-        let case2 = "false";
+    /// skips the comment starting at `cursor`, returns the offset right after it
+    fn skip_comment(raw_text: &str, cursor: usize) -> Result<usize, LexerError> {
+        let bytes = raw_text.as_bytes();
+        match bytes.get(cursor + 1) {
+            Some(b'/') => {
+                let len = raw_text[cursor..]
+                    .find(['\n', '\r'])
+                    .unwrap_or(raw_text.len() - cursor);
+                Ok(cursor + len)
+            }
+            Some(b'*') => match raw_text[cursor + 2..].find("*/") {
+                Some(len) => Ok(cursor + 2 + len + 2),
+                None => {
+                    let found = String::from("end of input");
+                    let expected = "`*/` to close the comment";
+                    let diag = Diagnostic::new(raw_text, cursor, expected, found);
+                    Err(LexerError::InvalidComment(diag))
+                }
+            },
+            _ => {
+                let found = describe_text_at(raw_text, cursor);
+                let diag = Diagnostic::new(raw_text, cursor, "`//` or `/*`", found);
+                Err(LexerError::InvalidComment(diag))
+            }
+        }
+    }
+}
 
-        let true_detected = case1[1..]
-            .chars()
-            .zip(stream.clone())
-            .all(|(a, (_, b))| a == b);
+/// # Description
+/// Describes the text at `offset` for a diagnostic's 'found' section
+/// # Comments
+/// - Grabs the whole word if the text starts with an identifier-like character, so the user sees `nul` instead of just `n`
+fn describe_text_at(raw_text: &str, offset: usize) -> String {
+    let rest = match raw_text.get(offset..) {
+        Some(rest) if rest.is_empty() == false => rest,
+        _ => return String::from("end of input"),
+    };
+    let first = rest.chars().next().unwrap();
+    let word: String = if first == '\\' {
+        rest.chars().take(2).collect()
+    } else if first.is_alphanumeric() || first == '-' || first == '.' {
+        rest.chars()
+            .take_while(|&c| c.is_alphanumeric() || c == '-' || c == '+' || c == '.')
+            .take(16)
+            .collect()
+    } else {
+        first.to_string()
+    };
+    let printable: String = word
+        .chars()
+        .map(|c| {
+            if c.is_control() {
+                c.escape_debug().to_string()
+            } else {
+                c.to_string()
+            }
+        })
+        .collect();
+    format!("`{}`", printable)
+}
 
-        let false_detected = case2[1..]
-            .chars()
-            .zip(stream.clone())
-            .all(|(a, (_, b))| a == b);
+/// # Description
+/// Runs the RFC 8259 number grammar over `bytes` starting at `lbound`:\
+/// `-? (0 | [1-9][0-9]*) (\.[0-9]+)? ([eE][+-]?[0-9]+)?`
+/// # Returns
+/// One past the last byte of the number, or `None` if the text at `lbound` isn't a number
+pub fn scan_number(bytes: &[u8], lbound: usize) -> Option<usize> {
+    let digits_from = |mut cursor: usize| -> Option<usize> {
+        let start = cursor;
+        while let Some(b'0'..=b'9') = bytes.get(cursor) {
+            cursor += 1;
+        }
+        if cursor > start {
+            Some(cursor)
+        } else {
+            None
+        }
+    };
+
+    let mut cursor = lbound;
+    if let Some(b'-') = bytes.get(cursor) {
+        cursor += 1;
+    }
+
+    cursor = match bytes.get(cursor) {
+        Some(b'0') => cursor + 1,
+        Some(b'1'..=b'9') => digits_from(cursor)?,
+        _ => return None,
+    };
+
+    if let Some(b'.') = bytes.get(cursor) {
+        cursor = digits_from(cursor + 1)?;
+    }
+
+    if let Some(b'e') | Some(b'E') = bytes.get(cursor) {
+        cursor += 1;
+        if let Some(b'+') | Some(b'-') = bytes.get(cursor) {
+            cursor += 1;
+        }
+        cursor = digits_from(cursor)?;
+    }
+
+    Some(cursor)
+}
+
+/// # Description
+/// Works out where lexing should pick up again after `err`, which was hit in the token starting at `lbound`
+/// # Returns
+/// An offset past `lbound` that lands on a char boundary
+fn resync_offset(raw_text: &str, lbound: usize, err: &LexerError) -> usize {
+    let bytes = raw_text.as_bytes();
+    let rest = &raw_text[lbound..];
+    match err {
+        LexerError::InvalidString(_) => {
+            // every byte checked here is ascii, so the cursor only ever stops on a char boundary
+            let quote = bytes[lbound];
+            let mut cursor = lbound + 1;
+            while cursor < bytes.len() {
+                match bytes[cursor] {
+                    b'\\' => cursor += 2,
+                    b'\n' | b'\r' => return cursor,
+                    c if c == quote => return cursor + 1,
+                    _ => cursor += 1,
+                }
+            }
+            raw_text.len()
+        }
+        // an unterminated block comment swallows the rest of the text
+        LexerError::InvalidComment(_) if rest.starts_with("/*") => raw_text.len(),
+        _ => {
+            let first_len = rest.chars().next().map_or(1, char::len_utf8);
+            let stop = rest[first_len..]
+                .find(|c: char| c.is_whitespace() || "{}[]:,\"".contains(c))
+                .unwrap_or(rest.len() - first_len);
+            lbound + first_len + stop
+        }
+    }
+}
+
+/// Whether all of `lexeme` is a RFC 8259 number, cheaper than `JsonNumber::parse(..)` since nothing is allocated
+pub fn is_json_number(lexeme: &str) -> bool {
+    scan_number(lexeme.as_bytes(), 0) == Some(lexeme.len())
+}
 
-        if true_detected {
-            Some(true)
-        } else if false_detected {
-            Some(false)
+/// # Description
+/// The JSON5 version of `scan_number(..)`, which also allows:
+/// - a leading `+`
+/// - hex integers like `0x1F`
+/// - a leading or trailing decimal point like `.5` or `5.`
+/// - `Infinity` and `NaN` with a sign in front (without one they are lexed as identifiers)
+pub fn scan_json5_number(bytes: &[u8], lbound: usize) -> Option<usize> {
+    let digits_from = |mut cursor: usize| -> Option<usize> {
+        let start = cursor;
+        while let Some(b'0'..=b'9') = bytes.get(cursor) {
+            cursor += 1;
+        }
+        if cursor > start {
+            Some(cursor)
         } else {
             None
         }
+    };
+
+    let mut cursor = lbound;
+    if let Some(b'+') | Some(b'-') = bytes.get(cursor) {
+        cursor += 1;
+    }
+    let rest = &bytes[cursor..];
+    if rest.starts_with(b"Infinity") {
+        return Some(cursor + 8);
+    }
+    if rest.starts_with(b"NaN") {
+        return Some(cursor + 3);
+    }
+    if let (Some(b'0'), Some(b'x')) | (Some(b'0'), Some(b'X')) = (rest.first(), rest.get(1)) {
+        let start = cursor + 2;
+        cursor = start;
+        while let Some(b'0'..=b'9') | Some(b'a'..=b'f') | Some(b'A'..=b'F') = bytes.get(cursor) {
+            cursor += 1;
+        }
+        return if cursor > start { Some(cursor) } else { None };
+    }
+
+    cursor = match bytes.get(cursor) {
+        Some(b'0') => cursor + 1,
+        Some(b'1'..=b'9') => digits_from(cursor)?,
+        // no integer part, so the fraction must have digits
+        Some(b'.') if digits_from(cursor + 1).is_some() => cursor,
+        _ => return None,
+    };
+    if let Some(b'.') = bytes.get(cursor) {
+        cursor = digits_from(cursor + 1).unwrap_or(cursor + 1);
+    }
+
+    if let Some(b'e') | Some(b'E') = bytes.get(cursor) {
+        cursor += 1;
+        if let Some(b'+') | Some(b'-') = bytes.get(cursor) {
+            cursor += 1;
+        }
+        cursor = digits_from(cursor)?;
+    }
+
+    Some(cursor)
+}
+
+/// # Description
+/// Scans a JSON5 identifier (an ECMAScript IdentifierName) starting at `lbound`
+/// # Returns
+/// One past the last byte of the identifier, or `None` if there isn't one at `lbound`
+/// # Comments
+/// - Letters, digits, `$`, `_` and `\uXXXX` escapes are accepted. That's a slight superset of
+/// the spec which is stricter about which unicode categories may appear
+fn scan_identifier(raw_text: &str, lbound: usize) -> Option<usize> {
+    let mut cursor = lbound;
+    loop {
+        let rest = &raw_text[cursor..];
+        let c = match rest.chars().next() {
+            Some('\\') => {
+                let bytes = rest.as_bytes();
+                let hex = bytes.get(2..6).unwrap_or(&[]);
+                let is_hex = hex.len() == 4 && hex.iter().all(u8::is_ascii_hexdigit);
+                if bytes.get(1) != Some(&b'u') || is_hex == false {
+                    return None;
+                }
+                let code = u32::from_str_radix(&rest[2..6], 16).unwrap();
+                let escaped = std::char::from_u32(code)?;
+                if is_identifier_char(escaped, cursor == lbound) == false {
+                    return None;
+                }
+                cursor += 6;
+                continue;
+            }
+            Some(c) => c,
+            None => break,
+        };
+        if is_identifier_char(c, cursor == lbound) == false {
+            break;
+        }
+        cursor += c.len_utf8();
+    }
+    if cursor > lbound {
+        Some(cursor)
+    } else {
+        None
+    }
+}
+
+fn is_identifier_char(c: char, first: bool) -> bool {
+    let start = c.is_alphabetic() || c == '$' || c == '_';
+    if first {
+        start
+    } else {
+        start || c.is_alphanumeric() || c == '\u{200C}' || c == '\u{200D}'
+    }
+}
+
+/// whitespace JSON5 allows on top of the usual four, includes the byte order mark and line/paragraph separators
+fn is_json5_whitespace(c: char) -> bool {
+    c == '\u{FEFF}' || (c as u32 >= 0x80 && c.is_whitespace())
+}
+
+/// # Description
+/// Like `unescape(..)` but hands back `raw` itself when there's nothing to decode
+/// # Comments
+/// - Most strings in real documents have no escapes, so this avoids an allocation per string
+pub fn unescape_cow(raw: &str) -> Cow<'_, str> {
+    if raw.contains('\\') {
+        Cow::Owned(unescape(raw))
+    } else {
+        Cow::Borrowed(raw)
+    }
+}
+
+/// # Description
+/// Decodes the body of a json string (the text between the quotes).
+/// # Comments
+/// - Assumes `raw` has already been validated by the lexer
+/// - Lone surrogates can't be stored in a rust `String` so they are replaced with `U+FFFD`
+/// - Also understands the JSON5 escapes (`\v`, `\0`, `\xHH`, line continuations). Strict json never
+/// contains those since the lexer rejects them
+pub fn unescape(raw: &str) -> String {
+    let mut decoded = String::with_capacity(raw.len());
+    let mut char_stream = raw.chars();
+
+    while let Some(c) = char_stream.next() {
+        if c != '\\' {
+            decoded.push(c);
+            continue;
+        }
+        let escaped = match char_stream.next() {
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('v') => '\u{b}',
+            Some('0') => '\0',
+            Some('x') => std::char::from_u32(read_hex(&mut char_stream, 2)).unwrap_or('\u{FFFD}'),
+            Some('\r') => {
+                // line continuation, swallow the `\n` of a `\r\n` as well
+                let mut lookahead = char_stream.clone();
+                if lookahead.next() == Some('\n') {
+                    char_stream = lookahead;
+                }
+                continue;
+            }
+            Some('\n') | Some('\u{2028}') | Some('\u{2029}') => continue,
+            Some('u') => {
+                let high = read_hex(&mut char_stream, 4);
+                if (0xD800..0xDC00).contains(&high) {
+                    // high surrogate, only valid if a low surrogate escape comes right after
+                    let mut lookahead = char_stream.clone();
+                    let low = match (lookahead.next(), lookahead.next()) {
+                        (Some('\\'), Some('u')) => Some(read_hex(&mut lookahead, 4)),
+                        _ => None,
+                    };
+                    match low {
+                        Some(low) if (0xDC00..0xE000).contains(&low) => {
+                            char_stream = lookahead;
+                            let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                            std::char::from_u32(code).unwrap_or('\u{FFFD}')
+                        }
+                        _ => '\u{FFFD}',
+                    }
+                } else {
+                    std::char::from_u32(high).unwrap_or('\u{FFFD}')
+                }
+            }
+            Some(other) => other,
+            None => break,
+        };
+        decoded.push(escaped);
     }
+    decoded
+}
+
+/// # Description
+/// The inverse of `unescape(..)`, writes `raw` into `out` escaped so that it can sit between two quotes
+/// # Comments
+/// - Only `"`, `\\` and control characters get escaped, everything else is written as-is
+pub fn escape(raw: &str, out: &mut String) {
+    for c in raw.chars() {
+        match c {
+            '\"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+}
+
+fn read_hex(char_stream: &mut impl Iterator<Item = char>, len: usize) -> u32 {
+    char_stream
+        .take(len)
+        .fold(0, |acc, c| (acc << 4) | c.to_digit(16).unwrap_or(0))
 }
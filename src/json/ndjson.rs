@@ -0,0 +1,207 @@
+use std::{fmt, io};
+
+use super::{ast::*, value::JsonValue};
+
+/// What `NdjsonReader` does when a line isn't valid json
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum BadLinePolicy {
+    /// Yield the error and end the iteration
+    Stop,
+    /// Remember the line number (see `skipped_lines()`) and carry on with the next line
+    Skip,
+}
+
+impl Default for BadLinePolicy {
+    fn default() -> Self {
+        Self::Stop
+    }
+}
+
+/// # Description
+/// Why a line of a JSON Lines stream couldn't be read
+/// # Comments
+/// - `line` starts at 1. The position inside a `Parse` error's diagnostic is relative to
+/// that line, so its `line` is always 1 and its `column` is the column within the line
+#[derive(Debug)]
+pub enum NdjsonError {
+    Io { line: usize, error: io::Error },
+    InvalidUtf8 { line: usize },
+    Parse { line: usize, error: ParseError },
+}
+
+impl NdjsonError {
+    pub fn line(&self) -> usize {
+        match self {
+            Self::Io { line, .. } | Self::InvalidUtf8 { line } | Self::Parse { line, .. } => *line,
+        }
+    }
+}
+
+impl fmt::Display for NdjsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { line, error } => write!(f, "io error on line {}: {}", line, error),
+            Self::InvalidUtf8 { line } => write!(f, "line {} isn't valid utf-8", line),
+            Self::Parse { line, error } => {
+                let diag = error.diagnostic();
+                write!(f, "{} on line {}, column {}", diag, line, diag.pos.column)
+            }
+        }
+    }
+}
+
+/// # Description
+/// Reads newline-delimited json (JSON Lines/NDJSON), yielding one `JsonValue` per line
+/// # Comments
+/// - Blank lines (and lines that are only whitespace) are ignored, `\r\n` line endings are fine
+/// - One `JsonAst` is reused for every line, so there's no per-line allocation of a new tree
+/// - Io errors always end the iteration, whatever the `BadLinePolicy` says
+pub struct NdjsonReader<R> {
+    reader: R,
+    policy: BadLinePolicy,
+    options: ParseOptions,
+    ast: JsonAst,
+    buffer: Vec<u8>,
+    line: usize,
+    skipped: Vec<usize>,
+    done: bool,
+}
+
+impl<R: io::BufRead> NdjsonReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            policy: BadLinePolicy::default(),
+            options: ParseOptions::default(),
+            ast: JsonAst::new(),
+            buffer: Vec::new(),
+            line: 0,
+            skipped: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// Picks what happens when a line fails to parse, the default is `BadLinePolicy::Stop`
+    pub fn with_policy(mut self, policy: BadLinePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Parses every line with `options` instead of strict json
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Number of the last line that was read, starts at 1
+    pub fn line_number(&self) -> usize {
+        self.line
+    }
+
+    /// Lines that were skipped because of `BadLinePolicy::Skip`
+    pub fn skipped_lines(&self) -> &[usize] {
+        &self.skipped
+    }
+
+    /// # Description
+    /// Reads the next value
+    /// # Returns
+    /// `Ok(None)` at the end of the stream, or after an error ended the iteration
+    pub fn next_value(&mut self) -> Result<Option<JsonValue>, NdjsonError> {
+        while self.done == false {
+            match self.read_line() {
+                Ok(Some(value)) => return Ok(Some(value)),
+                Ok(None) => continue,
+                Err(NdjsonError::Io { line, error }) => {
+                    self.done = true;
+                    return Err(NdjsonError::Io { line, error });
+                }
+                Err(err) => match self.policy {
+                    BadLinePolicy::Skip => self.skipped.push(err.line()),
+                    BadLinePolicy::Stop => {
+                        self.done = true;
+                        return Err(err);
+                    }
+                },
+            }
+        }
+        Ok(None)
+    }
+
+    /// reads and parses one line, `Ok(None)` for a blank line (or the end of the stream)
+    fn read_line(&mut self) -> Result<Option<JsonValue>, NdjsonError> {
+        self.buffer.clear();
+        let line = self.line + 1;
+        let num_read = loop {
+            match self.reader.read_until(b'\n', &mut self.buffer) {
+                Ok(num_read) => break num_read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(NdjsonError::Io { line, error }),
+            }
+        };
+        if num_read == 0 {
+            self.done = true;
+            return Ok(None);
+        }
+        self.line = line;
+
+        let text =
+            std::str::from_utf8(&self.buffer).map_err(|_| NdjsonError::InvalidUtf8 { line })?;
+        let text = text.trim_end_matches(['\n', '\r']);
+        if text.trim().is_empty() {
+            return Ok(None);
+        }
+        self.ast
+            .parse_with(text, &self.options)
+            .map_err(|error| NdjsonError::Parse { line, error })?;
+        Ok(Some(JsonValue::from_ast(&self.ast, text)))
+    }
+}
+
+impl<R: io::BufRead> Iterator for NdjsonReader<R> {
+    type Item = Result<JsonValue, NdjsonError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_value().transpose()
+    }
+}
+
+/// # Description
+/// Writes newline-delimited json, one compact value per line
+/// # Comments
+/// - Compact json never contains a raw newline (they are escaped inside strings) so every value
+/// is guaranteed to stay on its own line
+pub struct NdjsonWriter<W> {
+    writer: W,
+}
+
+impl<W: io::Write> NdjsonWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Writes `value` followed by `\n`
+    pub fn write_value(&mut self, value: &JsonValue) -> io::Result<()> {
+        value.write_json(&mut self.writer)?;
+        self.writer.write_all(b"\n")
+    }
+
+    /// Writes every value of `values`, one per line
+    pub fn write_all<'a>(
+        &mut self,
+        values: impl IntoIterator<Item = &'a JsonValue>,
+    ) -> io::Result<()> {
+        for value in values {
+            self.write_value(value)?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Hands back the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
@@ -0,0 +1,501 @@
+use std::{borrow::Cow, cmp::Ordering, fmt};
+
+use super::lexer::{is_json_number, scan_json5_number, scan_number};
+
+/// The longest plain decimal string `to_decimal_string()` is willing to build.
+/// Stops something like `1e999999999` from allocating a gigabyte of zeros
+const MAX_DECIMAL_LEN: usize = 4096;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum NumberError {
+    /// The number has a fractional part so it can't be converted to an integer
+    NotAnInteger,
+    /// The number doesn't fit in the requested type
+    OutOfRange,
+}
+
+impl fmt::Display for NumberError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotAnInteger => write!(f, "number is not an integer"),
+            Self::OutOfRange => write!(f, "number is out of range"),
+        }
+    }
+}
+
+/// # Description
+/// A json number stored exactly as it appeared in the document.
+/// # Comments
+/// - Nothing is converted until one of the `as_*` accessors is called, so IDs, timestamps
+/// and money values come back with every digit intact
+/// - Writing a `JsonNumber` back out reproduces the original lexeme
+#[derive(Clone, PartialEq, Debug)]
+pub struct JsonNumber {
+    lexeme: String,
+}
+
+/// A number split into `digits * 10^exponent` with leading and trailing zeros removed
+struct DecimalParts {
+    negative: bool,
+    digits: Vec<u8>,
+    exponent: i64,
+}
+
+impl JsonNumber {
+    /// # Description
+    /// Creates a number from `lexeme`
+    /// # Returns
+    /// `None` if `lexeme` doesn't follow the RFC 8259 number grammar
+    pub fn parse(lexeme: &str) -> Option<Self> {
+        match scan_number(lexeme.as_bytes(), 0) {
+            Some(ubound) if ubound == lexeme.len() => Some(Self {
+                lexeme: lexeme.to_string(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// # Description
+    /// Creates a number from a JSON5 number lexeme, normalizing it into plain json on the way
+    /// # Returns
+    /// `None` for `Infinity`/`NaN` (json has no way to write them) or if `lexeme` isn't a JSON5 number
+    /// # Comments
+    /// - Hex integers are converted to decimal digit by digit, so `0xFFFFFFFFFFFFFFFFFF` stays exact
+    /// - `+1` becomes `1`, `.5` becomes `0.5` and `5.` becomes `5`
+    pub fn parse_json5(lexeme: &str) -> Option<Self> {
+        if let Some(number) = Self::parse(lexeme) {
+            return Some(number);
+        }
+        match scan_json5_number(lexeme.as_bytes(), 0) {
+            Some(ubound) if ubound == lexeme.len() => (),
+            _ => return None,
+        }
+        let (negative, unsigned) = match lexeme.as_bytes()[0] {
+            b'-' => (true, &lexeme[1..]),
+            b'+' => (false, &lexeme[1..]),
+            _ => (false, lexeme),
+        };
+        let sign = if negative { "-" } else { "" };
+        if unsigned.starts_with("0x") || unsigned.starts_with("0X") {
+            // little endian base 10 digits, multiplied by 16 for each hex digit
+            let mut digits: Vec<u8> = vec![0];
+            for hex in unsigned[2..].chars() {
+                let mut carry = hex.to_digit(16).unwrap();
+                for digit in digits.iter_mut() {
+                    let val = *digit as u32 * 16 + carry;
+                    *digit = (val % 10) as u8;
+                    carry = val / 10;
+                }
+                while carry > 0 {
+                    digits.push((carry % 10) as u8);
+                    carry /= 10;
+                }
+            }
+            while digits.len() > 1 && digits.last() == Some(&0) {
+                digits.pop();
+            }
+            let decimal: String = digits.iter().rev().map(|d| (b'0' + d) as char).collect();
+            let sign = if decimal == "0" { "" } else { sign };
+            return Self::parse(&format!("{}{}", sign, decimal));
+        }
+        if unsigned.starts_with("Infinity") || unsigned.starts_with("NaN") {
+            return None;
+        }
+        let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+            Some(k) => unsigned.split_at(k),
+            None => (unsigned, ""),
+        };
+        let mut mantissa = mantissa.to_string();
+        if mantissa.starts_with('.') {
+            mantissa.insert(0, '0');
+        }
+        if mantissa.ends_with('.') {
+            mantissa.pop();
+        }
+        Self::parse(&format!("{}{}{}", sign, mantissa, exponent))
+    }
+
+    /// # Description
+    /// The plain json text of a number token, normalized the same way as `parse_json5(..)`
+    /// # Returns
+    /// `lexeme` itself when it's already RFC 8259 number text, `None` for `Infinity`/`NaN`
+    /// # Comments
+    /// - `JsonValue` and `BorrowedValue` both decode number tokens through here, so they can't disagree
+    /// and plain json numbers are only scanned once
+    pub(crate) fn normalize(lexeme: &str) -> Option<Cow<'_, str>> {
+        if is_json_number(lexeme) {
+            Some(Cow::Borrowed(lexeme))
+        } else {
+            Self::parse_json5(lexeme).map(|number| Cow::Owned(number.lexeme))
+        }
+    }
+
+    /// Wraps text that already is a RFC 8259 number (from `normalize(..)`) without scanning it again
+    pub(crate) fn from_normalized(lexeme: String) -> Self {
+        Self { lexeme }
+    }
+
+    /// Creates a number from a float, `None` for NaN and infinity since json can't represent them
+    pub fn from_f64(val: f64) -> Option<Self> {
+        if val.is_finite() {
+            // `{:?}` always prints the shortest text that reads back as the same f64
+            let mut lexeme = format!("{:?}", val);
+            if lexeme.ends_with(".0") {
+                lexeme.truncate(lexeme.len() - 2);
+            }
+            Self::parse(&lexeme)
+        } else {
+            None
+        }
+    }
+
+    /// Like `from_f64(..)` but keeps the shortest `f32` text, so `0.1f32` becomes `0.1` instead of `0.10000000149011612`
+    pub fn from_f32(val: f32) -> Option<Self> {
+        if val.is_finite() {
+            let mut lexeme = format!("{:?}", val);
+            if lexeme.ends_with(".0") {
+                lexeme.truncate(lexeme.len() - 2);
+            }
+            Self::parse(&lexeme)
+        } else {
+            None
+        }
+    }
+
+    /// The number exactly as it appeared in the document
+    pub fn as_str(&self) -> &str {
+        self.lexeme.as_str()
+    }
+
+    pub fn as_i64(&self) -> Result<i64, NumberError> {
+        let parts = self.decimal_parts();
+        let magnitude = parts.integer_magnitude()?;
+        if parts.negative {
+            if magnitude > i64::MAX as u128 + 1 {
+                Err(NumberError::OutOfRange)
+            } else {
+                Ok(-(magnitude as i128) as i64)
+            }
+        } else if magnitude > i64::MAX as u128 {
+            Err(NumberError::OutOfRange)
+        } else {
+            Ok(magnitude as i64)
+        }
+    }
+
+    pub fn as_u64(&self) -> Result<u64, NumberError> {
+        let parts = self.decimal_parts();
+        let magnitude = parts.integer_magnitude()?;
+        if (parts.negative && magnitude != 0) || magnitude > u64::MAX as u128 {
+            Err(NumberError::OutOfRange)
+        } else {
+            Ok(magnitude as u64)
+        }
+    }
+
+    /// # Description
+    /// Converts to the nearest `f64`
+    /// # Comments
+    /// Precision can be lost(that's the nature of floats), but a number too large to
+    /// represent at all is an `OutOfRange` error instead of infinity
+    pub fn as_f64(&self) -> Result<f64, NumberError> {
+        match self.lexeme.parse::<f64>() {
+            Ok(val) if val.is_finite() => Ok(val),
+            _ => Err(NumberError::OutOfRange),
+        }
+    }
+
+    /// # Description
+    /// Writes the number the way javascript's `Number.prototype.toString()` would, which is
+    /// what RFC 8785 (JCS) expects. For example `4.50` becomes `4.5`, `1E30` becomes `1e+30`
+    /// # Comments
+    /// - The number is rounded to the nearest `f64` first, so digits beyond double precision are lost
+    /// - `-0` is written as `0`
+    /// # Returns
+    /// `OutOfRange` if the number is too large for an `f64`
+    pub fn to_ecmascript_string(&self) -> Result<String, NumberError> {
+        let val = self.as_f64()?;
+        if val == 0.0 {
+            return Ok(String::from("0"));
+        }
+        // `{:e}` gives the fewest digits that read back as the same f64. When two numbers with that
+        // many digits both work javascript picks the closer one (ties go to even), so the value is
+        // then rounded exactly to that many digits
+        let shortest = format!("{:e}", val.abs());
+        let num_digits = shortest.find('e').unwrap() - shortest.find('.').map_or(0, |_| 1);
+        let scientific = format!("{:.*e}", num_digits - 1, val.abs());
+        let (mantissa, exponent) = scientific.split_at(scientific.find('e').unwrap());
+        let digits: String = mantissa.chars().filter(|&c| c != '.').collect();
+        let k = digits.len() as i64;
+        // javascript's n: the value is 0.d1d2..dk * 10^n
+        let n = exponent[1..].parse::<i64>().unwrap() + 1;
+
+        let mut text = String::with_capacity(k as usize + 8);
+        if val < 0.0 {
+            text.push('-');
+        }
+        if k <= n && n <= 21 {
+            text.push_str(&digits);
+            text.extend((0..n - k).map(|_| '0'));
+        } else if 0 < n && n <= 21 {
+            text.push_str(&digits[..n as usize]);
+            text.push('.');
+            text.push_str(&digits[n as usize..]);
+        } else if -6 < n && n <= 0 {
+            text.push_str("0.");
+            text.extend((0..-n).map(|_| '0'));
+            text.push_str(&digits);
+        } else {
+            text.push_str(&digits[..1]);
+            if k > 1 {
+                text.push('.');
+                text.push_str(&digits[1..]);
+            }
+            text.push_str(&format!("e{}{}", if n > 0 { '+' } else { '-' }, (n - 1).abs()));
+        }
+        Ok(text)
+    }
+
+    /// # Description
+    /// The exact value of the number written as a plain decimal, without an exponent.\
+    /// For example `1.50e3` becomes `1500` and `-25E-3` becomes `-0.025`
+    /// # Returns
+    /// `OutOfRange` if the expanded string would be unreasonably long
+    pub fn to_decimal_string(&self) -> Result<String, NumberError> {
+        let parts = self.decimal_parts();
+        if parts.digits.is_empty() {
+            return Ok(String::from("0"));
+        }
+
+        let num_digits = parts.digits.len() as i64;
+        let expanded_len = num_digits + parts.exponent.abs();
+        if expanded_len > MAX_DECIMAL_LEN as i64 {
+            return Err(NumberError::OutOfRange);
+        }
+
+        let digits: String = parts.digits.iter().map(|&d| d as char).collect();
+        let mut decimal = String::with_capacity(expanded_len as usize + 3);
+        if parts.negative {
+            decimal.push('-');
+        }
+        if parts.exponent >= 0 {
+            decimal.push_str(&digits);
+            decimal.extend((0..parts.exponent).map(|_| '0'));
+        } else {
+            let int_len = num_digits + parts.exponent;
+            if int_len > 0 {
+                decimal.push_str(&digits[..int_len as usize]);
+                decimal.push('.');
+                decimal.push_str(&digits[int_len as usize..]);
+            } else {
+                decimal.push_str("0.");
+                decimal.extend((0..-int_len).map(|_| '0'));
+                decimal.push_str(&digits);
+            }
+        }
+        Ok(decimal)
+    }
+
+    /// # Description
+    /// Compares two numbers by value, exactly. `1.0` equals `1` and `9007199254740993` is greater
+    /// than `9007199254740992` even though both round to the same `f64`
+    /// # Comments
+    /// - `-0` and `0` are equal
+    /// - Works on the digits alone, so it never fails, not even for lexemes like `1e400`
+    pub fn cmp_value(&self, other: &Self) -> Ordering {
+        let left = self.decimal_parts();
+        let right = other.decimal_parts();
+        let (left_sign, right_sign) = (left.sign(), right.sign());
+        if left_sign != right_sign || left_sign == 0 {
+            return left_sign.cmp(&right_sign);
+        }
+
+        // both are `0.d1d2d3.. * 10^magnitude` now, so the magnitude decides before any digit does
+        let ordering = left
+            .magnitude()
+            .cmp(&right.magnitude())
+            .then_with(|| left.digits.cmp(&right.digits));
+        if left_sign < 0 {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+
+    /// # Description
+    /// Whether `self` is an integer multiple of `divisor`, exactly. `1e20` isn't a multiple of `3` and
+    /// `0.3` is a multiple of `0.1`, which floats get wrong both ways
+    /// # Returns
+    /// `None` when it can't be decided: `divisor` is zero, has more significant digits than fit in 64 bits
+    /// or an exponent too large to have been read exactly
+    /// # Comments
+    /// - Works on the digits and exponents, so `1e400` and `1e-400` are fine
+    pub fn is_multiple_of(&self, divisor: &Self) -> Option<bool> {
+        let number = self.decimal_parts();
+        let divisor = divisor.decimal_parts();
+        let modulus = divisor.significand()?;
+        if number.digits.is_empty() {
+            return Some(true);
+        }
+        let saturated = |exponent: i64| exponent.abs() >= i64::MAX / 4;
+        if saturated(number.exponent) || saturated(divisor.exponent) {
+            return None;
+        }
+
+        // number / divisor = (digits / significand) * 10^shift
+        let shift = number.exponent - divisor.exponent;
+        if shift < 0 {
+            // the number has no trailing zeros, so it can't be divided by a power of 10 as well
+            return Some(false);
+        }
+        let modulus = modulus as u128;
+        let mut remainder = number
+            .digits
+            .iter()
+            .fold(0, |acc, &d| (acc * 10 + (d - b'0') as u128) % modulus);
+        // times 10^shift, squaring so even huge exponents only take a few steps
+        let (mut base, mut shift) = (10 % modulus, shift as u64);
+        while shift > 0 {
+            if shift & 1 == 1 {
+                remainder = remainder * base % modulus;
+            }
+            base = base * base % modulus;
+            shift >>= 1;
+        }
+        Some(remainder == 0)
+    }
+
+    fn decimal_parts(&self) -> DecimalParts {
+        let bytes = self.lexeme.as_bytes();
+        let negative = bytes[0] == b'-';
+        let mantissa_end = bytes
+            .iter()
+            .position(|&c| c == b'e' || c == b'E')
+            .unwrap_or(bytes.len());
+
+        let mut digits = Vec::new();
+        let mut exponent: i64 = 0;
+        let mut in_fraction = false;
+        for c in bytes[..mantissa_end].iter() {
+            match c {
+                b'.' => in_fraction = true,
+                b'0'..=b'9' => {
+                    digits.push(*c);
+                    if in_fraction {
+                        exponent -= 1;
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        if mantissa_end < bytes.len() {
+            // saturate instead of overflowing, numbers that large are out of range for everything anyway
+            let exp_text = &self.lexeme[mantissa_end + 1..];
+            let explicit = exp_text.parse::<i64>().unwrap_or_else(|_| {
+                if exp_text.starts_with('-') {
+                    i64::MIN / 2
+                } else {
+                    i64::MAX / 2
+                }
+            });
+            exponent = exponent.saturating_add(explicit);
+        }
+
+        let leading_zeros = digits.iter().take_while(|&&d| d == b'0').count();
+        digits.drain(..leading_zeros);
+        while let Some(&b'0') = digits.last() {
+            digits.pop();
+            exponent = exponent.saturating_add(1);
+        }
+
+        DecimalParts {
+            negative,
+            digits,
+            exponent,
+        }
+    }
+}
+
+impl DecimalParts {
+    /// -1, 0 or 1, zero has no sign
+    fn sign(&self) -> i8 {
+        match (self.digits.is_empty(), self.negative) {
+            (true, _) => 0,
+            (false, true) => -1,
+            (false, false) => 1,
+        }
+    }
+
+    /// the exponent of the number once it's written as `0.digits * 10^magnitude`
+    fn magnitude(&self) -> i64 {
+        self.exponent.saturating_add(self.digits.len() as i64)
+    }
+
+    /// the digits as an integer (the exponent is left out), `None` for zero or if they don't fit in 64 bits
+    fn significand(&self) -> Option<u64> {
+        if self.digits.is_empty() || self.digits.len() > 19 {
+            return None;
+        }
+        Some(
+            self.digits
+                .iter()
+                .fold(0, |acc, &d| acc * 10 + (d - b'0') as u64),
+        )
+    }
+
+    /// the absolute value of the number, provided its an integer that fits in 128 bits
+    fn integer_magnitude(&self) -> Result<u128, NumberError> {
+        if self.digits.is_empty() {
+            return Ok(0);
+        }
+        if self.exponent < 0 {
+            return Err(NumberError::NotAnInteger);
+        }
+        if self.digits.len() as i64 + self.exponent > 38 {
+            return Err(NumberError::OutOfRange);
+        }
+        let mut magnitude: u128 = 0;
+        for &d in self.digits.iter() {
+            magnitude = magnitude * 10 + (d - b'0') as u128;
+        }
+        for _ in 0..self.exponent {
+            magnitude *= 10;
+        }
+        Ok(magnitude)
+    }
+}
+
+impl fmt::Display for JsonNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.lexeme)
+    }
+}
+
+impl From<i64> for JsonNumber {
+    fn from(val: i64) -> Self {
+        Self {
+            lexeme: val.to_string(),
+        }
+    }
+}
+
+impl From<u64> for JsonNumber {
+    fn from(val: u64) -> Self {
+        Self {
+            lexeme: val.to_string(),
+        }
+    }
+}
+
+impl From<i32> for JsonNumber {
+    fn from(val: i32) -> Self {
+        Self::from(val as i64)
+    }
+}
+
+impl From<u32> for JsonNumber {
+    fn from(val: u32) -> Self {
+        Self::from(val as u64)
+    }
+}
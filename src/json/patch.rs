@@ -0,0 +1,362 @@
+use std::{fmt, mem};
+
+use super::{
+    path::values_equal,
+    pointer::{parse_index, parse_pointer, to_pointer, PointerError},
+    value::JsonValue,
+};
+
+/// # Description
+/// Why a JSON Patch (RFC 6902) couldn't be applied
+/// # Comments
+/// - `index` is the position of the failing operation in the patch
+#[derive(Clone, PartialEq, Debug)]
+pub enum PatchError {
+    /// The patch document isn't an array of operations
+    NotAnArray,
+    /// Operation `index` is missing a member, has one of the wrong type, or has an unknown `op`
+    Malformed { index: usize, reason: String },
+    /// A `path` or `from` in operation `index` didn't resolve
+    Pointer { index: usize, error: PointerError },
+    /// The `test` operation at `index` found a different value at `path`
+    TestFailed { index: usize, path: String },
+    /// Operation `index` tried to move a value into one of its own children
+    MoveIntoChild {
+        index: usize,
+        from: String,
+        path: String,
+    },
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotAnArray => write!(f, "a json patch must be an array of operations"),
+            Self::Malformed { index, reason } => {
+                write!(f, "patch operation {} is malformed: {}", index, reason)
+            }
+            Self::Pointer { index, error } => {
+                write!(f, "patch operation {} failed: {}", index, error)
+            }
+            Self::TestFailed { index, path } => {
+                write!(
+                    f,
+                    "patch operation {} failed: test at {:?} didn't match",
+                    index, path
+                )
+            }
+            Self::MoveIntoChild { index, from, path } => write!(
+                f,
+                "patch operation {} failed: can't move {:?} into its own child {:?}",
+                index, from, path
+            ),
+        }
+    }
+}
+
+/// A single JSON Patch operation, pointers are kept in their escaped string form
+#[derive(Clone, PartialEq, Debug)]
+pub enum PatchOperation {
+    Add { path: String, value: JsonValue },
+    Remove { path: String },
+    Replace { path: String, value: JsonValue },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: JsonValue },
+}
+
+impl PatchOperation {
+    /// # Description
+    /// Reads one operation object like `{"op":"add","path":"/a","value":1}`
+    /// # Arguments
+    /// - `index` : position of the operation in the patch, only used for error reporting
+    /// # Comments
+    /// - Unknown members are ignored like RFC 6902 asks, duplicate members are an error
+    /// - Pointers are checked for syntax here, whether they resolve is checked when the patch is applied
+    pub fn from_value(op: &JsonValue, index: usize) -> Result<Self, PatchError> {
+        let malformed = |reason: &str| PatchError::Malformed {
+            index,
+            reason: reason.to_string(),
+        };
+        let props = match op.as_object() {
+            Some(props) => props,
+            None => return Err(malformed("an operation must be an object")),
+        };
+        // a second `op` could turn an add into a remove depending on who reads it (RFC 6902 A.13)
+        for (position, (key, _)) in props.iter().enumerate() {
+            if props[..position].iter().any(|(other, _)| other == key) {
+                return Err(malformed(&format!("duplicate member `{}`", key)));
+            }
+        }
+        let pointer_member = |name: &str| -> Result<String, PatchError> {
+            let pointer = op
+                .get(name)
+                .ok_or_else(|| malformed(&format!("missing `{}`", name)))?
+                .as_str()
+                .ok_or_else(|| malformed(&format!("`{}` must be a string", name)))?;
+            parse_pointer(pointer).map_err(|error| PatchError::Pointer { index, error })?;
+            Ok(pointer.to_string())
+        };
+        let value_member = || -> Result<JsonValue, PatchError> {
+            op.get("value")
+                .cloned()
+                .ok_or_else(|| malformed("missing `value`"))
+        };
+
+        let name = op
+            .get("op")
+            .ok_or_else(|| malformed("missing `op`"))?
+            .as_str()
+            .ok_or_else(|| malformed("`op` must be a string"))?;
+        let operation = match name {
+            "add" => Self::Add {
+                path: pointer_member("path")?,
+                value: value_member()?,
+            },
+            "remove" => Self::Remove {
+                path: pointer_member("path")?,
+            },
+            "replace" => Self::Replace {
+                path: pointer_member("path")?,
+                value: value_member()?,
+            },
+            "move" => Self::Move {
+                from: pointer_member("from")?,
+                path: pointer_member("path")?,
+            },
+            "copy" => Self::Copy {
+                from: pointer_member("from")?,
+                path: pointer_member("path")?,
+            },
+            "test" => Self::Test {
+                path: pointer_member("path")?,
+                value: value_member()?,
+            },
+            _ => return Err(malformed(&format!("unknown op {:?}", name))),
+        };
+        Ok(operation)
+    }
+
+    /// The inverse of `from_value(..)`, builds the operation object
+    pub fn to_value(&self) -> JsonValue {
+        let member = |name: &str, val: &str| (name.to_string(), JsonValue::String(val.to_string()));
+        let mut props = match self {
+            Self::Add { path, .. } => vec![member("op", "add"), member("path", path)],
+            Self::Remove { path } => vec![member("op", "remove"), member("path", path)],
+            Self::Replace { path, .. } => vec![member("op", "replace"), member("path", path)],
+            Self::Move { from, path } => {
+                vec![member("op", "move"), member("from", from), member("path", path)]
+            }
+            Self::Copy { from, path } => {
+                vec![member("op", "copy"), member("from", from), member("path", path)]
+            }
+            Self::Test { path, .. } => vec![member("op", "test"), member("path", path)],
+        };
+        match self {
+            Self::Add { value, .. } | Self::Replace { value, .. } | Self::Test { value, .. } => {
+                props.push((String::from("value"), value.clone()))
+            }
+            _ => (),
+        }
+        JsonValue::Object(props)
+    }
+}
+
+/// Reads a whole patch document into a list of operations
+pub fn parse_patch(patch: &JsonValue) -> Result<Vec<PatchOperation>, PatchError> {
+    patch
+        .as_array()
+        .ok_or(PatchError::NotAnArray)?
+        .iter()
+        .enumerate()
+        .map(|(index, op)| PatchOperation::from_value(op, index))
+        .collect()
+}
+
+/// Builds a patch document (an array of operation objects) out of `operations`
+pub fn patch_to_value(operations: &[PatchOperation]) -> JsonValue {
+    JsonValue::Array(operations.iter().map(PatchOperation::to_value).collect())
+}
+
+impl JsonValue {
+    /// # Description
+    /// Applies a JSON Patch (RFC 6902) document to `self`
+    /// # Comments
+    /// - The patch is atomic, if any operation fails `self` is left exactly as it was
+    pub fn apply_patch(&mut self, patch: &JsonValue) -> Result<(), PatchError> {
+        let operations = parse_patch(patch)?;
+        self.apply_operations(&operations)
+    }
+
+    /// Like `apply_patch(..)` but for operations that were already parsed
+    pub fn apply_operations(&mut self, operations: &[PatchOperation]) -> Result<(), PatchError> {
+        // work on a copy and only swap it in once every operation went through
+        let mut patched = self.clone();
+        for (index, operation) in operations.iter().enumerate() {
+            patched.apply_operation(operation, index)?;
+        }
+        mem::swap(self, &mut patched);
+        Ok(())
+    }
+
+    fn apply_operation(
+        &mut self,
+        operation: &PatchOperation,
+        index: usize,
+    ) -> Result<(), PatchError> {
+        let pointer_error = |error| PatchError::Pointer { index, error };
+        match operation {
+            PatchOperation::Add { path, value } => {
+                self.patch_add(path, value.clone()).map_err(pointer_error)
+            }
+            PatchOperation::Remove { path } => {
+                self.patch_remove(path).map(|_| ()).map_err(pointer_error)
+            }
+            PatchOperation::Replace { path, value } => {
+                *self.pointer_mut(path).map_err(pointer_error)? = value.clone();
+                Ok(())
+            }
+            PatchOperation::Move { from, path } => {
+                if from == path {
+                    // still has to exist though
+                    return self.pointer(from).map(|_| ()).map_err(pointer_error);
+                }
+                if path.starts_with(from.as_str()) && path[from.len()..].starts_with('/') {
+                    return Err(PatchError::MoveIntoChild {
+                        index,
+                        from: from.clone(),
+                        path: path.clone(),
+                    });
+                }
+                let value = self.patch_remove(from).map_err(pointer_error)?;
+                self.patch_add(path, value).map_err(pointer_error)
+            }
+            PatchOperation::Copy { from, path } => {
+                let value = self.pointer(from).map_err(pointer_error)?.clone();
+                self.patch_add(path, value).map_err(pointer_error)
+            }
+            PatchOperation::Test { path, value } => {
+                let current = self.pointer(path).map_err(pointer_error)?;
+                if values_equal(current, value) {
+                    Ok(())
+                } else {
+                    Err(PatchError::TestFailed {
+                        index,
+                        path: path.clone(),
+                    })
+                }
+            }
+        }
+    }
+
+    /// add/replace a property, or insert into an array (`-` appends)
+    fn patch_add(&mut self, path: &str, value: JsonValue) -> Result<(), PointerError> {
+        let mut tokens = parse_pointer(path)?;
+        let last = match tokens.pop() {
+            Some(last) => last,
+            None => {
+                *self = value;
+                return Ok(());
+            }
+        };
+        let parent_pointer = to_pointer(&tokens);
+        match self.pointer_mut(&parent_pointer)? {
+            Self::Object(props) => {
+                match props.iter_mut().filter(|(key, _)| *key == last).next() {
+                    Some((_, val)) => *val = value,
+                    None => props.push((last, value)),
+                }
+                Ok(())
+            }
+            Self::Array(items) if last == "-" => {
+                items.push(value);
+                Ok(())
+            }
+            Self::Array(items) => match parse_index(&last) {
+                Some(index) if index <= items.len() => {
+                    items.insert(index, value);
+                    Ok(())
+                }
+                _ => Err(PointerError::InvalidIndex {
+                    at: parent_pointer,
+                    segment: last,
+                }),
+            },
+            _ => Err(PointerError::NotAContainer {
+                at: parent_pointer,
+                segment: last,
+            }),
+        }
+    }
+
+    /// removes the value at `path` and hands it back
+    fn patch_remove(&mut self, path: &str) -> Result<JsonValue, PointerError> {
+        let mut tokens = parse_pointer(path)?;
+        let last = match tokens.pop() {
+            Some(last) => last,
+            None => {
+                return Err(PointerError::Syntax {
+                    pointer: path.to_string(),
+                    reason: "the whole document can't be removed",
+                })
+            }
+        };
+        let parent_pointer = to_pointer(&tokens);
+        match self.pointer_mut(&parent_pointer)? {
+            Self::Object(props) => match props.iter().position(|(key, _)| *key == last) {
+                Some(position) => Ok(props.remove(position).1),
+                None => Err(PointerError::MissingKey {
+                    at: parent_pointer,
+                    segment: last,
+                }),
+            },
+            Self::Array(items) => match parse_index(&last) {
+                Some(index) if index < items.len() => Ok(items.remove(index)),
+                _ => Err(PointerError::InvalidIndex {
+                    at: parent_pointer,
+                    segment: last,
+                }),
+            },
+            _ => Err(PointerError::NotAContainer {
+                at: parent_pointer,
+                segment: last,
+            }),
+        }
+    }
+
+    /// # Description
+    /// Applies a JSON Merge Patch (RFC 7386) to `self`
+    /// # Comments
+    /// - `null` members in the patch delete the property, objects are merged recursively and
+    ///   everything else (arrays included) replaces the target wholesale
+    /// - A merge patch can't fail, so there's nothing to roll back
+    pub fn apply_merge_patch(&mut self, patch: &JsonValue) {
+        let patch_props = match patch {
+            Self::Object(props) => props,
+            _ => {
+                *self = patch.clone();
+                return;
+            }
+        };
+        if self.as_object().is_none() {
+            *self = Self::Object(Vec::new());
+        }
+        if let Self::Object(props) = self {
+            for (key, patch_val) in patch_props.iter() {
+                if patch_val.is_null() {
+                    props.retain(|(k, _)| k != key);
+                    continue;
+                }
+                match props.iter_mut().filter(|(k, _)| k == key).next() {
+                    Some((_, val)) => val.apply_merge_patch(patch_val),
+                    None => {
+                        // merging into null strips any nulls nested in the patch value
+                        let mut val = Self::Null;
+                        val.apply_merge_patch(patch_val);
+                        props.push((key.clone(), val));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,1109 @@
+use std::{borrow::Cow, cmp::Ordering, fmt};
+
+#[cfg(feature = "extras")]
+use regex::Regex;
+#[cfg(feature = "extras")]
+use std::{cell::RefCell, collections::HashMap};
+
+use super::{lexer::scan_number, number::JsonNumber, pointer::push_token, value::JsonValue};
+
+/// Integers in a query must fit in the range of integers an IEEE double can hold exactly (RFC 9535 section 2.1)
+const MAX_SAFE_INT: i64 = (1 << 53) - 1;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct PathError {
+    /// byte offset into the query where the problem was found
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid JSONPath query at offset {}: {}",
+            self.offset, self.message
+        )
+    }
+}
+
+/// One step of a node's location, a property name or an array index
+#[derive(Clone, PartialEq, Debug)]
+pub enum PathElement {
+    Key(String),
+    Index(usize),
+}
+
+/// A node selected by a query: the value and where it was found in the document
+#[derive(Clone, PartialEq, Debug)]
+pub struct PathNode<'a> {
+    pub location: Vec<PathElement>,
+    pub value: &'a JsonValue,
+}
+
+impl<'a> PathNode<'a> {
+    fn child(&self, element: PathElement, value: &'a JsonValue) -> Self {
+        let mut location = self.location.clone();
+        location.push(element);
+        Self { location, value }
+    }
+
+    /// The location as an RFC 9535 normalized path, for example `$['servers'][0]['host']`
+    pub fn normalized_path(&self) -> String {
+        let mut path = String::from("$");
+        for element in self.location.iter() {
+            match element {
+                PathElement::Key(key) => {
+                    path.push_str("['");
+                    for c in key.chars() {
+                        match c {
+                            '\u{8}' => path.push_str("\\b"),
+                            '\u{c}' => path.push_str("\\f"),
+                            '\n' => path.push_str("\\n"),
+                            '\r' => path.push_str("\\r"),
+                            '\t' => path.push_str("\\t"),
+                            '\'' => path.push_str("\\'"),
+                            '\\' => path.push_str("\\\\"),
+                            c if (c as u32) < 0x20 => {
+                                path.push_str(&format!("\\u{:04x}", c as u32))
+                            }
+                            c => path.push(c),
+                        }
+                    }
+                    path.push_str("']");
+                }
+                PathElement::Index(index) => path.push_str(&format!("[{}]", index)),
+            }
+        }
+        path
+    }
+
+    /// The location as a JSON Pointer, handy for feeding back into `JsonValue::pointer_mut(..)`
+    pub fn pointer(&self) -> String {
+        let mut pointer = String::new();
+        for element in self.location.iter() {
+            match element {
+                PathElement::Key(key) => push_token(&mut pointer, key),
+                PathElement::Index(index) => push_token(&mut pointer, &index.to_string()),
+            }
+        }
+        pointer
+    }
+}
+
+/// # Description
+/// A compiled JSONPath query (RFC 9535)
+/// # Comments
+/// - Supports the whole selector set: names, wildcards, indices, slices and filters, along with
+///   child (`.`/`[]`) and descendant (`..`) segments
+/// - Filters support comparisons, `&&`, `||`, `!`, existence tests and the standard functions
+///   `length()`, `count()` and `value()`. `match()` and `search()` need the `extras` feature since they use regex.
+/// - Queries are checked for well-typedness when they are parsed, so `select(..)` never fails
+#[derive(Clone, Debug)]
+pub struct JsonPath {
+    query: Query,
+}
+
+impl JsonPath {
+    pub fn parse(query: &str) -> Result<Self, PathError> {
+        let mut parser = PathParser { query, cursor: 0 };
+        if parser.peek() != Some('$') {
+            return parser.error("a query must start with `$`");
+        }
+        let query = parser.parse_query()?;
+        if parser.cursor != parser.query.len() {
+            return parser.error("unexpected character after the end of the query");
+        }
+        Ok(Self { query })
+    }
+
+    /// # Description
+    /// Runs the query against `root`
+    /// # Returns
+    /// Every selected node, in document order
+    pub fn select<'a>(&self, root: &'a JsonValue) -> Vec<PathNode<'a>> {
+        let context = Context::new(root);
+        let start = PathNode {
+            location: Vec::new(),
+            value: root,
+        };
+        context.apply_segments(&self.query.segments, vec![start])
+    }
+}
+
+impl JsonValue {
+    /// Parses `query` as JSONPath and runs it against `self`, see `JsonPath` for details
+    pub fn query(&self, query: &str) -> Result<Vec<PathNode<'_>>, PathError> {
+        Ok(JsonPath::parse(query)?.select(self))
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Query {
+    /// `true` for `@` queries inside of filters, `false` for `$` queries
+    relative: bool,
+    segments: Vec<Segment>,
+}
+
+impl Query {
+    /// A singular query can select at most one node, only those are allowed in comparisons
+    fn is_singular(&self) -> bool {
+        self.segments.iter().all(|segment| match segment {
+            Segment::Child(selectors) => match selectors.as_slice() {
+                [Selector::Name(_)] | [Selector::Index(_)] => true,
+                _ => false,
+            },
+            Segment::Descendant(_) => false,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Segment {
+    Child(Vec<Selector>),
+    Descendant(Vec<Selector>),
+}
+
+#[derive(Clone, Debug)]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice {
+        start: Option<i64>,
+        end: Option<i64>,
+        step: Option<i64>,
+    },
+    Filter(Box<LogicalExpr>),
+}
+
+#[derive(Clone, Debug)]
+enum LogicalExpr {
+    Or(Vec<LogicalExpr>),
+    And(Vec<LogicalExpr>),
+    Not(Box<LogicalExpr>),
+    Compare(Comparable, CompareOp, Comparable),
+    Exists(Query),
+    Function(FunctionExpr),
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// Anything that produces a value: literals, singular queries and functions.
+/// Also used for function arguments, where non-singular queries are allowed for `NodesType` parameters
+#[derive(Clone, Debug)]
+enum Comparable {
+    Literal(JsonValue),
+    Query(Query),
+    Function(FunctionExpr),
+}
+
+#[derive(Clone, Debug)]
+struct FunctionExpr {
+    function: Function,
+    args: Vec<Comparable>,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Function {
+    Length,
+    Count,
+    Match,
+    Search,
+    Value,
+}
+
+/// The type system from RFC 9535 section 2.4.1, minus `LogicalType` parameters since no standard function has one
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum ParamType {
+    Value,
+    Nodes,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum ResultType {
+    Value,
+    Logical,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "length" => Some(Self::Length),
+            "count" => Some(Self::Count),
+            "match" => Some(Self::Match),
+            "search" => Some(Self::Search),
+            "value" => Some(Self::Value),
+            _ => None,
+        }
+    }
+
+    fn params(self) -> &'static [ParamType] {
+        match self {
+            Self::Length => &[ParamType::Value],
+            Self::Count | Self::Value => &[ParamType::Nodes],
+            Self::Match | Self::Search => &[ParamType::Value, ParamType::Value],
+        }
+    }
+
+    fn result(self) -> ResultType {
+        match self {
+            Self::Match | Self::Search => ResultType::Logical,
+            _ => ResultType::Value,
+        }
+    }
+}
+
+struct PathParser<'q> {
+    query: &'q str,
+    cursor: usize,
+}
+
+impl<'q> PathParser<'q> {
+    fn error<T>(&self, message: &str) -> Result<T, PathError> {
+        Err(PathError {
+            offset: self.cursor,
+            message: message.to_string(),
+        })
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.query[self.cursor..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.cursor += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, text: &str) -> bool {
+        if self.query[self.cursor..].starts_with(text) {
+            self.cursor += text.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ') | Some('\t') | Some('\n') | Some('\r') = self.peek() {
+            self.cursor += 1;
+        }
+    }
+
+    fn parse_query(&mut self) -> Result<Query, PathError> {
+        let relative = match self.peek() {
+            Some('$') => false,
+            Some('@') => true,
+            _ => return self.error("expected `$` or `@`"),
+        };
+        self.bump();
+
+        let mut segments = Vec::new();
+        loop {
+            let save = self.cursor;
+            self.skip_whitespace();
+            if self.eat("..") {
+                let selectors = match self.peek() {
+                    Some('[') => self.parse_bracketed()?,
+                    Some('*') => {
+                        self.bump();
+                        vec![Selector::Wildcard]
+                    }
+                    _ => vec![Selector::Name(self.parse_member_name()?)],
+                };
+                segments.push(Segment::Descendant(selectors));
+            } else if self.eat(".") {
+                let selector = match self.peek() {
+                    Some('*') => {
+                        self.bump();
+                        Selector::Wildcard
+                    }
+                    _ => Selector::Name(self.parse_member_name()?),
+                };
+                segments.push(Segment::Child(vec![selector]));
+            } else if self.peek() == Some('[') {
+                segments.push(Segment::Child(self.parse_bracketed()?));
+            } else {
+                // whitespace only belongs to the query if a segment follows it
+                self.cursor = save;
+                break;
+            }
+        }
+        Ok(Query { relative, segments })
+    }
+
+    fn parse_member_name(&mut self) -> Result<String, PathError> {
+        match self.peek() {
+            Some(c) if is_name_first(c) => (),
+            _ => return self.error("expected a member name or `*`"),
+        }
+        let start = self.cursor;
+        while let Some(c) = self.peek() {
+            if is_name_first(c) == false && c.is_ascii_digit() == false {
+                break;
+            }
+            self.bump();
+        }
+        Ok(self.query[start..self.cursor].to_string())
+    }
+
+    fn parse_bracketed(&mut self) -> Result<Vec<Selector>, PathError> {
+        self.bump();
+        let mut selectors = Vec::new();
+        loop {
+            self.skip_whitespace();
+            selectors.push(self.parse_selector()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.bump();
+                }
+                Some(']') => {
+                    self.bump();
+                    return Ok(selectors);
+                }
+                _ => return self.error("expected `,` or `]`"),
+            }
+        }
+    }
+
+    fn parse_selector(&mut self) -> Result<Selector, PathError> {
+        match self.peek() {
+            Some('\'') | Some('\"') => Ok(Selector::Name(self.parse_string_literal()?)),
+            Some('*') => {
+                self.bump();
+                Ok(Selector::Wildcard)
+            }
+            Some('?') => {
+                self.bump();
+                self.skip_whitespace();
+                Ok(Selector::Filter(Box::new(self.parse_logical_or()?)))
+            }
+            Some('-') | Some('0'..='9') | Some(':') => {
+                let start = if self.peek() == Some(':') {
+                    None
+                } else {
+                    Some(self.parse_int()?)
+                };
+                let save = self.cursor;
+                self.skip_whitespace();
+                if self.eat(":") == false {
+                    self.cursor = save;
+                    return Ok(Selector::Index(start.unwrap()));
+                }
+
+                self.skip_whitespace();
+                let end = self.parse_optional_int()?;
+                self.skip_whitespace();
+                let step = if self.eat(":") {
+                    self.skip_whitespace();
+                    self.parse_optional_int()?
+                } else {
+                    None
+                };
+                Ok(Selector::Slice { start, end, step })
+            }
+            _ => self.error("expected a selector"),
+        }
+    }
+
+    fn parse_optional_int(&mut self) -> Result<Option<i64>, PathError> {
+        match self.peek() {
+            Some('-') | Some('0'..='9') => Ok(Some(self.parse_int()?)),
+            _ => Ok(None),
+        }
+    }
+
+    fn parse_int(&mut self) -> Result<i64, PathError> {
+        let start = self.cursor;
+        let negative = self.eat("-");
+        match self.peek() {
+            Some('0') if negative => return self.error("`-0` is not a valid integer"),
+            Some('0') => {
+                self.bump();
+            }
+            Some('1'..='9') => {
+                while let Some('0'..='9') = self.peek() {
+                    self.bump();
+                }
+            }
+            _ => return self.error("expected an integer"),
+        }
+        match self.query[start..self.cursor].parse::<i64>() {
+            Ok(val) if val.abs() <= MAX_SAFE_INT => Ok(val),
+            _ => {
+                self.cursor = start;
+                self.error("integer is out of range")
+            }
+        }
+    }
+
+    /// parses a single or double quoted string, with the escapes from RFC 9535 section 2.3.1.1
+    fn parse_string_literal(&mut self) -> Result<String, PathError> {
+        let quote = self.bump().unwrap();
+        let mut decoded = String::new();
+        loop {
+            let escape_start = self.cursor;
+            match self.bump() {
+                None => return self.error("unterminated string literal"),
+                Some(c) if c == quote => return Ok(decoded),
+                Some('\\') => {
+                    let escaped = match self.bump() {
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('/') => '/',
+                        Some('\\') => '\\',
+                        Some(c) if c == quote => c,
+                        Some('u') => self.parse_unicode_escape()?,
+                        _ => {
+                            self.cursor = escape_start;
+                            return self.error("invalid escape sequence");
+                        }
+                    };
+                    decoded.push(escaped);
+                }
+                Some(c) if (c as u32) < 0x20 => {
+                    self.cursor = escape_start;
+                    return self.error("control characters must be escaped");
+                }
+                Some(c) => decoded.push(c),
+            }
+        }
+    }
+
+    fn parse_unicode_escape(&mut self) -> Result<char, PathError> {
+        let high = self.parse_hex4()?;
+        if (0xDC00..0xE000).contains(&high) {
+            return self.error("unpaired low surrogate");
+        }
+        if (0xD800..0xDC00).contains(&high) == false {
+            return Ok(std::char::from_u32(high).unwrap());
+        }
+        if self.eat("\\u") == false {
+            return self.error("high surrogate must be followed by a low surrogate");
+        }
+        let low = self.parse_hex4()?;
+        if (0xDC00..0xE000).contains(&low) == false {
+            return self.error("high surrogate must be followed by a low surrogate");
+        }
+        let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+        Ok(std::char::from_u32(code).unwrap())
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, PathError> {
+        let hex = self.query.get(self.cursor..self.cursor + 4).unwrap_or("");
+        if hex.len() != 4 || hex.chars().all(|c| c.is_ascii_hexdigit()) == false {
+            return self.error("expected four hex digits");
+        }
+        self.cursor += 4;
+        Ok(u32::from_str_radix(hex, 16).unwrap())
+    }
+
+    fn parse_logical_or(&mut self) -> Result<LogicalExpr, PathError> {
+        let mut terms = vec![self.parse_logical_and()?];
+        loop {
+            let save = self.cursor;
+            self.skip_whitespace();
+            if self.eat("||") {
+                self.skip_whitespace();
+                terms.push(self.parse_logical_and()?);
+            } else {
+                self.cursor = save;
+                break;
+            }
+        }
+        if terms.len() == 1 {
+            Ok(terms.pop().unwrap())
+        } else {
+            Ok(LogicalExpr::Or(terms))
+        }
+    }
+
+    fn parse_logical_and(&mut self) -> Result<LogicalExpr, PathError> {
+        let mut terms = vec![self.parse_basic_expr()?];
+        loop {
+            let save = self.cursor;
+            self.skip_whitespace();
+            if self.eat("&&") {
+                self.skip_whitespace();
+                terms.push(self.parse_basic_expr()?);
+            } else {
+                self.cursor = save;
+                break;
+            }
+        }
+        if terms.len() == 1 {
+            Ok(terms.pop().unwrap())
+        } else {
+            Ok(LogicalExpr::And(terms))
+        }
+    }
+
+    fn parse_basic_expr(&mut self) -> Result<LogicalExpr, PathError> {
+        if self.eat("!") {
+            self.skip_whitespace();
+            let negated = if self.peek() == Some('(') {
+                self.parse_paren_expr()?
+            } else {
+                let start = self.cursor;
+                let operand = self.parse_comparable()?;
+                self.to_test_expr(operand, start)?
+            };
+            return Ok(LogicalExpr::Not(Box::new(negated)));
+        }
+        if self.peek() == Some('(') {
+            return self.parse_paren_expr();
+        }
+
+        let start = self.cursor;
+        let left = self.parse_comparable()?;
+        let save = self.cursor;
+        self.skip_whitespace();
+        let op = match self.parse_compare_op() {
+            Some(op) => op,
+            None => {
+                self.cursor = save;
+                return self.to_test_expr(left, start);
+            }
+        };
+        self.skip_whitespace();
+        let right_start = self.cursor;
+        let right = self.parse_comparable()?;
+        self.check_comparable(&left, start)?;
+        self.check_comparable(&right, right_start)?;
+        Ok(LogicalExpr::Compare(left, op, right))
+    }
+
+    fn parse_paren_expr(&mut self) -> Result<LogicalExpr, PathError> {
+        self.bump();
+        self.skip_whitespace();
+        let expr = self.parse_logical_or()?;
+        self.skip_whitespace();
+        if self.eat(")") == false {
+            return self.error("expected `)`");
+        }
+        Ok(expr)
+    }
+
+    fn parse_compare_op(&mut self) -> Option<CompareOp> {
+        // two character operators have to be tried first
+        let ops = [
+            ("==", CompareOp::Eq),
+            ("!=", CompareOp::Ne),
+            ("<=", CompareOp::Le),
+            (">=", CompareOp::Ge),
+            ("<", CompareOp::Lt),
+            (">", CompareOp::Gt),
+        ];
+        for &(text, op) in ops.iter() {
+            if self.eat(text) {
+                return Some(op);
+            }
+        }
+        None
+    }
+
+    /// turns an operand that isn't being compared into a test expression, if that's allowed
+    fn to_test_expr(&self, operand: Comparable, start: usize) -> Result<LogicalExpr, PathError> {
+        match operand {
+            Comparable::Query(query) => Ok(LogicalExpr::Exists(query)),
+            Comparable::Function(func) if func.function.result() == ResultType::Logical => {
+                Ok(LogicalExpr::Function(func))
+            }
+            Comparable::Function(_) => Err(PathError {
+                offset: start,
+                message: String::from("the result of this function must be compared"),
+            }),
+            Comparable::Literal(_) => Err(PathError {
+                offset: start,
+                message: String::from("a literal must be compared with something"),
+            }),
+        }
+    }
+
+    fn check_comparable(&self, operand: &Comparable, start: usize) -> Result<(), PathError> {
+        let message = match operand {
+            Comparable::Query(query) if query.is_singular() == false => {
+                "only singular queries can be compared"
+            }
+            Comparable::Function(func) if func.function.result() != ResultType::Value => {
+                "this function doesn't produce a value that can be compared"
+            }
+            _ => return Ok(()),
+        };
+        Err(PathError {
+            offset: start,
+            message: message.to_string(),
+        })
+    }
+
+    fn parse_comparable(&mut self) -> Result<Comparable, PathError> {
+        match self.peek() {
+            Some('@') | Some('$') => Ok(Comparable::Query(self.parse_query()?)),
+            Some('\'') | Some('\"') => Ok(Comparable::Literal(JsonValue::String(
+                self.parse_string_literal()?,
+            ))),
+            Some('-') | Some('0'..='9') => {
+                let bytes = self.query.as_bytes();
+                match scan_number(bytes, self.cursor) {
+                    Some(ubound) => {
+                        let number = JsonNumber::parse(&self.query[self.cursor..ubound]).unwrap();
+                        self.cursor = ubound;
+                        Ok(Comparable::Literal(JsonValue::Number(number)))
+                    }
+                    None => self.error("invalid number literal"),
+                }
+            }
+            Some(c) if c.is_ascii_lowercase() => {
+                let start = self.cursor;
+                while let Some(c) = self.peek() {
+                    if c.is_ascii_lowercase() == false && c.is_ascii_digit() == false && c != '_' {
+                        break;
+                    }
+                    self.bump();
+                }
+                let name = &self.query[start..self.cursor];
+                if self.peek() == Some('(') {
+                    return Ok(Comparable::Function(self.parse_function(name, start)?));
+                }
+                match name {
+                    "true" => Ok(Comparable::Literal(JsonValue::Bool(true))),
+                    "false" => Ok(Comparable::Literal(JsonValue::Bool(false))),
+                    "null" => Ok(Comparable::Literal(JsonValue::Null)),
+                    _ => {
+                        self.cursor = start;
+                        self.error("expected a literal, query or function call")
+                    }
+                }
+            }
+            _ => self.error("expected a literal, query or function call"),
+        }
+    }
+
+    fn parse_function(&mut self, name: &str, start: usize) -> Result<FunctionExpr, PathError> {
+        let function = match Function::from_name(name) {
+            Some(function) => function,
+            None => {
+                self.cursor = start;
+                return self.error("unknown function");
+            }
+        };
+        if cfg!(feature = "extras") == false {
+            if let Function::Match | Function::Search = function {
+                self.cursor = start;
+                return self.error("`match()` and `search()` need the `extras` feature");
+            }
+        }
+
+        self.bump();
+        self.skip_whitespace();
+        let mut args = Vec::new();
+        let mut arg_starts = Vec::new();
+        if self.eat(")") == false {
+            loop {
+                arg_starts.push(self.cursor);
+                args.push(self.parse_comparable()?);
+                self.skip_whitespace();
+                if self.eat(")") {
+                    break;
+                }
+                if self.eat(",") == false {
+                    return self.error("expected `,` or `)`");
+                }
+                self.skip_whitespace();
+            }
+        }
+
+        let params = function.params();
+        if args.len() != params.len() {
+            self.cursor = start;
+            let message = format!("`{}()` takes {} argument(s)", name, params.len());
+            return self.error(&message);
+        }
+        for ((arg, &param), &arg_start) in args.iter().zip(params).zip(arg_starts.iter()) {
+            let message = match (param, arg) {
+                (ParamType::Value, Comparable::Query(query)) if query.is_singular() == false => {
+                    "expected a value, only singular queries can be used here"
+                }
+                (ParamType::Value, Comparable::Function(func))
+                    if func.function.result() != ResultType::Value =>
+                {
+                    "expected a value, this function produces a logical result"
+                }
+                (ParamType::Nodes, Comparable::Query(_)) | (ParamType::Value, _) => continue,
+                (ParamType::Nodes, _) => "expected a query",
+            };
+            return Err(PathError {
+                offset: arg_start,
+                message: message.to_string(),
+            });
+        }
+        Ok(FunctionExpr { function, args })
+    }
+}
+
+fn is_name_first(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || c as u32 >= 0x80
+}
+
+enum FunctionResult<'a> {
+    Value(Option<Cow<'a, JsonValue>>),
+    Logical(bool),
+}
+
+/// State for a single `select(..)` call
+struct Context<'a> {
+    root: &'a JsonValue,
+    /// regexes get compiled once per pattern instead of once per node
+    #[cfg(feature = "extras")]
+    regex_cache: RefCell<HashMap<(String, bool), Option<Regex>>>,
+}
+
+impl<'a> Context<'a> {
+    fn new(root: &'a JsonValue) -> Self {
+        Self {
+            root,
+            #[cfg(feature = "extras")]
+            regex_cache: RefCell::new(HashMap::new()),
+        }
+    }
+
+    fn apply_segments(
+        &self,
+        segments: &[Segment],
+        mut nodes: Vec<PathNode<'a>>,
+    ) -> Vec<PathNode<'a>> {
+        for segment in segments {
+            let mut output = Vec::new();
+            for node in nodes.iter() {
+                match segment {
+                    Segment::Child(selectors) => {
+                        for selector in selectors {
+                            self.apply_selector(selector, node, &mut output);
+                        }
+                    }
+                    Segment::Descendant(selectors) => {
+                        let mut descendants = Vec::new();
+                        collect_descendants(node.clone(), &mut descendants);
+                        for descendant in descendants.iter() {
+                            for selector in selectors {
+                                self.apply_selector(selector, descendant, &mut output);
+                            }
+                        }
+                    }
+                }
+            }
+            nodes = output;
+        }
+        nodes
+    }
+
+    fn apply_selector(
+        &self,
+        selector: &Selector,
+        node: &PathNode<'a>,
+        output: &mut Vec<PathNode<'a>>,
+    ) {
+        match (selector, node.value) {
+            (Selector::Name(name), JsonValue::Object(props)) => {
+                if let Some((key, val)) = props.iter().filter(|(key, _)| key == name).next() {
+                    output.push(node.child(PathElement::Key(key.clone()), val));
+                }
+            }
+            (Selector::Wildcard, _) => push_children(node, output),
+            (&Selector::Index(index), JsonValue::Array(items)) => {
+                let len = items.len() as i64;
+                let index = if index < 0 { len + index } else { index };
+                if index >= 0 && index < len {
+                    let index = index as usize;
+                    output.push(node.child(PathElement::Index(index), &items[index]));
+                }
+            }
+            (&Selector::Slice { start, end, step }, JsonValue::Array(items)) => {
+                for index in slice_indices(items.len() as i64, start, end, step) {
+                    output.push(node.child(PathElement::Index(index), &items[index]));
+                }
+            }
+            (Selector::Filter(expr), _) => {
+                let mut children = Vec::new();
+                push_children(node, &mut children);
+                for child in children {
+                    if self.test(expr, child.value) {
+                        output.push(child);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn test(&self, expr: &LogicalExpr, current: &'a JsonValue) -> bool {
+        match expr {
+            LogicalExpr::Or(terms) => terms.iter().any(|term| self.test(term, current)),
+            LogicalExpr::And(terms) => terms.iter().all(|term| self.test(term, current)),
+            LogicalExpr::Not(term) => self.test(term, current) == false,
+            LogicalExpr::Compare(left, op, right) => {
+                let left = self.eval_value(left, current);
+                let right = self.eval_value(right, current);
+                compare(left.as_deref(), *op, right.as_deref())
+            }
+            LogicalExpr::Exists(query) => self.eval_query(query, current).is_empty() == false,
+            LogicalExpr::Function(func) => match self.eval_function(func, current) {
+                FunctionResult::Logical(result) => result,
+                FunctionResult::Value(_) => false,
+            },
+        }
+    }
+
+    fn eval_query(&self, query: &Query, current: &'a JsonValue) -> Vec<PathNode<'a>> {
+        let start = PathNode {
+            location: Vec::new(),
+            value: if query.relative { current } else { self.root },
+        };
+        self.apply_segments(&query.segments, vec![start])
+    }
+
+    /// evaluates something in a value context, `None` is the special 'Nothing' value from the RFC
+    fn eval_value(
+        &self,
+        operand: &Comparable,
+        current: &'a JsonValue,
+    ) -> Option<Cow<'a, JsonValue>> {
+        match operand {
+            Comparable::Literal(val) => Some(Cow::Owned(val.clone())),
+            Comparable::Query(query) => {
+                let nodes = self.eval_query(query, current);
+                nodes.first().map(|node| Cow::Borrowed(node.value))
+            }
+            Comparable::Function(func) => match self.eval_function(func, current) {
+                FunctionResult::Value(val) => val,
+                FunctionResult::Logical(_) => None,
+            },
+        }
+    }
+
+    fn eval_nodes(&self, operand: &Comparable, current: &'a JsonValue) -> Vec<PathNode<'a>> {
+        match operand {
+            Comparable::Query(query) => self.eval_query(query, current),
+            _ => Vec::new(),
+        }
+    }
+
+    fn eval_function(&self, func: &FunctionExpr, current: &'a JsonValue) -> FunctionResult<'a> {
+        let args = &func.args;
+        match func.function {
+            Function::Length => {
+                let len = match self.eval_value(&args[0], current).as_deref() {
+                    Some(JsonValue::String(val)) => Some(val.chars().count()),
+                    Some(JsonValue::Array(items)) => Some(items.len()),
+                    Some(JsonValue::Object(props)) => Some(props.len()),
+                    _ => None,
+                };
+                FunctionResult::Value(len.map(|len| Cow::Owned(number(len))))
+            }
+            Function::Count => {
+                let count = self.eval_nodes(&args[0], current).len();
+                FunctionResult::Value(Some(Cow::Owned(number(count))))
+            }
+            Function::Value => {
+                let nodes = self.eval_nodes(&args[0], current);
+                if nodes.len() == 1 {
+                    FunctionResult::Value(Some(Cow::Borrowed(nodes[0].value)))
+                } else {
+                    FunctionResult::Value(None)
+                }
+            }
+            Function::Match | Function::Search => {
+                let text = self.eval_value(&args[0], current);
+                let pattern = self.eval_value(&args[1], current);
+                let result = match (text.as_deref(), pattern.as_deref()) {
+                    (Some(JsonValue::String(text)), Some(JsonValue::String(pattern))) => {
+                        self.regex_matches(text, pattern, func.function == Function::Match)
+                    }
+                    _ => false,
+                };
+                FunctionResult::Logical(result)
+            }
+        }
+    }
+
+    #[cfg(feature = "extras")]
+    fn regex_matches(&self, text: &str, pattern: &str, full_match: bool) -> bool {
+        let mut cache = self.regex_cache.borrow_mut();
+        let regex = cache
+            .entry((pattern.to_string(), full_match))
+            .or_insert_with(|| compile_iregexp(pattern, full_match));
+        match regex {
+            Some(regex) => regex.is_match(text),
+            None => false,
+        }
+    }
+
+    #[cfg(not(feature = "extras"))]
+    fn regex_matches(&self, _text: &str, _pattern: &str, _full_match: bool) -> bool {
+        // unreachable in practice, the parser rejects match() and search() without the feature
+        false
+    }
+}
+
+/// # Description
+/// Translates an I-Regexp (RFC 9485) into the regex crate's syntax
+/// # Comments
+/// - The two are almost the same, the main difference is that `.` in I-Regexp doesn't match `\r` either
+/// - I-Regexp has no anchors, so `^` and `$` outside a class are escaped to match themselves
+/// - `match()` has to match the whole string so the pattern gets anchored
+#[cfg(feature = "extras")]
+fn compile_iregexp(pattern: &str, full_match: bool) -> Option<Regex> {
+    let mut translated = String::with_capacity(pattern.len());
+    let mut in_class = false;
+    let mut char_stream = pattern.chars();
+    while let Some(c) = char_stream.next() {
+        match c {
+            '\\' => {
+                translated.push('\\');
+                translated.push(char_stream.next()?);
+            }
+            '[' if in_class == false => {
+                in_class = true;
+                translated.push(c);
+            }
+            ']' if in_class => {
+                in_class = false;
+                translated.push(c);
+            }
+            '.' if in_class == false => translated.push_str("[^\\n\\r]"),
+            '^' | '$' if in_class == false => {
+                translated.push('\\');
+                translated.push(c);
+            }
+            c => translated.push(c),
+        }
+    }
+    if full_match {
+        translated = format!("^(?:{})$", translated);
+    }
+    Regex::new(&translated).ok()
+}
+
+fn number(val: usize) -> JsonValue {
+    JsonValue::Number(JsonNumber::from(val as u64))
+}
+
+fn push_children<'a>(node: &PathNode<'a>, output: &mut Vec<PathNode<'a>>) {
+    match node.value {
+        JsonValue::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                output.push(node.child(PathElement::Index(index), item));
+            }
+        }
+        JsonValue::Object(props) => {
+            for (key, val) in props.iter() {
+                output.push(node.child(PathElement::Key(key.clone()), val));
+            }
+        }
+        _ => (),
+    }
+}
+
+/// the node itself followed by all of its descendants, in document order
+fn collect_descendants<'a>(node: PathNode<'a>, output: &mut Vec<PathNode<'a>>) {
+    let mut children = Vec::new();
+    push_children(&node, &mut children);
+    output.push(node);
+    for child in children {
+        collect_descendants(child, output);
+    }
+}
+
+/// the array slice algorithm from RFC 9535 section 2.3.4.2.2
+fn slice_indices(len: i64, start: Option<i64>, end: Option<i64>, step: Option<i64>) -> Vec<usize> {
+    let step = step.unwrap_or(1);
+    let normalize = |index: i64| if index >= 0 { index } else { len + index };
+    let mut indices = Vec::new();
+    if step > 0 {
+        let lower = normalize(start.unwrap_or(0)).max(0).min(len);
+        let upper = normalize(end.unwrap_or(len)).max(0).min(len);
+        let mut index = lower;
+        while index < upper {
+            indices.push(index as usize);
+            index += step;
+        }
+    } else if step < 0 {
+        let upper = start.map(normalize).unwrap_or(len - 1).max(-1).min(len - 1);
+        let lower = end.map(normalize).unwrap_or(-len - 1).max(-1).min(len - 1);
+        let mut index = upper;
+        while lower < index {
+            indices.push(index as usize);
+            index += step;
+        }
+    }
+    indices
+}
+
+fn compare(left: Option<&JsonValue>, op: CompareOp, right: Option<&JsonValue>) -> bool {
+    match op {
+        CompareOp::Eq => is_equal(left, right),
+        CompareOp::Ne => is_equal(left, right) == false,
+        CompareOp::Lt => is_less(left, right),
+        CompareOp::Le => is_less(left, right) || is_equal(left, right),
+        CompareOp::Gt => is_less(right, left),
+        CompareOp::Ge => is_less(right, left) || is_equal(left, right),
+    }
+}
+
+fn is_equal(left: Option<&JsonValue>, right: Option<&JsonValue>) -> bool {
+    match (left, right) {
+        (None, None) => true,
+        (Some(left), Some(right)) => values_equal(left, right),
+        _ => false,
+    }
+}
+
+fn is_less(left: Option<&JsonValue>, right: Option<&JsonValue>) -> bool {
+    match (left, right) {
+        (Some(JsonValue::Number(left)), Some(JsonValue::Number(right))) => {
+            left.cmp_value(right) == Ordering::Less
+        }
+        // rust compares strings byte by byte which for utf-8 is the same as comparing code points
+        (Some(JsonValue::String(left)), Some(JsonValue::String(right))) => left < right,
+        _ => false,
+    }
+}
+
+/// Equality as defined by the RFC, numbers compare by value (`1 == 1.0`) and objects ignore member order
+pub(crate) fn values_equal(left: &JsonValue, right: &JsonValue) -> bool {
+    match (left, right) {
+        (JsonValue::Number(left), JsonValue::Number(right)) => {
+            left.cmp_value(right) == Ordering::Equal
+        }
+        (JsonValue::Array(left), JsonValue::Array(right)) => {
+            left.len() == right.len()
+                && left
+                    .iter()
+                    .zip(right.iter())
+                    .all(|(left, right)| values_equal(left, right))
+        }
+        (JsonValue::Object(left), JsonValue::Object(right)) => {
+            left.len() == right.len()
+                && left.iter().all(|(key, left_val)| {
+                    right.iter().any(|(other_key, right_val)| {
+                        key == other_key && values_equal(left_val, right_val)
+                    })
+                })
+        }
+        (left, right) => left == right,
+    }
+}
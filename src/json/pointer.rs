@@ -0,0 +1,196 @@
+use std::fmt;
+
+use super::value::JsonValue;
+
+/// # Description
+/// Why a JSON Pointer (RFC 6901) couldn't be resolved
+/// # Comments
+/// - `at` is always the part of the pointer that *did* resolve, `segment` is the reference token that failed
+#[derive(Clone, PartialEq, Debug)]
+pub enum PointerError {
+    /// The pointer itself is malformed, like a missing leading `/` or a `~` not followed by `0` or `1`
+    Syntax {
+        pointer: String,
+        reason: &'static str,
+    },
+    /// The object at `at` has no property named `segment`
+    MissingKey { at: String, segment: String },
+    /// `segment` isn't a valid index into the array at `at`, or is past its end
+    InvalidIndex { at: String, segment: String },
+    /// The value at `at` is a scalar so it can't be walked into
+    NotAContainer { at: String, segment: String },
+}
+
+impl fmt::Display for PointerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Syntax { pointer, reason } => {
+                write!(f, "invalid pointer {:?}: {}", pointer, reason)
+            }
+            Self::MissingKey { at, segment } => {
+                write!(f, "no property {:?} in the object at {:?}", segment, at)
+            }
+            Self::InvalidIndex { at, segment } => {
+                write!(
+                    f,
+                    "{:?} is not a valid index into the array at {:?}",
+                    segment, at
+                )
+            }
+            Self::NotAContainer { at, segment } => write!(
+                f,
+                "can't look up {:?}, the value at {:?} is not an object or array",
+                segment, at
+            ),
+        }
+    }
+}
+
+/// # Description
+/// Splits `pointer` into its reference tokens, decoding `~1` into `/` and `~0` into `~`
+/// # Comments
+/// - The empty pointer `""` refers to the whole document and has no tokens
+pub fn parse_pointer(pointer: &str) -> Result<Vec<String>, PointerError> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    if pointer.starts_with('/') == false {
+        return Err(PointerError::Syntax {
+            pointer: pointer.to_string(),
+            reason: "a non-empty pointer must start with `/`",
+        });
+    }
+    pointer[1..]
+        .split('/')
+        .map(|token| unescape_token(token, pointer))
+        .collect()
+}
+
+fn unescape_token(token: &str, pointer: &str) -> Result<String, PointerError> {
+    let mut decoded = String::with_capacity(token.len());
+    let mut char_stream = token.chars();
+    while let Some(c) = char_stream.next() {
+        if c != '~' {
+            decoded.push(c);
+            continue;
+        }
+        match char_stream.next() {
+            Some('0') => decoded.push('~'),
+            Some('1') => decoded.push('/'),
+            _ => {
+                return Err(PointerError::Syntax {
+                    pointer: pointer.to_string(),
+                    reason: "`~` must be followed by `0` or `1`",
+                })
+            }
+        }
+    }
+    Ok(decoded)
+}
+
+/// Escapes `token` (`~` becomes `~0` and `/` becomes `~1`) and appends it to `pointer` as a new segment
+pub fn push_token(pointer: &mut String, token: &str) {
+    pointer.push('/');
+    for c in token.chars() {
+        match c {
+            '~' => pointer.push_str("~0"),
+            '/' => pointer.push_str("~1"),
+            c => pointer.push(c),
+        }
+    }
+}
+
+/// Builds a pointer out of unescaped reference tokens
+pub fn to_pointer<S: AsRef<str>>(tokens: &[S]) -> String {
+    let mut pointer = String::new();
+    for token in tokens {
+        push_token(&mut pointer, token.as_ref());
+    }
+    pointer
+}
+
+/// # Description
+/// Parses an array index the way RFC 6901 wants it: `0` or a number without leading zeros.
+/// # Comments
+/// `-` (one past the end) is *not* accepted here since it never refers to an existing element
+pub fn parse_index(segment: &str) -> Option<usize> {
+    let valid = match segment.as_bytes() {
+        [b'0'] => true,
+        [b'1'..=b'9', rest @ ..] => rest.iter().all(u8::is_ascii_digit),
+        _ => false,
+    };
+    if valid {
+        segment.parse().ok()
+    } else {
+        None
+    }
+}
+
+impl JsonValue {
+    /// # Description
+    /// Resolves a JSON Pointer (RFC 6901) like `/config/servers/0/host` against `self`
+    /// # Returns
+    /// The value the pointer refers to, or an error naming the segment that couldn't be resolved
+    pub fn pointer(&self, pointer: &str) -> Result<&JsonValue, PointerError> {
+        let tokens = parse_pointer(pointer)?;
+        let mut current = self;
+        for (depth, segment) in tokens.iter().enumerate() {
+            current = match current {
+                Self::Object(props) => props
+                    .iter()
+                    .filter(|(key, _)| key == segment)
+                    .next()
+                    .map(|(_, val)| val)
+                    .ok_or_else(|| PointerError::MissingKey {
+                        at: to_pointer(&tokens[..depth]),
+                        segment: segment.clone(),
+                    })?,
+                Self::Array(items) => parse_index(segment)
+                    .and_then(|index| items.get(index))
+                    .ok_or_else(|| PointerError::InvalidIndex {
+                        at: to_pointer(&tokens[..depth]),
+                        segment: segment.clone(),
+                    })?,
+                _ => {
+                    return Err(PointerError::NotAContainer {
+                        at: to_pointer(&tokens[..depth]),
+                        segment: segment.clone(),
+                    })
+                }
+            };
+        }
+        Ok(current)
+    }
+
+    /// Like `pointer(..)` but hands back a mutable reference so the value can be updated in place
+    pub fn pointer_mut(&mut self, pointer: &str) -> Result<&mut JsonValue, PointerError> {
+        let tokens = parse_pointer(pointer)?;
+        let mut current = self;
+        for (depth, segment) in tokens.iter().enumerate() {
+            current = match current {
+                Self::Object(props) => props
+                    .iter_mut()
+                    .filter(|(key, _)| key == segment)
+                    .next()
+                    .map(|(_, val)| val)
+                    .ok_or_else(|| PointerError::MissingKey {
+                        at: to_pointer(&tokens[..depth]),
+                        segment: segment.clone(),
+                    })?,
+                Self::Array(items) => parse_index(segment)
+                    .and_then(move |index| items.get_mut(index))
+                    .ok_or_else(|| PointerError::InvalidIndex {
+                        at: to_pointer(&tokens[..depth]),
+                        segment: segment.clone(),
+                    })?,
+                _ => {
+                    return Err(PointerError::NotAContainer {
+                        at: to_pointer(&tokens[..depth]),
+                        segment: segment.clone(),
+                    })
+                }
+            };
+        }
+        Ok(current)
+    }
+}
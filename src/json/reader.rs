@@ -0,0 +1,472 @@
+use std::{fmt, io};
+
+use super::{ast::Expect, lexer::*, number::JsonNumber, value::JsonValue};
+use crate::limits::{Limit, Limits};
+
+const DEFAULT_CHUNK_SIZE: usize = 8 * 1024;
+
+/// One step of a json document, as seen by `JsonReader`
+#[derive(Clone, PartialEq, Debug)]
+pub enum JsonEvent {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    /// A property name, the next event is its value
+    Key(String),
+    /// A scalar value, always `Null`, `Bool`, `Number` or `String`
+    Value(JsonValue),
+}
+
+#[derive(Debug)]
+pub enum ReaderError {
+    Io(io::Error),
+    Syntax(Diagnostic),
+    /// The document went over one of the reader's `Limits`, `diag` points at where it happened
+    LimitExceeded {
+        limit: Limit,
+        diag: Diagnostic,
+    },
+}
+
+impl From<io::Error> for ReaderError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl fmt::Display for ReaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "io error: {}", err),
+            Self::Syntax(diag) | Self::LimitExceeded { diag, .. } => write!(
+                f,
+                "{} at line {}, column {}",
+                diag, diag.pos.line, diag.pos.column
+            ),
+        }
+    }
+}
+
+/// # Description
+/// A pull based json reader, call `next_event()` (or iterate) to walk the document one event at a time.
+/// # Comments
+/// - Input is read from `reader` in fixed size chunks, so the whole document never has to be in memory.
+/// The only thing that grows with the document is the stack of open brackets (plus the current token),
+/// which means memory is bounded by nesting depth instead of document size.
+/// - Runs the same PDA as `JsonAst::parse(..)`, so it accepts exactly the same documents
+/// - After an error the reader is done, every following call returns `Ok(None)`
+/// - `with_limits(..)` caps the document the same way `ParseOptions::limits` does for `JsonAst`. Strings
+/// and numbers are measured while they are read, so an oversized token is rejected before it's buffered whole
+pub struct JsonReader<R> {
+    reader: R,
+    chunk: Vec<u8>,
+    cursor: usize,
+    filled: usize,
+    /// position of `chunk[cursor]` in the document
+    pos: Position,
+    parent_stack: Vec<char>,
+    expect: Expect,
+    /// holds the bytes of the current string/number token
+    scratch: Vec<u8>,
+    limits: Limits,
+    /// tokens read so far, checked against `Limits::max_tokens`
+    tokens: usize,
+    failed: bool,
+}
+
+impl<R: io::Read> JsonReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_chunk_size(reader, DEFAULT_CHUNK_SIZE)
+    }
+
+    pub fn with_chunk_size(reader: R, chunk_size: usize) -> Self {
+        Self {
+            reader,
+            chunk: vec![0; chunk_size.max(1)],
+            cursor: 0,
+            filled: 0,
+            pos: Position {
+                offset: 0,
+                line: 1,
+                column: 1,
+            },
+            parent_stack: Vec::new(),
+            expect: Expect::Value,
+            scratch: Vec::new(),
+            limits: Limits::default(),
+            tokens: 0,
+            failed: false,
+        }
+    }
+
+    /// Rejects documents that go over `limits`, by default nothing is limited
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// How many objects/arrays are currently open
+    pub fn depth(&self) -> usize {
+        self.parent_stack.len()
+    }
+
+    /// Where the reader is in the document
+    pub fn position(&self) -> Position {
+        self.pos
+    }
+
+    /// # Description
+    /// Reads the next event from the document
+    /// # Returns
+    /// `Ok(None)` once the top-level value is finished and only whitespace is left
+    pub fn next_event(&mut self) -> Result<Option<JsonEvent>, ReaderError> {
+        if self.failed {
+            return Ok(None);
+        }
+        let result = self.next_event_helper();
+        if result.is_err() {
+            self.failed = true;
+        }
+        result
+    }
+
+    fn next_event_helper(&mut self) -> Result<Option<JsonEvent>, ReaderError> {
+        loop {
+            self.skip_whitespace()?;
+            let start = self.pos;
+            let in_object = self.parent_stack.last() == Some(&'{');
+            let expect = self.expect;
+
+            let c = match (expect, self.peek_byte()?) {
+                (Expect::End, None) => return Ok(None),
+                (_, None) => {
+                    let found = String::from("end of input");
+                    return Err(Self::syntax_error(start, expect.describe(in_object), found));
+                }
+                (_, Some(c)) => c,
+            };
+            if self.tokens >= self.limits.max_tokens {
+                let expected = format!("at most {} tokens", self.limits.max_tokens);
+                let found = Self::describe_byte(c);
+                return Err(Self::limit_error(Limit::Tokens, start, &expected, found));
+            }
+            self.tokens += 1;
+
+            match (expect, c) {
+                (Expect::Value, b'{')
+                | (Expect::ValueOrClose, b'{')
+                | (Expect::Value, b'[')
+                | (Expect::ValueOrClose, b'[') => {
+                    if self.parent_stack.len() >= self.limits.max_depth {
+                        let expected =
+                            format!("at most {} levels of nesting", self.limits.max_depth);
+                        let found = Self::describe_byte(c);
+                        return Err(Self::limit_error(Limit::Depth, start, &expected, found));
+                    }
+                    self.advance();
+                    if c == b'{' {
+                        self.parent_stack.push('{');
+                        self.expect = Expect::KeyOrClose;
+                        return Ok(Some(JsonEvent::StartObject));
+                    } else {
+                        self.parent_stack.push('[');
+                        self.expect = Expect::ValueOrClose;
+                        return Ok(Some(JsonEvent::StartArray));
+                    }
+                }
+
+                (Expect::Value, _) | (Expect::ValueOrClose, _) if Self::starts_scalar(c) => {
+                    let value = self.read_scalar(c)?;
+                    self.expect = self.after_value();
+                    return Ok(Some(JsonEvent::Value(value)));
+                }
+
+                (Expect::Key, b'\"') | (Expect::KeyOrClose, b'\"') => {
+                    let key = self.read_string()?;
+                    self.expect = Expect::Colon;
+                    return Ok(Some(JsonEvent::Key(key)));
+                }
+
+                (Expect::Colon, b':') => {
+                    self.advance();
+                    self.expect = Expect::Value;
+                }
+
+                (Expect::CommaOrClose, b',') => {
+                    self.advance();
+                    self.expect = if in_object {
+                        Expect::Key
+                    } else {
+                        Expect::Value
+                    };
+                }
+
+                (Expect::KeyOrClose, b'}')
+                | (Expect::KeyOrClose, b']')
+                | (Expect::ValueOrClose, b'}')
+                | (Expect::ValueOrClose, b']')
+                | (Expect::CommaOrClose, b'}')
+                | (Expect::CommaOrClose, b']') => {
+                    let ob_char = if c == b'}' { '{' } else { '[' };
+                    if self.parent_stack.last() != Some(&ob_char) {
+                        let found = Self::describe_byte(c);
+                        return Err(Self::syntax_error(start, expect.describe(in_object), found));
+                    }
+                    self.advance();
+                    self.parent_stack.pop();
+                    self.expect = self.after_value();
+                    if c == b'}' {
+                        return Ok(Some(JsonEvent::EndObject));
+                    } else {
+                        return Ok(Some(JsonEvent::EndArray));
+                    }
+                }
+
+                (_, _) => {
+                    let found = Self::describe_byte(c);
+                    return Err(Self::syntax_error(start, expect.describe(in_object), found));
+                }
+            }
+        }
+    }
+
+    fn after_value(&self) -> Expect {
+        if self.parent_stack.is_empty() {
+            Expect::End
+        } else {
+            Expect::CommaOrClose
+        }
+    }
+
+    fn starts_scalar(c: u8) -> bool {
+        match c {
+            b'\"' | b'-' | b'0'..=b'9' | b't' | b'f' | b'n' => true,
+            _ => false,
+        }
+    }
+
+    fn read_scalar(&mut self, c: u8) -> Result<JsonValue, ReaderError> {
+        match c {
+            b'\"' => Ok(JsonValue::String(self.read_string()?)),
+            b't' => self.read_literal("true", JsonValue::Bool(true)),
+            b'f' => self.read_literal("false", JsonValue::Bool(false)),
+            b'n' => self.read_literal("null", JsonValue::Null),
+            _ => Ok(JsonValue::Number(self.read_number()?)),
+        }
+    }
+
+    fn read_literal(&mut self, literal: &str, value: JsonValue) -> Result<JsonValue, ReaderError> {
+        let start = self.pos;
+        let mut matched = String::new();
+        for expected in literal.bytes() {
+            match self.peek_byte()? {
+                Some(c) if c == expected => {
+                    self.advance();
+                    matched.push(c as char);
+                }
+                _ => {
+                    let found = format!("`{}`", matched);
+                    return Err(Self::syntax_error(start, "a value", found));
+                }
+            }
+        }
+        Ok(value)
+    }
+
+    fn read_number(&mut self) -> Result<JsonNumber, ReaderError> {
+        let start = self.pos;
+        self.scratch.clear();
+        while let Some(c) = self.peek_byte()? {
+            match c {
+                b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E' => {
+                    self.scratch.push(c);
+                    self.check_token_len(start)?;
+                    self.advance();
+                }
+                _ => break,
+            }
+        }
+        let lexeme = String::from_utf8_lossy(&self.scratch);
+        match scan_number(&self.scratch, 0) {
+            Some(ubound) if ubound == self.scratch.len() => Ok(JsonNumber::parse(&lexeme).unwrap()),
+            _ => {
+                let found = format!("`{}`", lexeme);
+                Err(Self::syntax_error(start, "a number", found))
+            }
+        }
+    }
+
+    /// reads a string token (the reader must be on the opening quote) and decodes it
+    fn read_string(&mut self) -> Result<String, ReaderError> {
+        let start = self.pos;
+        self.advance();
+        self.scratch.clear();
+        loop {
+            self.check_token_len(start)?;
+            let char_pos = self.pos;
+            match self.next_byte()? {
+                Some(b'\"') => break,
+                Some(b'\\') => {
+                    self.scratch.push(b'\\');
+                    let hex_digits = match self.next_byte()? {
+                        Some(c @ b'\"') | Some(c @ b'\\') | Some(c @ b'/') | Some(c @ b'b')
+                        | Some(c @ b'f') | Some(c @ b'n') | Some(c @ b'r') | Some(c @ b't') => {
+                            self.scratch.push(c);
+                            0
+                        }
+                        Some(b'u') => {
+                            self.scratch.push(b'u');
+                            4
+                        }
+                        _ => {
+                            let found = String::from("invalid escape sequence");
+                            return Err(Self::syntax_error(
+                                char_pos,
+                                "a valid escape sequence",
+                                found,
+                            ));
+                        }
+                    };
+                    for _ in 0..hex_digits {
+                        match self.next_byte()? {
+                            Some(c) if c.is_ascii_hexdigit() => self.scratch.push(c),
+                            _ => {
+                                let found = String::from("invalid unicode escape");
+                                let expected = "four hex digits after `\\u`";
+                                return Err(Self::syntax_error(char_pos, expected, found));
+                            }
+                        }
+                    }
+                }
+                Some(c) if c < 0x20 => {
+                    let found = format!("unescaped control character U+{:04X}", c);
+                    return Err(Self::syntax_error(
+                        char_pos,
+                        "`\"` to close the string",
+                        found,
+                    ));
+                }
+                Some(c) => self.scratch.push(c),
+                None => {
+                    let found = String::from("end of input");
+                    return Err(Self::syntax_error(start, "`\"` to close the string", found));
+                }
+            }
+        }
+        self.check_token_len(start)?;
+        match std::str::from_utf8(&self.scratch) {
+            Ok(raw) => Ok(unescape(raw)),
+            Err(_) => {
+                let found = String::from("invalid utf-8");
+                Err(Self::syntax_error(start, "a utf-8 string", found))
+            }
+        }
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), ReaderError> {
+        while let Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') = self.peek_byte()? {
+            self.advance();
+        }
+        Ok(())
+    }
+
+    /// looks at the next byte, pulling in a new chunk from the reader when the current one runs out
+    fn peek_byte(&mut self) -> Result<Option<u8>, ReaderError> {
+        if self.cursor == self.filled {
+            self.cursor = 0;
+            self.filled = loop {
+                match self.reader.read(&mut self.chunk) {
+                    Ok(num_read) => break num_read,
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(err) => return Err(err.into()),
+                }
+            };
+            if self.filled == 0 {
+                return Ok(None);
+            }
+        }
+        if self.pos.offset >= self.limits.max_document_bytes {
+            let expected = format!("at most {} bytes", self.limits.max_document_bytes);
+            let found = String::from("a longer document");
+            return Err(Self::limit_error(
+                Limit::DocumentBytes,
+                self.pos,
+                &expected,
+                found,
+            ));
+        }
+        Ok(Some(self.chunk[self.cursor]))
+    }
+
+    fn next_byte(&mut self) -> Result<Option<u8>, ReaderError> {
+        let c = self.peek_byte()?;
+        if c.is_some() {
+            self.advance();
+        }
+        Ok(c)
+    }
+
+    /// moves past the current byte, keeping the line/column up to date
+    fn advance(&mut self) {
+        let c = self.chunk[self.cursor];
+        self.cursor += 1;
+        self.pos.offset += 1;
+        if c == b'\n' {
+            self.pos.line += 1;
+            self.pos.column = 1;
+        } else if c & 0xC0 != 0x80 {
+            // continuation bytes of a multi-byte char don't start a new column
+            self.pos.column += 1;
+        }
+    }
+
+    fn syntax_error(pos: Position, expected: &str, found: String) -> ReaderError {
+        ReaderError::Syntax(Diagnostic {
+            pos,
+            expected: expected.to_string(),
+            found,
+        })
+    }
+
+    /// fails once the string/number token starting at `start` is longer than `Limits::max_string_len`
+    fn check_token_len(&self, start: Position) -> Result<(), ReaderError> {
+        if self.scratch.len() > self.limits.max_string_len {
+            let expected = format!("tokens of at most {} bytes", self.limits.max_string_len);
+            let found = String::from("a longer token");
+            return Err(Self::limit_error(
+                Limit::StringLength,
+                start,
+                &expected,
+                found,
+            ));
+        }
+        Ok(())
+    }
+
+    fn limit_error(limit: Limit, pos: Position, expected: &str, found: String) -> ReaderError {
+        let diag = Diagnostic {
+            pos,
+            expected: expected.to_string(),
+            found,
+        };
+        ReaderError::LimitExceeded { limit, diag }
+    }
+
+    fn describe_byte(c: u8) -> String {
+        match c {
+            b'\"' => String::from("string"),
+            b'-' | b'0'..=b'9' => String::from("number"),
+            c if c.is_ascii_graphic() => format!("`{}`", c as char),
+            c if c.is_ascii() => format!("`{}`", (c as char).escape_debug()),
+            _ => String::from("non-ascii character"),
+        }
+    }
+}
+
+impl<R: io::Read> Iterator for JsonReader<R> {
+    type Item = Result<JsonEvent, ReaderError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
@@ -0,0 +1,730 @@
+use std::{cmp::Ordering, collections::HashMap, fmt};
+
+#[cfg(feature = "extras")]
+use regex::Regex;
+
+use super::{
+    number::JsonNumber,
+    path::values_equal,
+    pointer::push_token,
+    value::JsonValue,
+};
+
+/// Stops schemas like `{"$ref": "#"}` from recursing forever
+const MAX_REF_DEPTH: usize = 64;
+
+const TYPE_NAMES: [&str; 7] = [
+    "null", "boolean", "object", "array", "number", "string", "integer",
+];
+
+/// keywords whose value is a single subschema
+const SCHEMA_KEYWORDS: [&str; 8] = [
+    "additionalProperties",
+    "items",
+    "contains",
+    "propertyNames",
+    "not",
+    "if",
+    "then",
+    "else",
+];
+
+/// keywords whose value is an object of subschemas
+const SCHEMA_MAP_KEYWORDS: [&str; 4] = ["properties", "patternProperties", "$defs", "definitions"];
+
+/// keywords whose value is an array of subschemas
+const SCHEMA_LIST_KEYWORDS: [&str; 4] = ["prefixItems", "allOf", "anyOf", "oneOf"];
+
+/// The schema itself is broken, `pointer` is the location of the offending keyword inside the schema
+#[derive(Clone, PartialEq, Debug)]
+pub struct SchemaError {
+    pub pointer: String,
+    pub reason: String,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid schema at {:?}: {}", self.pointer, self.reason)
+    }
+}
+
+/// # Description
+/// One way an instance failed validation
+/// # Comments
+/// - `instance_path` points at the failing value in the instance
+/// - `schema_path` points at the keyword that failed, following `$ref`s the way they were evaluated
+///   (so `/properties/a/$ref/minimum` rather than `/$defs/positive/minimum`)
+#[derive(Clone, PartialEq, Debug)]
+pub struct Violation {
+    pub instance_path: String,
+    pub schema_path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?}: {} (schema keyword {:?})",
+            self.instance_path, self.message, self.schema_path
+        )
+    }
+}
+
+/// # Description
+/// A JSON Schema (draft 2020-12) ready to validate documents
+/// # Comments
+/// - Supports the core applicators (`allOf`, `anyOf`, `oneOf`, `not`, `if`/`then`/`else`,
+///   `properties`, `patternProperties`, `additionalProperties`, `propertyNames`, `prefixItems`,
+///   `items`, `contains`) and the validation vocabulary (`type`, `enum`, `const`, the numeric and
+///   length bounds, `pattern`, `required`, `uniqueItems`, `multipleOf`)
+/// - `$ref` only works within the same document, as a JSON Pointer fragment (`#/$defs/x`) or an `$anchor`
+/// - `pattern` and `patternProperties` need the `extras` feature since they use regex
+/// - Unknown keywords are ignored, as the spec asks
+pub struct JsonSchema {
+    schema: JsonValue,
+    /// `$anchor` name -> pointer of the schema that declared it
+    anchors: HashMap<String, String>,
+    #[cfg(feature = "extras")]
+    regexes: HashMap<String, Regex>,
+}
+
+impl JsonSchema {
+    /// # Description
+    /// Loads `schema`, checking every keyword this validator understands up front
+    /// # Returns
+    /// An error if a keyword has the wrong type, a `$ref` doesn't resolve or a pattern doesn't compile
+    pub fn new(schema: JsonValue) -> Result<Self, SchemaError> {
+        let mut anchors = HashMap::new();
+        let mut subschemas = Vec::new();
+        collect_subschemas(&schema, String::new(), &mut subschemas)?;
+        // a `$ref` can point anywhere in the document, not just at a keyword that holds subschemas,
+        // and whatever it points to has to be checked (and have its patterns compiled) like the rest
+        let mut k = 0;
+        while k < subschemas.len() {
+            let fragment = subschemas[k]
+                .0
+                .get("$ref")
+                .and_then(JsonValue::as_str)
+                .and_then(|reference| reference.strip_prefix('#'))
+                .and_then(percent_decode);
+            if let Some(fragment) = fragment.filter(|fragment| fragment.starts_with('/')) {
+                let known = subschemas.iter().any(|(_, pointer)| *pointer == fragment);
+                if let (false, Ok(target)) = (known, schema.pointer(&fragment)) {
+                    collect_subschemas(target, fragment, &mut subschemas)?;
+                }
+            }
+            k += 1;
+        }
+        for (subschema, pointer) in subschemas.iter() {
+            if let Some(anchor) = subschema.get("$anchor") {
+                let anchor = anchor.as_str().ok_or_else(|| SchemaError {
+                    pointer: format!("{}/$anchor", pointer),
+                    reason: String::from("`$anchor` must be a string"),
+                })?;
+                anchors.insert(anchor.to_string(), pointer.clone());
+            }
+        }
+
+        let mut compiled = Self {
+            schema: JsonValue::Null,
+            anchors,
+            #[cfg(feature = "extras")]
+            regexes: HashMap::new(),
+        };
+        for (subschema, pointer) in subschemas.iter() {
+            compiled.check_keywords(subschema, pointer, &schema)?;
+        }
+        compiled.schema = schema;
+        Ok(compiled)
+    }
+
+    /// # Description
+    /// Validates `instance` against the schema
+    /// # Returns
+    /// Every violation found, not just the first one
+    pub fn validate(&self, instance: &JsonValue) -> Result<(), Vec<Violation>> {
+        let mut violations = Vec::new();
+        self.validate_helper(&self.schema, "", instance, "", 0, &mut violations);
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    pub fn is_valid(&self, instance: &JsonValue) -> bool {
+        self.validate(instance).is_ok()
+    }
+
+    /// checks the keywords of a single schema object, subschemas get their own call
+    fn check_keywords(
+        &mut self,
+        schema: &JsonValue,
+        pointer: &str,
+        root: &JsonValue,
+    ) -> Result<(), SchemaError> {
+        let props = match schema {
+            JsonValue::Object(props) => props,
+            _ => return Ok(()),
+        };
+        for (keyword, val) in props.iter() {
+            let error = |reason: &str| SchemaError {
+                pointer: format!("{}/{}", pointer, keyword),
+                reason: reason.to_string(),
+            };
+            match keyword.as_str() {
+                "type" => {
+                    let valid_name = |val: &JsonValue| match val.as_str() {
+                        Some(name) => TYPE_NAMES.contains(&name),
+                        None => false,
+                    };
+                    let valid = match val {
+                        JsonValue::Array(names) => names.iter().all(valid_name),
+                        val => valid_name(val),
+                    };
+                    if valid == false {
+                        return Err(error("expected a type name or an array of type names"));
+                    }
+                }
+                "minimum" | "maximum" | "exclusiveMinimum" | "exclusiveMaximum" => {
+                    if val.as_number().is_none() {
+                        return Err(error("expected a number"));
+                    }
+                }
+                "multipleOf" => {
+                    // compared exactly, so `1e-400` and `1e400` are fine as well
+                    let zero = JsonNumber::from(0);
+                    match val.as_number() {
+                        Some(divisor) if divisor.cmp_value(&zero) == Ordering::Greater => (),
+                        _ => return Err(error("expected a number greater than 0")),
+                    }
+                }
+                "minLength" | "maxLength" | "minItems" | "maxItems" | "minProperties"
+                | "maxProperties" | "minContains" | "maxContains" => {
+                    if val.as_u64().is_none() {
+                        return Err(error("expected a non-negative integer"));
+                    }
+                }
+                "required" => match val.as_array() {
+                    Some(names) if names.iter().all(|name| name.as_str().is_some()) => (),
+                    _ => return Err(error("expected an array of strings")),
+                },
+                "enum" => {
+                    if val.as_array().is_none() {
+                        return Err(error("expected an array"));
+                    }
+                }
+                "uniqueItems" => {
+                    if val.as_bool().is_none() {
+                        return Err(error("expected a boolean"));
+                    }
+                }
+                "pattern" => match val.as_str() {
+                    Some(pattern) => self
+                        .compile_pattern(pattern)
+                        .map_err(|reason| error(&reason))?,
+                    None => return Err(error("expected a string")),
+                },
+                "patternProperties" => {
+                    for (pattern, _) in val.as_object().into_iter().flatten() {
+                        self.compile_pattern(pattern)
+                            .map_err(|reason| error(&reason))?;
+                    }
+                }
+                "$ref" => {
+                    let reference = val.as_str().ok_or_else(|| error("expected a string"))?;
+                    if self.resolve_ref(root, reference).is_none() {
+                        return Err(error(&format!(
+                            "can't resolve {:?}, only references within the document are supported",
+                            reference
+                        )));
+                    }
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "extras")]
+    fn compile_pattern(&mut self, pattern: &str) -> Result<(), String> {
+        if self.regexes.contains_key(pattern) == false {
+            let regex = Regex::new(pattern).map_err(|err| format!("invalid pattern: {}", err))?;
+            self.regexes.insert(pattern.to_string(), regex);
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "extras"))]
+    fn compile_pattern(&mut self, _pattern: &str) -> Result<(), String> {
+        Err(String::from("patterns need the `extras` feature"))
+    }
+
+    #[cfg(feature = "extras")]
+    fn pattern_matches(&self, pattern: &str, text: &str) -> bool {
+        // every reachable pattern got compiled in `new(..)`, compiling here is only a fallback
+        match self.regexes.get(pattern) {
+            Some(regex) => regex.is_match(text),
+            None => Regex::new(pattern)
+                .map(|regex| regex.is_match(text))
+                .unwrap_or(false),
+        }
+    }
+
+    #[cfg(not(feature = "extras"))]
+    fn pattern_matches(&self, _pattern: &str, _text: &str) -> bool {
+        // unreachable, `new(..)` rejects schemas with patterns without the feature
+        false
+    }
+
+    /// finds the subschema a `$ref` points to
+    fn resolve_ref<'r>(&self, root: &'r JsonValue, reference: &str) -> Option<&'r JsonValue> {
+        let fragment = percent_decode(reference.strip_prefix('#')?)?;
+        if fragment.is_empty() || fragment.starts_with('/') {
+            root.pointer(&fragment).ok()
+        } else {
+            root.pointer(self.anchors.get(&fragment)?).ok()
+        }
+    }
+
+    fn validate_helper(
+        &self,
+        schema: &JsonValue,
+        schema_path: &str,
+        instance: &JsonValue,
+        instance_path: &str,
+        ref_depth: usize,
+        out: &mut Vec<Violation>,
+    ) {
+        let props = match schema {
+            JsonValue::Bool(false) => {
+                out.push(Violation {
+                    instance_path: instance_path.to_string(),
+                    schema_path: schema_path.to_string(),
+                    message: String::from("no value is allowed here"),
+                });
+                return;
+            }
+            JsonValue::Object(props) => props,
+            _ => return,
+        };
+        let mut violation = |keyword: &str, message: String| {
+            out.push(Violation {
+                instance_path: instance_path.to_string(),
+                schema_path: format!("{}/{}", schema_path, keyword),
+                message,
+            })
+        };
+
+        for (keyword, val) in props.iter() {
+            match keyword.as_str() {
+                "type" => {
+                    let matches = match val {
+                        JsonValue::Array(names) => names
+                            .iter()
+                            .any(|name| has_type(instance, name.as_str().unwrap_or(""))),
+                        name => has_type(instance, name.as_str().unwrap_or("")),
+                    };
+                    if matches == false {
+                        violation(
+                            keyword,
+                            format!(
+                                "expected type {}, found {}",
+                                val.to_json(),
+                                type_name(instance)
+                            ),
+                        );
+                    }
+                }
+                "enum" => {
+                    let options = val.as_array().map(Vec::as_slice).unwrap_or(&[]);
+                    if options.iter().any(|option| values_equal(option, instance)) == false {
+                        violation(keyword, format!("must be one of {}", val.to_json()));
+                    }
+                }
+                "const" => {
+                    if values_equal(val, instance) == false {
+                        violation(keyword, format!("must be {}", val.to_json()));
+                    }
+                }
+                "minimum" | "maximum" | "exclusiveMinimum" | "exclusiveMaximum" | "multipleOf" => {
+                    if let (JsonValue::Number(number), Some(bound)) = (instance, val.as_number()) {
+                        if let Some(message) = check_number(keyword, number, bound) {
+                            violation(keyword, message);
+                        }
+                    }
+                }
+                "minLength" | "maxLength" => {
+                    if let JsonValue::String(text) = instance {
+                        // length is in code points, not bytes
+                        let len = text.chars().count();
+                        if let Some(message) = check_count(keyword, len, val, "characters") {
+                            violation(keyword, message);
+                        }
+                    }
+                }
+                "pattern" => {
+                    if let (JsonValue::String(text), Some(pattern)) = (instance, val.as_str()) {
+                        if self.pattern_matches(pattern, text) == false {
+                            violation(keyword, format!("must match the pattern {:?}", pattern));
+                        }
+                    }
+                }
+                "minItems" | "maxItems" => {
+                    if let JsonValue::Array(items) = instance {
+                        if let Some(message) = check_count(keyword, items.len(), val, "items") {
+                            violation(keyword, message);
+                        }
+                    }
+                }
+                "uniqueItems" => {
+                    if let (JsonValue::Array(items), Some(true)) = (instance, val.as_bool()) {
+                        let duplicate = items.iter().enumerate().any(|(k, item)| {
+                            items[k + 1..].iter().any(|other| values_equal(item, other))
+                        });
+                        if duplicate {
+                            violation(keyword, String::from("items must be unique"));
+                        }
+                    }
+                }
+                "minProperties" | "maxProperties" => {
+                    if let JsonValue::Object(members) = instance {
+                        if let Some(message) =
+                            check_count(keyword, members.len(), val, "properties")
+                        {
+                            violation(keyword, message);
+                        }
+                    }
+                }
+                "required" => {
+                    if let (JsonValue::Object(_), Some(names)) = (instance, val.as_array()) {
+                        for name in names.iter().filter_map(JsonValue::as_str) {
+                            if instance.get(name).is_none() {
+                                violation(keyword, format!("missing required property {:?}", name));
+                            }
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+        self.validate_applicators(props, schema_path, instance, instance_path, ref_depth, out);
+    }
+
+    /// the keywords that apply subschemas to the instance or its children
+    fn validate_applicators(
+        &self,
+        props: &[(String, JsonValue)],
+        schema_path: &str,
+        instance: &JsonValue,
+        instance_path: &str,
+        ref_depth: usize,
+        out: &mut Vec<Violation>,
+    ) {
+        let schema = |keyword: &str| {
+            props
+                .iter()
+                .filter(|(key, _)| key == keyword)
+                .next()
+                .map(|(_, val)| val)
+        };
+        let keyword_path = |keyword: &str| format!("{}/{}", schema_path, keyword);
+        let violation = |out: &mut Vec<Violation>, keyword: &str, message: String| {
+            out.push(Violation {
+                instance_path: instance_path.to_string(),
+                schema_path: keyword_path(keyword),
+                message,
+            })
+        };
+
+        if let Some(reference) = schema("$ref").and_then(JsonValue::as_str) {
+            if ref_depth >= MAX_REF_DEPTH {
+                violation(out, "$ref", String::from("too many nested `$ref`s"));
+            } else if let Some(target) = self.resolve_ref(&self.schema, reference) {
+                let path = keyword_path("$ref");
+                self.validate_helper(target, &path, instance, instance_path, ref_depth + 1, out);
+            }
+        }
+
+        if let Some(subschemas) = schema("allOf").and_then(JsonValue::as_array) {
+            for (k, subschema) in subschemas.iter().enumerate() {
+                let path = format!("{}/{}", keyword_path("allOf"), k);
+                self.validate_helper(subschema, &path, instance, instance_path, ref_depth, out);
+            }
+        }
+        if let Some(subschemas) = schema("anyOf").and_then(JsonValue::as_array) {
+            let matched = subschemas
+                .iter()
+                .any(|subschema| self.matches(subschema, instance, ref_depth));
+            if matched == false {
+                violation(
+                    out,
+                    "anyOf",
+                    String::from("doesn't match any of the schemas"),
+                );
+            }
+        }
+        if let Some(subschemas) = schema("oneOf").and_then(JsonValue::as_array) {
+            let matched = subschemas
+                .iter()
+                .filter(|subschema| self.matches(subschema, instance, ref_depth))
+                .count();
+            if matched != 1 {
+                let message = format!("must match exactly one schema, matched {}", matched);
+                violation(out, "oneOf", message);
+            }
+        }
+        if let Some(subschema) = schema("not") {
+            if self.matches(subschema, instance, ref_depth) {
+                violation(out, "not", String::from("must not match the schema"));
+            }
+        }
+        if let Some(condition) = schema("if") {
+            let (keyword, branch) = if self.matches(condition, instance, ref_depth) {
+                ("then", schema("then"))
+            } else {
+                ("else", schema("else"))
+            };
+            if let Some(branch) = branch {
+                let path = keyword_path(keyword);
+                self.validate_helper(branch, &path, instance, instance_path, ref_depth, out);
+            }
+        }
+
+        match instance {
+            JsonValue::Object(members) => {
+                let properties = schema("properties").and_then(JsonValue::as_object);
+                let patterns = schema("patternProperties").and_then(JsonValue::as_object);
+                for (key, val) in members.iter() {
+                    let mut member_path = instance_path.to_string();
+                    push_token(&mut member_path, key);
+                    let mut evaluated = false;
+
+                    if let Some(subschema) = properties
+                        .and_then(|properties| {
+                            properties.iter().filter(|(name, _)| name == key).next()
+                        })
+                        .map(|(_, subschema)| subschema)
+                    {
+                        let mut path = keyword_path("properties");
+                        push_token(&mut path, key);
+                        self.validate_helper(subschema, &path, val, &member_path, ref_depth, out);
+                        evaluated = true;
+                    }
+                    for (pattern, subschema) in patterns.into_iter().flatten() {
+                        if self.pattern_matches(pattern, key) {
+                            let mut path = keyword_path("patternProperties");
+                            push_token(&mut path, pattern);
+                            self.validate_helper(
+                                subschema,
+                                &path,
+                                val,
+                                &member_path,
+                                ref_depth,
+                                out,
+                            );
+                            evaluated = true;
+                        }
+                    }
+                    if let (false, Some(subschema)) = (evaluated, schema("additionalProperties")) {
+                        let path = keyword_path("additionalProperties");
+                        self.validate_helper(subschema, &path, val, &member_path, ref_depth, out);
+                    }
+                    if let Some(subschema) = schema("propertyNames") {
+                        let name = JsonValue::String(key.clone());
+                        let path = keyword_path("propertyNames");
+                        self.validate_helper(subschema, &path, &name, &member_path, ref_depth, out);
+                    }
+                }
+            }
+            JsonValue::Array(items) => {
+                let prefix = schema("prefixItems")
+                    .and_then(JsonValue::as_array)
+                    .map(Vec::as_slice)
+                    .unwrap_or(&[]);
+                for (k, item) in items.iter().enumerate() {
+                    let item_path = format!("{}/{}", instance_path, k);
+                    if let Some(subschema) = prefix.get(k) {
+                        let path = format!("{}/{}", keyword_path("prefixItems"), k);
+                        self.validate_helper(subschema, &path, item, &item_path, ref_depth, out);
+                    } else if let Some(subschema) = schema("items") {
+                        let path = keyword_path("items");
+                        self.validate_helper(subschema, &path, item, &item_path, ref_depth, out);
+                    }
+                }
+
+                if let Some(subschema) = schema("contains") {
+                    let found = items
+                        .iter()
+                        .filter(|item| self.matches(subschema, item, ref_depth))
+                        .count();
+                    let min = schema("minContains")
+                        .and_then(JsonValue::as_u64)
+                        .unwrap_or(1);
+                    let max = schema("maxContains").and_then(JsonValue::as_u64);
+                    if (found as u64) < min {
+                        let message = format!(
+                            "must contain at least {} matching item(s), found {}",
+                            min, found
+                        );
+                        violation(out, "contains", message);
+                    }
+                    if let Some(max) = max.filter(|&max| found as u64 > max) {
+                        let message = format!(
+                            "must contain at most {} matching item(s), found {}",
+                            max, found
+                        );
+                        violation(out, "maxContains", message);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// validates without keeping the violations, for the keywords that only care about pass/fail
+    fn matches(&self, schema: &JsonValue, instance: &JsonValue, ref_depth: usize) -> bool {
+        let mut violations = Vec::new();
+        self.validate_helper(schema, "", instance, "", ref_depth, &mut violations);
+        violations.is_empty()
+    }
+}
+
+/// lists every subschema (the root included) along with its pointer, in the order they appear
+fn collect_subschemas<'s>(
+    schema: &'s JsonValue,
+    pointer: String,
+    out: &mut Vec<(&'s JsonValue, String)>,
+) -> Result<(), SchemaError> {
+    let props = match schema {
+        JsonValue::Bool(_) => {
+            out.push((schema, pointer));
+            return Ok(());
+        }
+        JsonValue::Object(props) => props,
+        _ => {
+            return Err(SchemaError {
+                pointer,
+                reason: String::from("a schema must be an object or a boolean"),
+            })
+        }
+    };
+    out.push((schema, pointer.clone()));
+    for (keyword, val) in props.iter() {
+        let mut keyword_pointer = pointer.clone();
+        push_token(&mut keyword_pointer, keyword);
+        let wrong_type = |expected: &str| SchemaError {
+            pointer: keyword_pointer.clone(),
+            reason: format!("expected {}", expected),
+        };
+        if SCHEMA_KEYWORDS.contains(&keyword.as_str()) {
+            collect_subschemas(val, keyword_pointer.clone(), out)?;
+        } else if SCHEMA_MAP_KEYWORDS.contains(&keyword.as_str()) {
+            let members = val.as_object().ok_or_else(|| wrong_type("an object"))?;
+            for (name, subschema) in members.iter() {
+                let mut member_pointer = keyword_pointer.clone();
+                push_token(&mut member_pointer, name);
+                collect_subschemas(subschema, member_pointer, out)?;
+            }
+        } else if SCHEMA_LIST_KEYWORDS.contains(&keyword.as_str()) {
+            let subschemas = match val.as_array() {
+                Some(subschemas) if subschemas.is_empty() == false => subschemas,
+                _ => return Err(wrong_type("a non-empty array")),
+            };
+            for (k, subschema) in subschemas.iter().enumerate() {
+                collect_subschemas(subschema, format!("{}/{}", keyword_pointer, k), out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn percent_decode(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut k = 0;
+    while k < bytes.len() {
+        if bytes[k] == b'%' {
+            let hex = text.get(k + 1..k + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            k += 3;
+        } else {
+            decoded.push(bytes[k]);
+            k += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+fn type_name(instance: &JsonValue) -> &'static str {
+    match instance {
+        JsonValue::Null => "null",
+        JsonValue::Bool(_) => "boolean",
+        JsonValue::Number(_) => "number",
+        JsonValue::String(_) => "string",
+        JsonValue::Array(_) => "array",
+        JsonValue::Object(_) => "object",
+    }
+}
+
+fn has_type(instance: &JsonValue, name: &str) -> bool {
+    match (instance, name) {
+        // `1.0` counts as an integer, only the value matters
+        (JsonValue::Number(number), "integer") => is_integer(number),
+        (instance, name) => type_name(instance) == name,
+    }
+}
+
+fn is_integer(number: &JsonNumber) -> bool {
+    match number.to_decimal_string() {
+        Ok(decimal) => decimal.contains('.') == false,
+        Err(_) => number
+            .as_f64()
+            .map(|val| val.fract() == 0.0)
+            .unwrap_or(true),
+    }
+}
+
+fn check_number(keyword: &str, number: &JsonNumber, bound: &JsonNumber) -> Option<String> {
+    let ordering = number.cmp_value(bound);
+    let (valid, relation) = match keyword {
+        "minimum" => (ordering != Ordering::Less, ">="),
+        "maximum" => (ordering != Ordering::Greater, "<="),
+        "exclusiveMinimum" => (ordering == Ordering::Greater, ">"),
+        "exclusiveMaximum" => (ordering == Ordering::Less, "<"),
+        _ => {
+            // exact, on the digits. Floats would accept `1e20` as a multiple of `3`
+            return match number.is_multiple_of(bound) {
+                Some(true) => None,
+                Some(false) => Some(format!("must be a multiple of {}", bound)),
+                None => Some(format!(
+                    "can't be checked against multipleOf {}, it has too many digits",
+                    bound
+                )),
+            };
+        }
+    };
+    if valid {
+        None
+    } else {
+        Some(format!("must be {} {}", relation, bound))
+    }
+}
+
+fn check_count(keyword: &str, count: usize, bound: &JsonValue, unit: &str) -> Option<String> {
+    let bound = bound.as_u64()?;
+    if keyword.starts_with("min") && (count as u64) < bound {
+        Some(format!(
+            "must have at least {} {}, found {}",
+            bound, unit, count
+        ))
+    } else if keyword.starts_with("max") && (count as u64) > bound {
+        Some(format!(
+            "must have at most {} {}, found {}",
+            bound, unit, count
+        ))
+    } else {
+        None
+    }
+}
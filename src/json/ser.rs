@@ -0,0 +1,442 @@
+use serde::{
+    ser::{
+        self, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
+        SerializeTupleStruct, SerializeTupleVariant,
+    },
+    Serialize, Serializer,
+};
+
+use super::{de::SerdeError, number::JsonNumber, value::JsonValue};
+
+/// Converts any `Serialize` type into a `JsonValue`
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<JsonValue, SerdeError> {
+    value.serialize(ValueSerializer)
+}
+
+/// Serializes `value` as compact json
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, SerdeError> {
+    Ok(to_value(value)?.to_json())
+}
+
+/// Serializes `value` as json with every item/property on its own line, indented by `indent` spaces per level
+pub fn to_string_pretty<T: Serialize + ?Sized>(
+    value: &T,
+    indent: usize,
+) -> Result<String, SerdeError> {
+    Ok(to_value(value)?.to_json_pretty(indent))
+}
+
+/// # Description
+/// A `serde::Serializer` that builds a `JsonValue`
+/// # Comments
+/// - Enums are externally tagged like serde_json does it: `"Variant"` or `{"Variant": ...}`
+/// - NaN and infinity are errors since json has no way to write them
+/// - Map keys have to serialize to a string, number or bool, the latter two are written as text
+pub struct ValueSerializer;
+
+/// `JsonValue` hands numbers its primitives can't reproduce to serializers as a newtype struct with this
+/// name, `ValueSerializer` recognizes it and rebuilds the number from the lexeme
+const NUMBER_TOKEN: &str = "$parser_util::json::JsonNumber";
+
+fn float_error() -> SerdeError {
+    SerdeError::Message(String::from(
+        "NaN and infinity can't be represented in json",
+    ))
+}
+
+impl Serializer for ValueSerializer {
+    type Ok = JsonValue;
+    type Error = SerdeError;
+    type SerializeSeq = SerializeVec;
+    type SerializeTuple = SerializeVec;
+    type SerializeTupleStruct = SerializeVec;
+    type SerializeTupleVariant = SerializeVariant<Vec<JsonValue>>;
+    type SerializeMap = SerializeObject;
+    type SerializeStruct = SerializeObject;
+    type SerializeStructVariant = SerializeVariant<Vec<(String, JsonValue)>>;
+
+    fn serialize_bool(self, val: bool) -> Result<JsonValue, SerdeError> {
+        Ok(JsonValue::Bool(val))
+    }
+
+    fn serialize_i8(self, val: i8) -> Result<JsonValue, SerdeError> {
+        self.serialize_i64(val as i64)
+    }
+
+    fn serialize_i16(self, val: i16) -> Result<JsonValue, SerdeError> {
+        self.serialize_i64(val as i64)
+    }
+
+    fn serialize_i32(self, val: i32) -> Result<JsonValue, SerdeError> {
+        self.serialize_i64(val as i64)
+    }
+
+    fn serialize_i64(self, val: i64) -> Result<JsonValue, SerdeError> {
+        Ok(JsonValue::Number(JsonNumber::from(val)))
+    }
+
+    fn serialize_i128(self, val: i128) -> Result<JsonValue, SerdeError> {
+        Ok(JsonValue::Number(
+            JsonNumber::parse(&val.to_string()).unwrap(),
+        ))
+    }
+
+    fn serialize_u8(self, val: u8) -> Result<JsonValue, SerdeError> {
+        self.serialize_u64(val as u64)
+    }
+
+    fn serialize_u16(self, val: u16) -> Result<JsonValue, SerdeError> {
+        self.serialize_u64(val as u64)
+    }
+
+    fn serialize_u32(self, val: u32) -> Result<JsonValue, SerdeError> {
+        self.serialize_u64(val as u64)
+    }
+
+    fn serialize_u64(self, val: u64) -> Result<JsonValue, SerdeError> {
+        Ok(JsonValue::Number(JsonNumber::from(val)))
+    }
+
+    fn serialize_u128(self, val: u128) -> Result<JsonValue, SerdeError> {
+        Ok(JsonValue::Number(
+            JsonNumber::parse(&val.to_string()).unwrap(),
+        ))
+    }
+
+    fn serialize_f32(self, val: f32) -> Result<JsonValue, SerdeError> {
+        JsonNumber::from_f32(val)
+            .map(JsonValue::Number)
+            .ok_or_else(float_error)
+    }
+
+    fn serialize_f64(self, val: f64) -> Result<JsonValue, SerdeError> {
+        JsonNumber::from_f64(val)
+            .map(JsonValue::Number)
+            .ok_or_else(float_error)
+    }
+
+    fn serialize_char(self, val: char) -> Result<JsonValue, SerdeError> {
+        Ok(JsonValue::String(val.to_string()))
+    }
+
+    fn serialize_str(self, val: &str) -> Result<JsonValue, SerdeError> {
+        Ok(JsonValue::String(val.to_string()))
+    }
+
+    fn serialize_bytes(self, val: &[u8]) -> Result<JsonValue, SerdeError> {
+        let items = val
+            .iter()
+            .map(|&byte| JsonValue::Number(JsonNumber::from(byte as u64)))
+            .collect();
+        Ok(JsonValue::Array(items))
+    }
+
+    fn serialize_none(self) -> Result<JsonValue, SerdeError> {
+        Ok(JsonValue::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, val: &T) -> Result<JsonValue, SerdeError> {
+        val.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<JsonValue, SerdeError> {
+        Ok(JsonValue::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<JsonValue, SerdeError> {
+        Ok(JsonValue::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<JsonValue, SerdeError> {
+        Ok(JsonValue::String(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        val: &T,
+    ) -> Result<JsonValue, SerdeError> {
+        let val = val.serialize(self)?;
+        if name != NUMBER_TOKEN {
+            return Ok(val);
+        }
+        val.as_str()
+            .and_then(JsonNumber::parse)
+            .map(JsonValue::Number)
+            .ok_or_else(|| SerdeError::Message(String::from("invalid json number")))
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        val: &T,
+    ) -> Result<JsonValue, SerdeError> {
+        let val = val.serialize(self)?;
+        Ok(JsonValue::Object(vec![(variant.to_string(), val)]))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeVec, SerdeError> {
+        Ok(SerializeVec {
+            items: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeVec, SerdeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeVec, SerdeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVariant<Vec<JsonValue>>, SerdeError> {
+        Ok(SerializeVariant {
+            variant,
+            fields: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<SerializeObject, SerdeError> {
+        Ok(SerializeObject {
+            props: Vec::with_capacity(len.unwrap_or(0)),
+            next_key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeObject, SerdeError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVariant<Vec<(String, JsonValue)>>, SerdeError> {
+        Ok(SerializeVariant {
+            variant,
+            fields: Vec::with_capacity(len),
+        })
+    }
+}
+
+pub struct SerializeVec {
+    items: Vec<JsonValue>,
+}
+
+impl SerializeSeq for SerializeVec {
+    type Ok = JsonValue;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, val: &T) -> Result<(), SerdeError> {
+        self.items.push(to_value(val)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<JsonValue, SerdeError> {
+        Ok(JsonValue::Array(self.items))
+    }
+}
+
+impl SerializeTuple for SerializeVec {
+    type Ok = JsonValue;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, val: &T) -> Result<(), SerdeError> {
+        SerializeSeq::serialize_element(self, val)
+    }
+
+    fn end(self) -> Result<JsonValue, SerdeError> {
+        SerializeSeq::end(self)
+    }
+}
+
+impl SerializeTupleStruct for SerializeVec {
+    type Ok = JsonValue;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, val: &T) -> Result<(), SerdeError> {
+        SerializeSeq::serialize_element(self, val)
+    }
+
+    fn end(self) -> Result<JsonValue, SerdeError> {
+        SerializeSeq::end(self)
+    }
+}
+
+pub struct SerializeObject {
+    props: Vec<(String, JsonValue)>,
+    /// key waiting for its value, serde hands them to us one at a time
+    next_key: Option<String>,
+}
+
+impl SerializeMap for SerializeObject {
+    type Ok = JsonValue;
+    type Error = SerdeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerdeError> {
+        let key = match to_value(key)? {
+            JsonValue::String(key) => key,
+            JsonValue::Number(number) => number.as_str().to_string(),
+            JsonValue::Bool(val) => val.to_string(),
+            _ => {
+                return Err(SerdeError::Message(String::from(
+                    "map keys must be strings, numbers or booleans",
+                )))
+            }
+        };
+        self.next_key = Some(key);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, val: &T) -> Result<(), SerdeError> {
+        let key = self
+            .next_key
+            .take()
+            .ok_or_else(|| <SerdeError as ser::Error>::custom("value serialized before its key"))?;
+        self.props.push((key, to_value(val)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<JsonValue, SerdeError> {
+        Ok(JsonValue::Object(self.props))
+    }
+}
+
+impl SerializeStruct for SerializeObject {
+    type Ok = JsonValue;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        val: &T,
+    ) -> Result<(), SerdeError> {
+        self.props.push((key.to_string(), to_value(val)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<JsonValue, SerdeError> {
+        Ok(JsonValue::Object(self.props))
+    }
+}
+
+/// Collects the fields of a tuple or struct variant, which end up wrapped as `{"Variant": fields}`
+pub struct SerializeVariant<F> {
+    variant: &'static str,
+    fields: F,
+}
+
+impl SerializeTupleVariant for SerializeVariant<Vec<JsonValue>> {
+    type Ok = JsonValue;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, val: &T) -> Result<(), SerdeError> {
+        self.fields.push(to_value(val)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<JsonValue, SerdeError> {
+        let fields = JsonValue::Array(self.fields);
+        Ok(JsonValue::Object(vec![(self.variant.to_string(), fields)]))
+    }
+}
+
+impl SerializeStructVariant for SerializeVariant<Vec<(String, JsonValue)>> {
+    type Ok = JsonValue;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        val: &T,
+    ) -> Result<(), SerdeError> {
+        self.fields.push((key.to_string(), to_value(val)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<JsonValue, SerdeError> {
+        let fields = JsonValue::Object(self.fields);
+        Ok(JsonValue::Object(vec![(self.variant.to_string(), fields)]))
+    }
+}
+
+impl Serialize for JsonValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Null => serializer.serialize_unit(),
+            Self::Bool(val) => serializer.serialize_bool(*val),
+            Self::Number(number) => serialize_number(number, serializer),
+            Self::String(val) => serializer.serialize_str(val),
+            Self::Array(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items.iter() {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Self::Object(props) => {
+                let mut map = serializer.serialize_map(Some(props.len()))?;
+                for (key, val) in props.iter() {
+                    map.serialize_entry(key, val)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+/// # Description
+/// Serializes `number` as the primitive that reproduces its lexeme: an integer type for plain
+/// integers and `f64` when the shortest representation of the float is the lexeme itself
+/// # Comments
+/// - Anything else (`1.10`, `1e2`, `1e400`, ...) goes out as a `NUMBER_TOKEN` newtype holding the
+///   lexeme. `ValueSerializer` turns it back into the same number, other serializers typically write the
+///   lexeme as a string, which at least doesn't round it
+fn serialize_number<S: Serializer>(number: &JsonNumber, serializer: S) -> Result<S::Ok, S::Error> {
+    let lexeme = number.as_str();
+    // `-0` parses as a signed integer but would come back as `0`
+    let signed = lexeme != "-0";
+    if let Ok(val) = lexeme.parse::<u64>() {
+        return serializer.serialize_u64(val);
+    }
+    match lexeme.parse::<i64>() {
+        Ok(val) if signed => return serializer.serialize_i64(val),
+        _ => (),
+    }
+    if let Ok(val) = lexeme.parse::<u128>() {
+        return serializer.serialize_u128(val);
+    }
+    match lexeme.parse::<i128>() {
+        Ok(val) if signed => return serializer.serialize_i128(val),
+        _ => (),
+    }
+    if let Ok(val) = number.as_f64() {
+        if JsonNumber::from_f64(val).filter(|float| float == number).is_some() {
+            return serializer.serialize_f64(val);
+        }
+    }
+    serializer.serialize_newtype_struct(NUMBER_TOKEN, lexeme)
+}
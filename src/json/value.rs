@@ -0,0 +1,261 @@
+use sandboxed_collections::narytree::*;
+
+use super::{ast::*, lexer::*, number::*};
+
+/// # Description
+/// A typed json value. Unlike `JsonAst` it owns all of its text, strings are fully decoded.
+/// # Comments
+/// - Objects are a vector of (key, value) pairs so the order of the document is kept.
+/// For the small objects I usually deal with a vector is faster than a `HashMap` anyway.
+#[derive(Clone, PartialEq, Debug)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(JsonNumber),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// Lexes and parses `raw_text` straight into a `JsonValue`
+    pub fn parse(raw_text: &str) -> Result<Self, ParseError> {
+        let mut ast = JsonAst::new();
+        ast.parse(raw_text)?;
+        Ok(Self::from_ast(&ast, raw_text))
+    }
+
+    /// # Description
+    /// Like `parse(..)` but accepts whatever dialect `options` asks for
+    /// # Comments
+    /// - JSON5 numbers are normalized into plain json numbers (`0x10` becomes `16`, `.5` becomes `0.5`)
+    /// - `Infinity` and `NaN` have no json representation, they are an error unless `options.non_finite_as_null`
+    /// turns them into `Null`
+    pub fn parse_with(raw_text: &str, options: &ParseOptions) -> Result<Self, ParseError> {
+        let mut ast = JsonAst::new();
+        ast.parse_with(raw_text, options)?;
+        Ok(Self::from_ast(&ast, raw_text))
+    }
+
+    /// # Description
+    /// Converts a parsed `JsonAst` into a `JsonValue`
+    /// # Arguments
+    /// - `ast` : a tree that was filled by `JsonAst::parse(..)`
+    /// - `raw_text` : the same text that was given to `parse(..)`, string tokens are spans into it
+    /// # Comments
+    /// - Tokens are decoded the same way as `BorrowedValue::from_ast(..)`
+    pub fn from_ast(ast: &JsonAst, raw_text: &str) -> Self {
+        Self::from_ast_helper(&ast.ast, ast.ast.root, raw_text)
+    }
+
+    fn from_ast_helper(tree: &NaryTree<JsToken>, node: NodeAddr, raw_text: &str) -> Self {
+        if node == NULL {
+            return Self::Null;
+        }
+        let ast_node = &tree[node];
+        let token = ast_node.data.unwrap();
+        match token {
+            JsToken::Open('{') => Self::Object(
+                ast_node
+                    .children
+                    .iter()
+                    .map(|&key_node| {
+                        let key = tree[key_node].data.unwrap().decode_text(raw_text);
+                        let value_node = tree[key_node].children.first().copied().unwrap_or(NULL);
+                        (
+                            key.into_owned(),
+                            Self::from_ast_helper(tree, value_node, raw_text),
+                        )
+                    })
+                    .collect(),
+            ),
+            JsToken::Open(_) => Self::Array(
+                ast_node
+                    .children
+                    .iter()
+                    .map(|&child| Self::from_ast_helper(tree, child, raw_text))
+                    .collect(),
+            ),
+            JsToken::String { .. } => Self::String(token.decode_text(raw_text).into_owned()),
+            JsToken::Number { lbound, ubound } => {
+                match JsonNumber::normalize(&raw_text[lbound as usize..ubound as usize]) {
+                    Some(lexeme) => Self::Number(JsonNumber::from_normalized(lexeme.into_owned())),
+                    None => Self::Null,
+                }
+            }
+            JsToken::Boolean(val) => Self::Bool(val),
+            _ => Self::Null,
+        }
+    }
+
+    /// # Description
+    /// Converts `self` back into a `JsonAst`
+    /// # Returns
+    /// The tree along with the (compact) json text its string tokens point into
+    pub fn to_ast(&self) -> (JsonAst, String) {
+        let mut json_ast = JsonAst::new();
+        let mut raw_text = String::new();
+        json_ast.ast.root = self.to_ast_helper(&mut json_ast.ast, &mut raw_text);
+        (json_ast, raw_text)
+    }
+
+    fn to_ast_helper(&self, tree: &mut NaryTree<JsToken>, raw_text: &mut String) -> NodeAddr {
+        match self {
+            Self::Null => {
+                raw_text.push_str("null");
+                tree.allocate_node(Some(JsToken::Null))
+            }
+            Self::Bool(val) => {
+                raw_text.push_str(if *val { "true" } else { "false" });
+                tree.allocate_node(Some(JsToken::Boolean(*val)))
+            }
+            Self::Number(val) => {
+                let lbound = raw_text.len();
+                raw_text.push_str(val.as_str());
+                tree.allocate_node(Some(JsToken::Number {
+                    lbound: lbound as u32,
+                    ubound: raw_text.len() as u32,
+                }))
+            }
+            Self::String(val) => {
+                let token = Self::push_string(val, raw_text);
+                tree.allocate_node(Some(token))
+            }
+            Self::Array(items) => {
+                let addr = tree.allocate_node(Some(JsToken::Open('[')));
+                raw_text.push('[');
+                for (k, item) in items.iter().enumerate() {
+                    if k > 0 {
+                        raw_text.push(',');
+                    }
+                    let child_addr = item.to_ast_helper(tree, raw_text);
+                    tree[addr].add_child(child_addr, addr);
+                }
+                raw_text.push(']');
+                addr
+            }
+            Self::Object(props) => {
+                let addr = tree.allocate_node(Some(JsToken::Open('{')));
+                raw_text.push('{');
+                for (k, (key, val)) in props.iter().enumerate() {
+                    if k > 0 {
+                        raw_text.push(',');
+                    }
+                    let key_token = Self::push_string(key, raw_text);
+                    let key_addr = tree.allocate_node(Some(key_token));
+                    tree[addr].add_child(key_addr, addr);
+                    raw_text.push(':');
+                    let val_addr = val.to_ast_helper(tree, raw_text);
+                    tree[key_addr].add_child(val_addr, key_addr);
+                }
+                raw_text.push('}');
+                addr
+            }
+        }
+    }
+
+    fn push_string(val: &str, raw_text: &mut String) -> JsToken {
+        let lbound = raw_text.len();
+        raw_text.push('\"');
+        escape(val, raw_text);
+        let ubound = raw_text.len();
+        raw_text.push('\"');
+        JsToken::String {
+            lbound: lbound as u32,
+            ubound: ubound as u32,
+        }
+    }
+
+    /// # Description
+    /// Looks up `key` in an object
+    /// # Returns
+    /// `None` if `self` isn't an object or the key is missing. If the key is duplicated the first one wins.
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        self.as_object()?
+            .iter()
+            .filter(|(k, _)| k.as_str() == key)
+            .next()
+            .map(|(_, v)| v)
+    }
+
+    /// # Description
+    /// Every value stored under `key`, in document order
+    /// # Comments
+    /// - Objects are a list of pairs so they double as a multimap, parse with `DuplicateKeys::KeepAll`
+    /// (the default) and every duplicate is kept. Empty if `self` isn't an object
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a JsonValue> + 'a {
+        self.as_object()
+            .map(|props| props.as_slice())
+            .unwrap_or(&[])
+            .iter()
+            .filter(move |(k, _)| k.as_str() == key)
+            .map(|(_, v)| v)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut JsonValue> {
+        match self {
+            Self::Object(props) => props
+                .iter_mut()
+                .filter(|(k, _)| k.as_str() == key)
+                .next()
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        if let Self::Null = self {
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            &Self::Bool(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&JsonNumber> {
+        match self {
+            Self::Number(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// `None` if `self` isn't a number or the number doesn't fit, use `as_number()` to find out why
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_number()?.as_i64().ok()
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_number()?.as_u64().ok()
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number()?.as_f64().ok()
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(val) => Some(val.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<JsonValue>> {
+        match self {
+            Self::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&Vec<(String, JsonValue)>> {
+        match self {
+            Self::Object(props) => Some(props),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use sandboxed_collections::narytree::*;
+use std::io;
+
+use super::{ast::*, lexer::*, number::*, value::*};
+
+/// # Description
+/// Handles the whitespace side of serialization
+/// # Comments
+/// - `indent == None` is compact mode, no whitespace is written at all
+/// - `Some(n)` puts every array item/object property on its own line, indented by `n` spaces per level
+#[derive(Copy, Clone)]
+struct Layout {
+    indent: Option<usize>,
+}
+
+impl Layout {
+    fn newline<W: io::Write>(&self, writer: &mut W, depth: usize) -> io::Result<()> {
+        if let Some(indent) = self.indent {
+            writer.write_all(b"\n")?;
+            for _ in 0..indent * depth {
+                writer.write_all(b" ")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn colon<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        if self.indent.is_some() {
+            writer.write_all(b": ")
+        } else {
+            writer.write_all(b":")
+        }
+    }
+}
+
+fn write_string<W: io::Write>(writer: &mut W, val: &str) -> io::Result<()> {
+    let mut escaped = String::with_capacity(val.len() + 2);
+    escaped.push('\"');
+    escape(val, &mut escaped);
+    escaped.push('\"');
+    writer.write_all(escaped.as_bytes())
+}
+
+fn into_string(buffer: Vec<u8>) -> String {
+    String::from_utf8(buffer).expect("serializer wrote invalid utf-8")
+}
+
+impl JsonAst {
+    /// # Description
+    /// Serializes the tree as compact json
+    /// # Arguments
+    /// - `raw_text` : the text the tree was parsed from, string and number tokens are spans into it
+    /// # Comments
+    /// - strings are decoded and re-escaped, so escapes like `\/` or `\u0041` come out in their shortest form
+    /// - numbers are written back with their original digits
+    pub fn to_json(&self, raw_text: &str) -> String {
+        let mut buffer = Vec::new();
+        self.write_json(raw_text, &mut buffer)
+            .expect("writing to a Vec can't fail");
+        into_string(buffer)
+    }
+
+    /// Like `to_json(..)` but each item/property is put on its own line indented by `indent` spaces per level
+    pub fn to_json_pretty(&self, raw_text: &str, indent: usize) -> String {
+        let mut buffer = Vec::new();
+        self.write_json_pretty(raw_text, &mut buffer, indent)
+            .expect("writing to a Vec can't fail");
+        into_string(buffer)
+    }
+
+    pub fn write_json<W: io::Write>(&self, raw_text: &str, writer: &mut W) -> io::Result<()> {
+        let layout = Layout { indent: None };
+        self.write_json_helper(self.ast.root, raw_text, writer, layout, 0)
+    }
+
+    pub fn write_json_pretty<W: io::Write>(
+        &self,
+        raw_text: &str,
+        writer: &mut W,
+        indent: usize,
+    ) -> io::Result<()> {
+        let layout = Layout {
+            indent: Some(indent),
+        };
+        self.write_json_helper(self.ast.root, raw_text, writer, layout, 0)
+    }
+
+    fn write_json_helper<W: io::Write>(
+        &self,
+        node: NodeAddr,
+        raw_text: &str,
+        writer: &mut W,
+        layout: Layout,
+        depth: usize,
+    ) -> io::Result<()> {
+        if node == NULL {
+            return Ok(());
+        }
+        let ast_node = &self.ast[node];
+        match ast_node.data.unwrap() {
+            JsToken::Open(ob_char) => {
+                let cb_char = if ob_char == '{' { b'}' } else { b']' };
+                writer.write_all(&[ob_char as u8])?;
+                for (k, &child) in ast_node.children.iter().enumerate() {
+                    if k > 0 {
+                        writer.write_all(b",")?;
+                    }
+                    layout.newline(writer, depth + 1)?;
+                    if ob_char == '{' {
+                        // child is the property name, its only child is the value
+                        if let Some(JsToken::Error { .. }) = self.ast[child].data {
+                            // placeholder left by `parse_recovering(..)`, the name is unknown
+                            write_string(writer, "")?;
+                        } else {
+                            self.write_json_helper(child, raw_text, writer, layout, depth + 1)?;
+                        }
+                        layout.colon(writer)?;
+                        let value = self.ast[child].children.get(0).copied().unwrap_or(NULL);
+                        self.write_json_helper(value, raw_text, writer, layout, depth + 1)?;
+                    } else {
+                        self.write_json_helper(child, raw_text, writer, layout, depth + 1)?;
+                    }
+                }
+                if ast_node.children.is_empty() == false {
+                    layout.newline(writer, depth)?;
+                }
+                writer.write_all(&[cb_char])
+            }
+            JsToken::String { lbound, ubound } => {
+                let decoded = unescape(&raw_text[1 + lbound as usize..ubound as usize]);
+                write_string(writer, &decoded)
+            }
+            JsToken::Number { lbound, ubound } => {
+                // plain json numbers are copied as they are, only JSON5 ones get normalized.
+                // `NaN` and `Infinity` (only parsed with `non_finite_as_null`) can't be written in json
+                let lexeme = &raw_text[lbound as usize..ubound as usize];
+                if is_json_number(lexeme) {
+                    return writer.write_all(lexeme.as_bytes());
+                }
+                match JsonNumber::parse_json5(lexeme) {
+                    Some(number) => writer.write_all(number.as_str().as_bytes()),
+                    None => writer.write_all(b"null"),
+                }
+            }
+            JsToken::Identifier { lbound, ubound } => {
+                let decoded = unescape(&raw_text[lbound as usize..ubound as usize]);
+                write_string(writer, &decoded)
+            }
+            JsToken::Boolean(true) => writer.write_all(b"true"),
+            JsToken::Boolean(false) => writer.write_all(b"false"),
+            _ => writer.write_all(b"null"),
+        }
+    }
+}
+
+impl JsonValue {
+    /// Serializes the value as compact json
+    pub fn to_json(&self) -> String {
+        let mut buffer = Vec::new();
+        self.write_json(&mut buffer)
+            .expect("writing to a Vec can't fail");
+        into_string(buffer)
+    }
+
+    /// Like `to_json()` but each item/property is put on its own line indented by `indent` spaces per level
+    pub fn to_json_pretty(&self, indent: usize) -> String {
+        let mut buffer = Vec::new();
+        self.write_json_pretty(&mut buffer, indent)
+            .expect("writing to a Vec can't fail");
+        into_string(buffer)
+    }
+
+    pub fn write_json<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.write_json_helper(writer, Layout { indent: None }, 0)
+    }
+
+    pub fn write_json_pretty<W: io::Write>(&self, writer: &mut W, indent: usize) -> io::Result<()> {
+        let layout = Layout {
+            indent: Some(indent),
+        };
+        self.write_json_helper(writer, layout, 0)
+    }
+
+    fn write_json_helper<W: io::Write>(
+        &self,
+        writer: &mut W,
+        layout: Layout,
+        depth: usize,
+    ) -> io::Result<()> {
+        match self {
+            Self::Null => writer.write_all(b"null"),
+            Self::Bool(true) => writer.write_all(b"true"),
+            Self::Bool(false) => writer.write_all(b"false"),
+            Self::Number(val) => writer.write_all(val.as_str().as_bytes()),
+            Self::String(val) => write_string(writer, val),
+            Self::Array(items) => {
+                writer.write_all(b"[")?;
+                for (k, item) in items.iter().enumerate() {
+                    if k > 0 {
+                        writer.write_all(b",")?;
+                    }
+                    layout.newline(writer, depth + 1)?;
+                    item.write_json_helper(writer, layout, depth + 1)?;
+                }
+                if items.is_empty() == false {
+                    layout.newline(writer, depth)?;
+                }
+                writer.write_all(b"]")
+            }
+            Self::Object(props) => {
+                writer.write_all(b"{")?;
+                for (k, (key, val)) in props.iter().enumerate() {
+                    if k > 0 {
+                        writer.write_all(b",")?;
+                    }
+                    layout.newline(writer, depth + 1)?;
+                    write_string(writer, key)?;
+                    layout.colon(writer)?;
+                    val.write_json_helper(writer, layout, depth + 1)?;
+                }
+                if props.is_empty() == false {
+                    layout.newline(writer, depth)?;
+                }
+                writer.write_all(b"}")
+            }
+        }
+    }
+}
@@ -1,2 +1,3 @@
 pub mod json; 
+pub mod limits;
 pub mod xml; 
\ No newline at end of file
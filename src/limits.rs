@@ -0,0 +1,97 @@
+use std::fmt;
+
+/// Which of the `Limits` a document went over
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Limit {
+    Depth,
+    DocumentBytes,
+    Tokens,
+    StringLength,
+    Attributes,
+    EntityDepth,
+    EntityExpansion,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Depth => write!(f, "maximum nesting depth"),
+            Self::DocumentBytes => write!(f, "maximum document size"),
+            Self::Tokens => write!(f, "maximum token count"),
+            Self::StringLength => write!(f, "maximum string length"),
+            Self::Attributes => write!(f, "maximum attributes per element"),
+            Self::EntityDepth => write!(f, "maximum entity nesting depth"),
+            Self::EntityExpansion => write!(f, "maximum entity expansion size"),
+        }
+    }
+}
+
+/// # Description
+/// Caps on how much a single document is allowed to make the json and xml parsers do
+/// # Comments
+/// - `Limits::default()` doesn't limit anything, which is how the parsers have always behaved. The one
+/// exception is xml entity expansion, which is capped like `untrusted()` does since an unbounded
+/// expansion is never what anyone wants. Use `Limits::untrusted()` (or your own numbers) for anything
+/// that comes off the network
+/// - `Limits::none()` really doesn't limit anything, entities included
+/// - Every check happens while lexing/parsing, so an oversized document is rejected before the tree is built
+/// - Lengths are in bytes
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Limits {
+    /// How many arrays/objects (or xml elements) may be open at once
+    pub max_depth: usize,
+    pub max_document_bytes: usize,
+    pub max_tokens: usize,
+    /// The longest string, property name or number (json), or tag name, text run or attribute value (xml)
+    pub max_string_len: usize,
+    /// Only used by the xml parser
+    pub max_attributes: usize,
+    /// How many entity references may be expanded inside one another (xml only)
+    pub max_entity_depth: usize,
+    /// Total bytes all entity expansions of a document may produce, this is what stops
+    /// "billion laughs" style input (xml only)
+    pub max_entity_expansion: usize,
+}
+
+impl Limits {
+    pub fn none() -> Self {
+        Self {
+            max_depth: usize::MAX,
+            max_document_bytes: usize::MAX,
+            max_tokens: usize::MAX,
+            max_string_len: usize::MAX,
+            max_attributes: usize::MAX,
+            max_entity_depth: usize::MAX,
+            max_entity_expansion: usize::MAX,
+        }
+    }
+
+    /// # Description
+    /// Conservative limits for input you don't control
+    /// # Comments
+    /// - 128 levels of nesting, 16 MiB documents, a million tokens, 1 MiB strings and 256 attributes per element.
+    /// That's plenty for any sane payload while keeping the worst case memory use in check
+    /// - Entities may nest 8 deep and expand to 1 MiB in total
+    pub fn untrusted() -> Self {
+        Self {
+            max_depth: 128,
+            max_document_bytes: 16 * 1024 * 1024,
+            max_tokens: 1_000_000,
+            max_string_len: 1024 * 1024,
+            max_attributes: 256,
+            max_entity_depth: 8,
+            max_entity_expansion: 1024 * 1024,
+        }
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        let untrusted = Self::untrusted();
+        Self {
+            max_entity_depth: untrusted.max_entity_depth,
+            max_entity_expansion: untrusted.max_entity_expansion,
+            ..Self::none()
+        }
+    }
+}
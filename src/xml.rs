@@ -1,8 +1,13 @@
 pub mod ast; 
+pub mod dtd;
 pub mod lexer; 
 
+use crate::limits::Limit;
+
 #[derive(Debug)]
 pub enum XMLErrorKind {
     TokenizerErr(&'static str),
     ParserErr(&'static str),
+    /// The document went over one of the `Limits` given to `parse_with_limits(..)`
+    LimitExceeded(Limit),
 }
\ No newline at end of file
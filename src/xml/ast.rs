@@ -1,15 +1,36 @@
-use super::lexer::{XmlLexer, XmlToken, XmlTokenKind};
+use super::dtd::Doctype;
+use super::lexer::{escape_attribute, escape_text, XmlLexer, XmlToken, XmlTokenKind};
 use super::XMLErrorKind;
+use crate::limits::{Limit, Limits};
 use sandboxed_collections::naryforest::{Pointer, NULL, *};
 use std::ops;
 
+/// # Description
+/// Knobs for `XmlParser::parse_with(..)`
+/// # Comments
+/// - The default keeps comments and only limits entity expansion (see `Limits::default()`), which is
+/// what `parse(..)` does
+#[derive(Clone, Debug, Default)]
+pub struct XmlParseOptions {
+    /// Drop `<!-- .. -->` comments instead of keeping them as `Comment` nodes
+    pub strip_comments: bool,
+    pub limits: Limits,
+}
+
 /// Can correctly parse  only  a subset of XML grammar *only*.\
 /// I repeat, this code  cannot parse the entire XML grammar. The parser was intented to parse xml that stores raw data.\
-/// All the `<!DOCTYPE .. >`, `<!ENTITY ..>` stuff has been cut out of the grammar in this parser \
-/// Comments should still work though.
+/// A `<!DOCTYPE .. >` is parsed into `doctype` and the internal entities it declares are expanded,
+/// but external DTDs and entities are never loaded.
 pub struct XmlParser {
     pub lexer: XmlLexer,
     pub ast: NaryForest<XmlToken>,
+    /// Nodes before the root element (the declaration, doctype, processing instructions and comments),
+    /// in document order
+    pub prolog: Vec<Pointer>,
+    /// Processing instructions and comments after the root element
+    pub epilog: Vec<Pointer>,
+    /// The parsed `<!DOCTYPE ..>`, its raw text is also kept as a `Doctype` node in `prolog`
+    pub doctype: Option<Doctype>,
 }
 
 impl XmlParser {
@@ -17,34 +38,131 @@ impl XmlParser {
         XmlParser {
             lexer: XmlLexer::new(),
             ast: NaryForest::new(),
+            prolog: Vec::new(),
+            epilog: Vec::new(),
+            doctype: None,
         }
     }
 
     ///Builds AST with an explicit stack
     pub fn parse(&mut self, src: &String) -> Result<(), XMLErrorKind> {
+        self.parse_with(src, &XmlParseOptions::default())
+    }
+
+    /// # Description
+    /// Like `parse(..)` but rejects documents that go over `limits`
+    /// # Comments
+    /// - Use this (with `Limits::untrusted()` or similar) for xml that comes off the network,
+    /// `to_xml()` and friends recurse so an unbounded depth can blow the stack later on
+    pub fn parse_with_limits(&mut self, src: &str, limits: &Limits) -> Result<(), XMLErrorKind> {
+        let options = XmlParseOptions {
+            limits: *limits,
+            ..XmlParseOptions::default()
+        };
+        self.parse_with(src, &options)
+    }
+
+    /// # Description
+    /// Builds the AST, the way `options` asks for
+    /// # Comments
+    /// - Comments, processing instructions, the declaration and the doctype before the root element end up
+    /// in `prolog`, comments and processing instructions after it in `epilog`. Everywhere else they are
+    /// regular child nodes
+    /// - Entity references are expanded as plain text, an entity whose replacement text holds markup
+    /// doesn't add elements to the tree
+    /// - The parser can be reused, whatever the last call left in `ast`, `prolog`, `epilog` and `doctype`
+    /// is thrown away
+    pub fn parse_with(&mut self, src: &str, options: &XmlParseOptions) -> Result<(), XMLErrorKind> {
+        let limits = &options.limits;
+        // nothing from a previous document carries over
+        self.ast = NaryForest::new();
+        self.prolog.clear();
+        self.epilog.clear();
+        self.doctype = None;
         //lex raw text first
-        self.lexer.lex(src.as_str())?;
+        self.lexer.lex_with_limits(src, limits)?;
+        self.doctype = self.lexer.doctype.take();
         // self.print_tokens();
 
+        //everything before the root element goes in the prolog
+        let mut first = 0;
+        while let Some(Some(token)) = self.lexer.tokens.get(first) {
+            match token.token_kind {
+                XmlTokenKind::Declaration if first > 0 => {
+                    return Err(XMLErrorKind::ParserErr(
+                        "the xml declaration must come first",
+                    ))
+                }
+                XmlTokenKind::Comment if options.strip_comments => first += 1,
+                XmlTokenKind::Declaration
+                | XmlTokenKind::Doctype
+                | XmlTokenKind::ProcessingInstruction
+                | XmlTokenKind::Comment => {
+                    let token = self.lexer.tokens[first].take().unwrap();
+                    let node_ptr = self.ast.allocate(token);
+                    self.prolog.push(node_ptr);
+                    first += 1;
+                }
+                _ => break,
+            }
+        }
+        if first == self.lexer.tokens.len() {
+            return Err(XMLErrorKind::ParserErr("document has no root element"));
+        }
+
         //init ast_stack with the root_node
-        let root_token = self.lexer.tokens[0].take().unwrap();
+        if limits.max_depth == 0 {
+            return Err(XMLErrorKind::LimitExceeded(Limit::Depth));
+        }
+        let root_token = self.lexer.tokens[first].take().unwrap();
         let root_node_ptr = self.ast.allocate(root_token);
         let mut parent_stack = vec![root_node_ptr];
 
-        //if theres only one empty-tag, the xml is valid, clear parent_stack
+        //if the root is an empty-tag, the xml is valid, clear parent_stack
         let root_node_ref = self.ast[root_node_ptr].data.as_ref().unwrap();
-        if self.lexer.tokens.len() == 1 && root_node_ref.token_kind.is_emptytag() {
+        if root_node_ref.token_kind.is_emptytag() {
             parent_stack.clear();
         }
 
-        for k in 1..self.lexer.tokens.len() {
+        for k in first + 1..self.lexer.tokens.len() {
+            let current_token = self.lexer.tokens[k].take().unwrap();
+            match current_token.token_kind {
+                XmlTokenKind::Declaration => {
+                    return Err(XMLErrorKind::ParserErr(
+                        "the xml declaration must come first",
+                    ))
+                }
+                XmlTokenKind::Doctype => {
+                    return Err(XMLErrorKind::ParserErr(
+                        "the DOCTYPE must come before the root element",
+                    ))
+                }
+                XmlTokenKind::Comment if options.strip_comments => continue,
+                _ => (),
+            }
+            if parent_stack.is_empty() {
+                //past the root element, only processing instructions and comments are kept
+                if let XmlTokenKind::ProcessingInstruction | XmlTokenKind::Comment =
+                    current_token.token_kind
+                {
+                    let node_ptr = self.ast.allocate(current_token);
+                    self.epilog.push(node_ptr);
+                }
+                continue;
+            }
             if let Some(&parent_ptr) = parent_stack.last() {
-                let current_token = self.lexer.tokens[k].take().unwrap();
                 match current_token.token_kind {
-                    XmlTokenKind::OpenTag => {
+                    XmlTokenKind::OpenTag | XmlTokenKind::EmptyTag => {
+                        // an empty element is just as deep as one with children
+                        if parent_stack.len() >= limits.max_depth {
+                            return Err(XMLErrorKind::LimitExceeded(Limit::Depth));
+                        }
+                        let is_open = current_token.token_kind == XmlTokenKind::OpenTag;
                         let node_ptr = self.ast.allocate(current_token);
                         self.ast.add_child(parent_ptr, node_ptr);
-                        parent_stack.push(node_ptr);
+                        if is_open {
+                            parent_stack.push(node_ptr);
+                        }
                     }
                     XmlTokenKind::CloseTag => {
                         let open_tag_name = &self.ast[parent_ptr].data.as_ref().unwrap().content;
@@ -56,11 +174,10 @@ impl XmlParser {
                             return Err(XMLErrorKind::ParserErr("Close Tag without Opening Tag"));
                         }
                     }
-                    XmlTokenKind::ContentTag => {
-                        let node_ptr = self.ast.allocate(current_token);
-                        self.ast.add_child(parent_ptr, node_ptr);
-                    }
-                    XmlTokenKind::EmptyTag => {
+                    XmlTokenKind::ContentTag
+                    | XmlTokenKind::CData
+                    | XmlTokenKind::ProcessingInstruction
+                    | XmlTokenKind::Comment => {
                         let node_ptr = self.ast.allocate(current_token);
                         self.ast.add_child(parent_ptr, node_ptr);
                     }
@@ -86,12 +203,22 @@ impl XmlParser {
     /// # Comments
     /// This function lets you drop the lexer now that its not needed
     pub fn into_ast(self) -> XmlAst {
-        XmlAst { ast: self.ast }
+        XmlAst {
+            ast: self.ast,
+            prolog: self.prolog,
+            epilog: self.epilog,
+            doctype: self.doctype,
+        }
     }
 }
 #[derive(Clone)]
 pub struct XmlAst {
     pub ast: NaryForest<XmlToken>,
+    /// Nodes before the root element, `to_xml()` writes them first
+    pub prolog: Vec<Pointer>,
+    /// Nodes after the root element, `to_xml()` writes them last
+    pub epilog: Vec<Pointer>,
+    pub doctype: Option<Doctype>,
 }
 
 impl XmlAst {
@@ -174,6 +301,26 @@ impl XmlAst {
         self.ast.allocate(duplicated_token)
     }
 
+    /// # Description
+    /// Concatenates the text (`ContentTag` and `CData` alike) of `node` and everything below it, in document order
+    pub fn inner_text(&self, node: Pointer) -> String {
+        let mut text = String::new();
+        self.inner_text_helper(node, &mut text);
+        text
+    }
+
+    fn inner_text_helper(&self, node: Pointer, text: &mut String) {
+        if node == NULL {
+            return;
+        }
+        if let Some(val) = self.ast[node].data.as_ref().and_then(XmlToken::text) {
+            text.push_str(val);
+        }
+        for &child in self.ast[node].children.iter() {
+            self.inner_text_helper(child, text);
+        }
+    }
+
     pub fn print_tree(&self) {
         let mut char_stack = String::new();
         self.print_tree_helper(self.ast.root_list[0], &mut char_stack, ".");
@@ -205,9 +352,15 @@ impl XmlAst {
     ///converts the xml AST back to text form
     pub fn to_xml(&self) -> String {
         let mut xml = String::new();
+        for &node in self.prolog.iter() {
+            self.to_xml_helper(node, &mut xml);
+        }
         if let Some(&root) = self.ast.root_list.get(0) {
             self.to_xml_helper(root, &mut xml);
         }
+        for &node in self.epilog.iter() {
+            self.to_xml_helper(node, &mut xml);
+        }
         xml
     }
     ///  The recursive helper function that renders the tree into a string (spacing is intact)\
@@ -224,7 +377,9 @@ impl XmlAst {
                 XmlTokenKind::OpenTag => {
                     xml_stream.push_str(format!("<{}", token.content).as_str());
                     for (key, val) in token.attribs.iter() {
-                        xml_stream.push_str(format!(" {}=\"{}\"", key, val).as_str());
+                        xml_stream.push_str(format!(" {}=\"", key).as_str());
+                        escape_attribute(val, xml_stream);
+                        xml_stream.push('\"');
                     }
                     xml_stream.push('>');
                     for &child in self.ast[node_ptr].children.iter() {
@@ -233,12 +388,24 @@ impl XmlAst {
                     xml_stream.push_str(format!("</{}>", token.content).as_str());
                 }
                 XmlTokenKind::ContentTag => {
-                    xml_stream.push_str(format!("{}", token.content).as_str());
+                    escape_text(&token.content, xml_stream);
+                }
+                XmlTokenKind::CData => write_cdata(&token.content, xml_stream),
+                XmlTokenKind::Declaration | XmlTokenKind::ProcessingInstruction => {
+                    write_processing_instruction(token, xml_stream)
+                }
+                XmlTokenKind::Comment => {
+                    xml_stream.push_str(format!("<!--{}-->", token.content).as_str());
+                }
+                XmlTokenKind::Doctype => {
+                    xml_stream.push_str(format!("<!DOCTYPE{}>", token.content).as_str());
                 }
                 XmlTokenKind::EmptyTag => {
                     xml_stream.push_str(format!("<{}", token.content).as_str());
                     for (key, val) in token.attribs.iter() {
-                        xml_stream.push_str(format!(" {}=\"{}\"", key, val).as_str());
+                        xml_stream.push_str(format!(" {}=\"", key).as_str());
+                        escape_attribute(val, xml_stream);
+                        xml_stream.push('\"');
                     }
                     xml_stream.push_str("/>");
                 }
@@ -251,9 +418,15 @@ impl XmlAst {
     /// Like to_xml(..) with removes all spacing
     pub fn to_xml_trim(&self) -> String {
         let mut xml = String::new();
+        for &node in self.prolog.iter() {
+            self.to_xml_helper_trim(node, &mut xml);
+        }
         if let Some(&root) = self.ast.root_list.get(0) {
             self.to_xml_helper_trim(root, &mut xml);
         }
+        for &node in self.epilog.iter() {
+            self.to_xml_helper_trim(node, &mut xml);
+        }
         xml
     }
 
@@ -268,7 +441,9 @@ impl XmlAst {
                 XmlTokenKind::OpenTag => {
                     xml_stream.push_str(format!("<{}", token.content).as_str().trim());
                     for (key, val) in token.attribs.iter() {
-                        xml_stream.push_str(format!(" {}=\"{}\"", key.trim(), val.trim()).as_str());
+                        xml_stream.push_str(format!(" {}=\"", key.trim()).as_str());
+                        escape_attribute(val.trim(), xml_stream);
+                        xml_stream.push('\"');
                     }
                     xml_stream.push('>');
                     for &child in self.ast[node_ptr].children.iter() {
@@ -277,12 +452,25 @@ impl XmlAst {
                     xml_stream.push_str(format!("</{}>", token.content.trim()).as_str());
                 }
                 XmlTokenKind::ContentTag => {
-                    xml_stream.push_str(format!("{}", token.content.trim()).as_str());
+                    escape_text(token.content.trim(), xml_stream);
+                }
+                // CDATA is usually a payload (scripts, embedded markup) so it's never trimmed
+                XmlTokenKind::CData => write_cdata(&token.content, xml_stream),
+                XmlTokenKind::Declaration | XmlTokenKind::ProcessingInstruction => {
+                    write_processing_instruction(token, xml_stream)
+                }
+                XmlTokenKind::Comment => {
+                    xml_stream.push_str(format!("<!--{}-->", token.content).as_str());
+                }
+                XmlTokenKind::Doctype => {
+                    xml_stream.push_str(format!("<!DOCTYPE{}>", token.content).as_str());
                 }
                 XmlTokenKind::EmptyTag => {
                     xml_stream.push_str(format!("<{} ", token.content.trim()).as_str());
                     for (key, val) in token.attribs.iter() {
-                        xml_stream.push_str(format!(" {}=\"{}\"", key.trim(), val.trim()).as_str());
+                        xml_stream.push_str(format!(" {}=\"", key.trim()).as_str());
+                        escape_attribute(val.trim(), xml_stream);
+                        xml_stream.push('\"');
                     }
                     xml_stream.push_str("/>");
                 }
@@ -293,6 +481,30 @@ impl XmlAst {
     }
 }
 
+/// writes a CDATA section, a `]]>` inside the text is split across two sections since it can't be escaped
+fn write_cdata(text: &str, xml_stream: &mut String) {
+    xml_stream.push_str("<![CDATA[");
+    xml_stream.push_str(&text.replace("]]>", "]]]]><![CDATA[>"));
+    xml_stream.push_str("]]>");
+}
+
+/// writes `<?xml version=".." ...?>` for the declaration and `<?target data?>` for other instructions.
+/// Pseudo-attributes can't hold references, so a value with a `"` in it (only possible when the token was
+/// built by hand, the lexer rejects those) is put in single quotes instead of being escaped
+fn write_processing_instruction(token: &XmlToken, xml_stream: &mut String) {
+    xml_stream.push_str("<?");
+    xml_stream.push_str(&token.content);
+    for (key, val) in token.attribs.iter() {
+        let quote = if val.contains('"') { '\'' } else { '"' };
+        xml_stream.push_str(format!(" {}={}{}{}", key, quote, val, quote).as_str());
+    }
+    if token.data.is_empty() == false {
+        xml_stream.push(' ');
+        xml_stream.push_str(&token.data);
+    }
+    xml_stream.push_str("?>");
+}
+
 impl ops::Index<Pointer> for XmlAst {
     type Output = NaryNode<XmlToken>;
     fn index(&self, index: Pointer) -> &Self::Output {
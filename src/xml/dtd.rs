@@ -0,0 +1,365 @@
+use super::XMLErrorKind;
+
+/// `SYSTEM "uri"` or `PUBLIC "public id" "uri"`
+#[derive(Clone, PartialEq, Debug)]
+pub enum ExternalId {
+    System(String),
+    /// The system literal is only optional in `<!NOTATION ..>` declarations
+    Public {
+        public: String,
+        system: Option<String>,
+    },
+}
+
+/// How an attribute declared in an `<!ATTLIST ..>` gets its value when an element leaves it out
+#[derive(Clone, PartialEq, Debug)]
+pub enum AttDefault {
+    Required,
+    Implied,
+    Fixed(String),
+    Value(String),
+}
+
+/// One attribute of an `<!ATTLIST ..>`
+#[derive(Clone, PartialEq, Debug)]
+pub struct AttDef {
+    pub name: String,
+    /// `CDATA`, `ID`, `NMTOKENS`, an enumeration like `(a|b)`, `NOTATION (x|y)` and so on, as written
+    pub att_type: String,
+    pub default: AttDefault,
+}
+
+/// What an `<!ENTITY ..>` stands for
+#[derive(Clone, PartialEq, Debug)]
+pub enum EntityValue {
+    /// The replacement text, exactly as written between the quotes
+    Internal(String),
+    /// An entity that lives in another file, `notation` is set for unparsed (`NDATA`) entities
+    External {
+        id: ExternalId,
+        notation: Option<String>,
+    },
+}
+
+/// One declaration of the internal subset
+#[derive(Clone, PartialEq, Debug)]
+pub enum MarkupDecl {
+    /// `content_spec` is kept as written, e.g. `EMPTY`, `ANY` or `(title, body*)`
+    Element {
+        name: String,
+        content_spec: String,
+    },
+    AttList {
+        element: String,
+        attributes: Vec<AttDef>,
+    },
+    /// `parameter` is true for `<!ENTITY % name ..>`, which can only be used inside the DTD
+    Entity {
+        name: String,
+        parameter: bool,
+        value: EntityValue,
+    },
+    Notation {
+        name: String,
+        id: ExternalId,
+    },
+}
+
+/// # Description
+/// A parsed `<!DOCTYPE ..>`
+/// # Comments
+/// - Comments, processing instructions and parameter entity references (`%name;`) inside the internal
+/// subset are skipped. External subsets are never fetched
+#[derive(Clone, PartialEq, Debug)]
+pub struct Doctype {
+    /// Name of the root element
+    pub name: String,
+    pub external_id: Option<ExternalId>,
+    /// The internal subset (the part between `[` and `]`) in document order
+    pub declarations: Vec<MarkupDecl>,
+}
+
+impl Doctype {
+    /// # Description
+    /// Looks up a general entity by name
+    /// # Comments
+    /// - If an entity is declared twice the first declaration wins, like the spec says
+    pub fn entity(&self, name: &str) -> Option<&EntityValue> {
+        self.declarations
+            .iter()
+            .filter_map(|decl| match decl {
+                MarkupDecl::Entity {
+                    name: entity_name,
+                    parameter: false,
+                    value,
+                } if entity_name == name => Some(value),
+                _ => None,
+            })
+            .next()
+    }
+}
+
+/// # Description
+/// Parses the inside of a doctype declaration
+/// # Arguments
+/// - `raw` : everything between `<!DOCTYPE` and the closing `>`
+pub fn parse_doctype(raw: &str) -> Result<Doctype, XMLErrorKind> {
+    let mut scanner = Scanner { rest: raw };
+    if scanner.skip_whitespace() == false {
+        return Err(XMLErrorKind::ParserErr(
+            "expected whitespace after '<!DOCTYPE'",
+        ));
+    }
+    let name = scanner
+        .name()
+        .ok_or(XMLErrorKind::ParserErr("DOCTYPE is missing its name"))?;
+    scanner.skip_whitespace();
+    let external_id = scanner.external_id(false)?;
+    scanner.skip_whitespace();
+
+    let mut declarations = Vec::new();
+    if scanner.eat("[") {
+        loop {
+            scanner.skip_whitespace();
+            if scanner.eat("]") {
+                break;
+            } else if scanner.eat("<!--") {
+                scanner.skip_past("-->")?;
+            } else if scanner.eat("<?") {
+                scanner.skip_past("?>")?;
+            } else if scanner.eat("%") {
+                scanner.name();
+                scanner.expect(";")?;
+            } else if scanner.eat("<!") {
+                declarations.push(scanner.markup_decl()?);
+            } else {
+                return Err(XMLErrorKind::ParserErr(
+                    "unexpected text in the DTD internal subset",
+                ));
+            }
+        }
+        scanner.skip_whitespace();
+    }
+    if scanner.rest.is_empty() == false {
+        return Err(XMLErrorKind::ParserErr(
+            "unexpected text at the end of the DOCTYPE",
+        ));
+    }
+    Ok(Doctype {
+        name,
+        external_id,
+        declarations,
+    })
+}
+
+/// a cursor over the text of a doctype declaration
+struct Scanner<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Scanner<'a> {
+    /// returns whether there was any whitespace to skip
+    fn skip_whitespace(&mut self) -> bool {
+        let trimmed = self.rest.trim_start_matches([' ', '\t', '\r', '\n']);
+        let skipped = trimmed.len() != self.rest.len();
+        self.rest = trimmed;
+        skipped
+    }
+
+    fn eat(&mut self, prefix: &str) -> bool {
+        if self.rest.starts_with(prefix) {
+            self.rest = &self.rest[prefix.len()..];
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, prefix: &'static str) -> Result<(), XMLErrorKind> {
+        if self.eat(prefix) {
+            Ok(())
+        } else {
+            Err(XMLErrorKind::ParserErr("malformed DTD declaration"))
+        }
+    }
+
+    fn skip_past(&mut self, end: &str) -> Result<(), XMLErrorKind> {
+        match self.rest.find(end) {
+            Some(k) => {
+                self.rest = &self.rest[k + end.len()..];
+                Ok(())
+            }
+            None => Err(XMLErrorKind::ParserErr(
+                "unterminated comment or PI in the DTD",
+            )),
+        }
+    }
+
+    /// a name, or a name token like `#PCDATA`, stops at whitespace and punctuation
+    fn name(&mut self) -> Option<String> {
+        let end = self
+            .rest
+            .find(|c: char| c.is_whitespace() || "[]<>()|,?*+'\"%;=".contains(c))
+            .unwrap_or(self.rest.len());
+        if end == 0 {
+            return None;
+        }
+        let name = self.rest[..end].to_string();
+        self.rest = &self.rest[end..];
+        Some(name)
+    }
+
+    /// a `"double"` or `'single'` quoted literal, without the quotes
+    fn quoted(&mut self) -> Result<String, XMLErrorKind> {
+        let malformed = || XMLErrorKind::ParserErr("expected a quoted literal in the DTD");
+        let quote = self.rest.chars().next().filter(|&q| q == '"' || q == '\'');
+        let quote = quote.ok_or_else(malformed)?;
+        let close = self.rest[1..].find(quote).ok_or_else(malformed)? + 1;
+        let literal = self.rest[1..close].to_string();
+        self.rest = &self.rest[close + 1..];
+        Ok(literal)
+    }
+
+    /// `SYSTEM ".."` or `PUBLIC ".." ".."`, `None` if neither keyword is next.
+    /// Notations may leave out the system literal of a public id
+    fn external_id(&mut self, notation: bool) -> Result<Option<ExternalId>, XMLErrorKind> {
+        if self.eat("SYSTEM") {
+            self.skip_whitespace();
+            return Ok(Some(ExternalId::System(self.quoted()?)));
+        }
+        if self.eat("PUBLIC") == false {
+            return Ok(None);
+        }
+        self.skip_whitespace();
+        let public = self.quoted()?;
+        self.skip_whitespace();
+        let system = if notation && self.rest.starts_with(['"', '\'']) == false {
+            None
+        } else {
+            Some(self.quoted()?)
+        };
+        Ok(Some(ExternalId::Public { public, system }))
+    }
+
+    /// everything after a `<!` in the internal subset, up to and including the closing `>`
+    fn markup_decl(&mut self) -> Result<MarkupDecl, XMLErrorKind> {
+        let keyword = self.name().unwrap_or_default();
+        if self.skip_whitespace() == false {
+            return Err(XMLErrorKind::ParserErr("malformed DTD declaration"));
+        }
+        let decl = match keyword.as_str() {
+            "ELEMENT" => {
+                let name = self.required_name()?;
+                let end = self
+                    .rest
+                    .find('>')
+                    .ok_or(XMLErrorKind::ParserErr("unterminated ELEMENT declaration"))?;
+                let content_spec = self.rest[..end].trim().to_string();
+                self.rest = &self.rest[end..];
+                MarkupDecl::Element { name, content_spec }
+            }
+            "ATTLIST" => {
+                let element = self.required_name()?;
+                let mut attributes = Vec::new();
+                loop {
+                    self.skip_whitespace();
+                    if self.rest.starts_with('>') {
+                        break;
+                    }
+                    attributes.push(self.att_def()?);
+                }
+                MarkupDecl::AttList {
+                    element,
+                    attributes,
+                }
+            }
+            "ENTITY" => {
+                let parameter = self.eat("%");
+                if parameter {
+                    self.skip_whitespace();
+                }
+                let name = self.required_name()?;
+                self.skip_whitespace();
+                let value = match self.external_id(false)? {
+                    Some(id) => {
+                        self.skip_whitespace();
+                        let notation = if self.eat("NDATA") {
+                            self.skip_whitespace();
+                            Some(self.required_name()?)
+                        } else {
+                            None
+                        };
+                        EntityValue::External { id, notation }
+                    }
+                    None => EntityValue::Internal(self.quoted()?),
+                };
+                MarkupDecl::Entity {
+                    name,
+                    parameter,
+                    value,
+                }
+            }
+            "NOTATION" => {
+                let name = self.required_name()?;
+                self.skip_whitespace();
+                let id = self.external_id(true)?.ok_or(XMLErrorKind::ParserErr(
+                    "NOTATION needs a SYSTEM or PUBLIC id",
+                ))?;
+                MarkupDecl::Notation { name, id }
+            }
+            _ => return Err(XMLErrorKind::ParserErr("unknown declaration in the DTD")),
+        };
+        self.skip_whitespace();
+        self.expect(">")?;
+        Ok(decl)
+    }
+
+    fn required_name(&mut self) -> Result<String, XMLErrorKind> {
+        self.name()
+            .ok_or(XMLErrorKind::ParserErr("expected a name in the DTD"))
+    }
+
+    /// `name type default` inside an `<!ATTLIST ..>`
+    fn att_def(&mut self) -> Result<AttDef, XMLErrorKind> {
+        let name = self.required_name()?;
+        self.skip_whitespace();
+        let att_type = if self.rest.starts_with('(') {
+            self.group()?
+        } else {
+            let keyword = self.required_name()?;
+            if keyword == "NOTATION" {
+                self.skip_whitespace();
+                format!("NOTATION {}", self.group()?)
+            } else {
+                keyword
+            }
+        };
+        self.skip_whitespace();
+        let default = if self.eat("#REQUIRED") {
+            AttDefault::Required
+        } else if self.eat("#IMPLIED") {
+            AttDefault::Implied
+        } else if self.eat("#FIXED") {
+            self.skip_whitespace();
+            AttDefault::Fixed(self.quoted()?)
+        } else {
+            AttDefault::Value(self.quoted()?)
+        };
+        Ok(AttDef {
+            name,
+            att_type,
+            default,
+        })
+    }
+
+    /// an enumeration like `(a | b)`, returned as written
+    fn group(&mut self) -> Result<String, XMLErrorKind> {
+        let end = self
+            .rest
+            .find(')')
+            .ok_or(XMLErrorKind::ParserErr("unterminated '(' in the DTD"))?;
+        let group = self.rest[..=end].to_string();
+        self.rest = &self.rest[end + 1..];
+        Ok(group)
+    }
+}
@@ -1,12 +1,21 @@
-use std::array;
+use std::{array, iter::Peekable, str::Chars};
 
-use super::XMLErrorKind;
+use super::{
+    dtd::{parse_doctype, Doctype, EntityValue},
+    XMLErrorKind,
+};
+use crate::limits::{Limit, Limits};
 /// # Description
 /// A token is either:
 /// - `OpenTag`
 /// - `CloseTag`
 /// - `EmptyTag`
 /// - `ContentTag` - raw text
+/// - `CData` - the text of a `<![CDATA[ ... ]]>` section
+/// - `Declaration` - the `<?xml version="1.0" ...?>` at the top of a document
+/// - `ProcessingInstruction` - any other `<?target data?>`
+/// - `Comment` - the text of a `<!-- ... -->`
+/// - `Doctype` - everything after `<!DOCTYPE` in a `<!DOCTYPE ...>`
 /// # Comments
 /// The rest of the `TokenKinds` are for states in the lexer
 #[derive(Copy, Clone, PartialEq)]
@@ -19,10 +28,22 @@ pub enum XmlTokenKind {
     EmptyTag,
     ///This tag just has text and nothing else in it
     ContentTag,
+    ///The text inside `<![CDATA[` and `]]>`, kept verbatim
+    CData,
+    ///The `<?xml ...?>` declaration, `version`/`encoding`/`standalone` are stored as attributes
+    Declaration,
+    ///Tags like `<?xml-stylesheet href="a.xsl"?>`, `content` is the target and `data` the rest
+    ProcessingInstruction,
+    ///The text between `<!--` and `-->`
+    Comment,
+    ///A `<!DOCTYPE ..>`, `content` is the raw text after `DOCTYPE` so it can be written back out as is
+    Doctype,
     AuxUnknown,
     AuxOpenAttribOpen,
     AuxOpenAttribClose,
     AuxComment,
+    ///Markup declarations like `<!DOCTYPE ..>`
+    AuxMarkupDecl,
 }
 impl XmlTokenKind {
     pub fn is_emptytag(&self) -> bool {
@@ -32,6 +53,11 @@ impl XmlTokenKind {
             false
         }
     }
+
+    /// `ContentTag` and `CData` both hold the text of an element
+    pub fn is_text(&self) -> bool {
+        matches!(self, Self::ContentTag | Self::CData)
+    }
 }
 
 #[derive(Clone)]
@@ -39,6 +65,8 @@ pub struct XmlToken {
     pub token_kind: XmlTokenKind,
     pub content: String,
     pub attribs: Vec<(String, String)>,
+    /// The data of a processing instruction, empty for everything else
+    pub data: String,
 }
 
 impl XmlToken {
@@ -47,6 +75,7 @@ impl XmlToken {
             token_kind,
             content,
             attribs: Vec::new(),
+            data: String::new(),
         }
     }
     /// # Description
@@ -62,6 +91,41 @@ impl XmlToken {
             .map(|(_, v)| v)
     }
 
+    /// The text of a `ContentTag` or `CData` token, `None` for tags
+    pub fn text(&self) -> Option<&str> {
+        if self.token_kind.is_text() {
+            Some(self.content.as_str())
+        } else {
+            None
+        }
+    }
+
+    /// The `version` of a `Declaration` token
+    pub fn version(&self) -> Option<&str> {
+        self.declaration_attrib("version")
+    }
+
+    /// The `encoding` of a `Declaration` token, if the document spelled it out
+    pub fn encoding(&self) -> Option<&str> {
+        self.declaration_attrib("encoding")
+    }
+
+    /// `Some(true)` for `standalone="yes"`, `Some(false)` for `"no"` and `None` if it's missing or anything else
+    pub fn standalone(&self) -> Option<bool> {
+        match self.declaration_attrib("standalone") {
+            Some("yes") => Some(true),
+            Some("no") => Some(false),
+            _ => None,
+        }
+    }
+
+    fn declaration_attrib(&self, key: &str) -> Option<&str> {
+        match self.token_kind {
+            XmlTokenKind::Declaration => self.get_attrib(key).map(|val| val.as_str()),
+            _ => None,
+        }
+    }
+
     pub fn get_attrib_mut(&mut self, attrib: &str) -> Option<&mut String> {
         self.attribs
             .iter_mut()
@@ -76,50 +140,83 @@ impl Default for XmlToken {
             token_kind: XmlTokenKind::AuxUnknown,
             content: String::new(),
             attribs: Vec::new(),
+            data: String::new(),
         }
     }
 }
 
 /// Can correctly parse  only  a subset of XML grammar *only*.\
 /// I repeat, this code  cannot parse the entire XML grammar. The parser was intented to parse xml that stores raw data.\
-/// All the `<!DOCTYPE .. >`, `<!ENTITY ..>` stuff has been cut out of the grammar in this parser \
-/// Comments should still work though.
+/// The `<!DOCTYPE .. >` and its internal subset are understood, and the internal entities it declares get
+/// expanded in text and attribute values. External DTDs and external entities are never loaded though.
 pub struct XmlLexer {
     pub tokens: Vec<Option<XmlToken>>,
+    /// The parsed `<!DOCTYPE ..>`, if the document had one
+    pub doctype: Option<Doctype>,
+    limits: Limits,
+    /// bytes of replacement text expanded so far, checked against `Limits::max_entity_expansion`
+    entity_bytes: usize,
 }
 
 impl XmlLexer {
     pub fn new() -> XmlLexer {
-        XmlLexer { tokens: Vec::new() }
+        XmlLexer {
+            tokens: Vec::new(),
+            doctype: None,
+            limits: Limits::default(),
+            entity_bytes: 0,
+        }
     }
 
     ///tokenizes raw  xml text with FSM logic
     pub fn lex(&mut self, src: &str) -> Result<(), XMLErrorKind> {
+        self.lex_with_limits(src, &Limits::default())
+    }
+
+    /// # Description
+    /// Like `lex(..)` but fails with `XMLErrorKind::LimitExceeded` as soon as `src` goes over one of `limits`
+    /// # Comments
+    /// - Nesting depth isn't known until parsing, `XmlParser` checks that one
+    pub fn lex_with_limits(&mut self, src: &str, limits: &Limits) -> Result<(), XMLErrorKind> {
+        self.tokens.clear();
+        if src.len() > limits.max_document_bytes {
+            return Err(XMLErrorKind::LimitExceeded(Limit::DocumentBytes));
+        }
+        self.limits = *limits;
+        self.entity_bytes = 0;
+        self.doctype = None;
         let mut state = XmlTokenKind::AuxUnknown;
         let mut accum = String::new();
         let mut current_key = String::new();
 
         let mut char_iter = src.chars().peekable();
         while let Some(c) = char_iter.next() {
+            // accum holds the tag name/text/attribute currently being read
+            if accum.len() > limits.max_string_len {
+                return Err(XMLErrorKind::LimitExceeded(Limit::StringLength));
+            }
+            if self.tokens.len() > limits.max_tokens {
+                return Err(XMLErrorKind::LimitExceeded(Limit::Tokens));
+            }
             match state {
                 XmlTokenKind::AuxUnknown => {
                     if c == '<' {
-                        state = XmlTokenKind::OpenTag;
+                        state = markup_state(&mut char_iter);
                     }
                 }
                 XmlTokenKind::OpenTag => {
                     if c == '>' {
                         state = XmlTokenKind::ContentTag;
-                        self.push_token(XmlTokenKind::OpenTag, &mut accum);
+                        self.push_token(XmlTokenKind::OpenTag, &mut accum)?;
                     } else if let ('/', Some('>')) = (c, char_iter.peek()) {
                         state = XmlTokenKind::ContentTag;
                         char_iter.next();
-                        self.push_token(XmlTokenKind::EmptyTag, &mut accum);
+                        self.push_token(XmlTokenKind::EmptyTag, &mut accum)?;
                     } else if let (' ', Some(lookahead)) = (c, char_iter.peek()) {
                         if lookahead.is_alphabetic() {
                             state = XmlTokenKind::AuxOpenAttribOpen;
                             //label token as "open" by default
-                            self.push_token(XmlTokenKind::OpenTag, &mut accum);
+                            self.push_token(XmlTokenKind::OpenTag, &mut accum)?;
                         }
                     } else {
                         let adding_first_character = accum.len() == 0;
@@ -159,10 +256,12 @@ impl XmlLexer {
                 }
                 XmlTokenKind::AuxOpenAttribClose => {
                     if c == '\"' {
+                        let value = self.expand_references(&accum)?;
                         let open_token = &mut self.tokens.last_mut().unwrap().as_mut().unwrap();
-                        open_token
-                            .attribs
-                            .push((current_key.clone(), accum.clone()));
+                        if open_token.attribs.len() >= limits.max_attributes {
+                            return Err(XMLErrorKind::LimitExceeded(Limit::Attributes));
+                        }
+                        open_token.attribs.push((current_key.clone(), value));
                         accum.clear();
 
                         state = XmlTokenKind::AuxOpenAttribOpen;
@@ -173,52 +272,178 @@ impl XmlLexer {
                 XmlTokenKind::CloseTag => {
                     if c == '>' {
                         state = XmlTokenKind::ContentTag;
-                        self.push_token(XmlTokenKind::CloseTag, &mut accum);
+                        self.push_token(XmlTokenKind::CloseTag, &mut accum)?;
                     } else {
                         accum.push(c);
                     }
                 }
                 XmlTokenKind::ContentTag => {
                     if c == '<' {
-                        let peek = char_iter.peek();
-                        if let Some('/') = peek {
-                            char_iter.next();
-                            state = XmlTokenKind::CloseTag;
-                        } else if let Some('!') = peek {
-                            char_iter.next();
-                            state = XmlTokenKind::AuxComment;
-                        } else {
-                            state = XmlTokenKind::OpenTag;
-                        }
-                        self.push_token(XmlTokenKind::ContentTag, &mut accum);
+                        state = markup_state(&mut char_iter);
+                        self.push_token(XmlTokenKind::ContentTag, &mut accum)?;
+                    } else {
+                        accum.push(c);
+                    }
+                }
+                XmlTokenKind::CData => {
+                    if c == ']' && char_iter.clone().take(2).eq("]>".chars()) {
+                        char_iter.nth(1);
+                        state = XmlTokenKind::ContentTag;
+                        let token = XmlToken::new(XmlTokenKind::CData, accum.clone());
+                        self.tokens.push(Some(token));
+                        accum.clear();
+                    } else {
+                        accum.push(c);
+                    }
+                }
+                XmlTokenKind::ProcessingInstruction => {
+                    if let ('?', Some('>')) = (c, char_iter.peek()) {
+                        char_iter.next();
+                        state = XmlTokenKind::ContentTag;
+                        self.push_processing_instruction(&mut accum)?;
                     } else {
                         accum.push(c);
                     }
                 }
                 XmlTokenKind::AuxComment => {
-                    if c == '-' {
-                        let peek = char_iter.peek();
-                        if let Some('>') = peek {
+                    if let ('-', Some('-')) = (c, char_iter.peek()) {
+                        char_iter.next();
+                        if let Some('>') = char_iter.next() {
                             state = XmlTokenKind::ContentTag;
-                            char_iter.next();
+                            let token = XmlToken::new(XmlTokenKind::Comment, accum.clone());
+                            self.tokens.push(Some(token));
+                            accum.clear();
+                        } else {
+                            return Err(XMLErrorKind::TokenizerErr(
+                                "'--' is not allowed inside a comment",
+                            ));
                         }
+                    } else {
+                        accum.push(c);
                     }
                 }
+                XmlTokenKind::AuxMarkupDecl => {
+                    let raw = read_markup_decl(c, &mut char_iter, limits)?;
+                    state = XmlTokenKind::ContentTag;
+                    self.push_doctype(raw)?;
+                }
                 _ => (),
             }
         }
+        if accum.len() > limits.max_string_len {
+            return Err(XMLErrorKind::LimitExceeded(Limit::StringLength));
+        }
+        if self.tokens.len() > limits.max_tokens {
+            return Err(XMLErrorKind::LimitExceeded(Limit::Tokens));
+        }
+        match state {
+            XmlTokenKind::CData => {
+                Err(XMLErrorKind::TokenizerErr("CDATA section is missing its ']]>'"))
+            }
+            XmlTokenKind::ProcessingInstruction => Err(XMLErrorKind::TokenizerErr(
+                "processing instruction is missing its '?>'",
+            )),
+            XmlTokenKind::AuxComment => {
+                Err(XMLErrorKind::TokenizerErr("comment is missing its '-->'"))
+            }
+            XmlTokenKind::AuxMarkupDecl => Err(XMLErrorKind::TokenizerErr(
+                "markup declaration is missing its '>'",
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// # Description
+    /// Turns the text between `<?` and `?>` into a `Declaration` or `ProcessingInstruction` token
+    /// # Comments
+    /// - The target `xml` is the declaration, any other capitalization of it is reserved by the spec
+    fn push_processing_instruction(&mut self, accum: &mut String) -> Result<(), XMLErrorKind> {
+        let (target, data) = match accum.find(char::is_whitespace) {
+            Some(k) => (&accum[..k], accum[k..].trim_start()),
+            None => (accum.as_str(), ""),
+        };
+        let token = if target == "xml" {
+            let mut token = XmlToken::new(XmlTokenKind::Declaration, target.to_string());
+            token.attribs = parse_pseudo_attributes(data)?;
+            if token.version().is_none() {
+                return Err(XMLErrorKind::TokenizerErr(
+                    "the xml declaration must have a version",
+                ));
+            }
+            token
+        } else if target.is_empty() || target.eq_ignore_ascii_case("xml") {
+            return Err(XMLErrorKind::TokenizerErr(
+                "processing instruction has a missing or reserved target",
+            ));
+        } else {
+            let mut token = XmlToken::new(XmlTokenKind::ProcessingInstruction, target.to_string());
+            token.data = data.to_string();
+            token
+        };
+        self.tokens.push(Some(token));
+        accum.clear();
+        Ok(())
+    }
+
+    /// # Description
+    /// Parses the text between `<!` and `>` as a doctype and pushes a `Doctype` token for it
+    /// # Comments
+    /// - `<!DOCTYPE` is the only markup declaration allowed outside of the internal subset
+    fn push_doctype(&mut self, raw: String) -> Result<(), XMLErrorKind> {
+        let starts_doctype = raw.starts_with("DOCTYPE")
+            && raw["DOCTYPE".len()..].starts_with(char::is_whitespace);
+        if starts_doctype == false {
+            return Err(XMLErrorKind::TokenizerErr("unknown markup declaration"));
+        }
+        if self.doctype.is_some() {
+            return Err(XMLErrorKind::TokenizerErr("a document can only have one DOCTYPE"));
+        }
+        let content = raw["DOCTYPE".len()..].to_string();
+        self.doctype = Some(parse_doctype(&content)?);
+        self.tokens.push(Some(XmlToken::new(XmlTokenKind::Doctype, content)));
         Ok(())
     }
 
-    fn push_token(&mut self, token_kind: XmlTokenKind, accum: &mut String) {
+    /// # Description
+    /// Like `decode_references(..)` but also expands the general entities declared in the doctype
+    /// # Comments
+    /// - The replacement text of an entity is treated as plain character data, any markup in it stays
+    /// text instead of becoming elements
+    /// - Fails with `LimitExceeded` once entities nest deeper than `max_entity_depth` or the document
+    /// as a whole has expanded more than `max_entity_expansion` bytes of replacement text
+    fn expand_references(&mut self, raw: &str) -> Result<String, XMLErrorKind> {
+        if raw.contains('&') == false {
+            return Ok(raw.to_string());
+        }
+        let mut expanded = String::with_capacity(raw.len());
+        let mut entities = EntityExpander {
+            doctype: self.doctype.as_ref(),
+            limits: &self.limits,
+            open: Vec::new(),
+            entity_bytes: &mut self.entity_bytes,
+        };
+        entities.expand(raw, &mut expanded)?;
+        Ok(expanded)
+    }
+
+    fn push_token(
+        &mut self,
+        token_kind: XmlTokenKind,
+        accum: &mut String,
+    ) -> Result<(), XMLErrorKind> {
         if accum.len() == 0 || accum.trim().len() == 0 {
             accum.clear();
-            return;
+            return Ok(());
         }
 
-        let token = XmlToken::new(token_kind, accum.clone());
-        self.tokens.push(Some(token));
+        let content = if let XmlTokenKind::ContentTag = token_kind {
+            self.expand_references(accum)?
+        } else {
+            accum.clone()
+        };
+        self.tokens.push(Some(XmlToken::new(token_kind, content)));
         accum.clear();
+        Ok(())
     }
     #[allow(dead_code)]
     pub fn print_tokens(&self) {
@@ -238,6 +463,35 @@ impl XmlLexer {
                 }) => {
                     println!("kind=Inner Content=\'{}\'", txt.trim());
                 }
+                Some(XmlToken {
+                    token_kind: XmlTokenKind::ProcessingInstruction,
+                    content: txt,
+                    data,
+                    ..
+                }) => {
+                    println!("kind=PI Content=\'{}\' Data=\'{}\'", txt, data);
+                }
+                Some(XmlToken {
+                    token_kind: XmlTokenKind::Comment,
+                    content: txt,
+                    ..
+                }) => {
+                    println!("kind=Comment Content=\'{}\'", txt);
+                }
+                Some(XmlToken {
+                    token_kind: XmlTokenKind::Doctype,
+                    content: txt,
+                    ..
+                }) => {
+                    println!("kind=Doctype Content=\'{}\'", txt.trim());
+                }
+                Some(XmlToken {
+                    token_kind: XmlTokenKind::CData,
+                    content: txt,
+                    ..
+                }) => {
+                    println!("kind=CData Content=\'{}\'", txt);
+                }
                 Some(XmlToken {
                     token_kind: XmlTokenKind::CloseTag,
                     content: txt,
@@ -257,3 +511,289 @@ impl XmlLexer {
         }
     }
 }
+
+/// # Description
+/// Picks the lexer state for the markup that starts right after a `<`, consuming its lead-in (`/`, `?`, `!`..)
+fn markup_state(char_iter: &mut Peekable<Chars>) -> XmlTokenKind {
+    let peek = char_iter.peek().copied();
+    if let Some('/') = peek {
+        char_iter.next();
+        XmlTokenKind::CloseTag
+    } else if let Some('?') = peek {
+        char_iter.next();
+        XmlTokenKind::ProcessingInstruction
+    } else if char_iter.clone().take(8).eq("![CDATA[".chars()) {
+        char_iter.nth(7);
+        XmlTokenKind::CData
+    } else if char_iter.clone().take(3).eq("!--".chars()) {
+        char_iter.nth(2);
+        XmlTokenKind::AuxComment
+    } else if let Some('!') = peek {
+        char_iter.next();
+        XmlTokenKind::AuxMarkupDecl
+    } else {
+        XmlTokenKind::OpenTag
+    }
+}
+
+/// # Description
+/// Reads a markup declaration up to (and not including) its closing `>`, `first` is the character after `<!`
+/// # Comments
+/// - A `<!DOCTYPE ..>` can hold a `[..]` subset full of `>`s, so quoted literals, comments and
+/// processing instructions are stepped over and only a `>` outside of the subset ends the declaration
+fn read_markup_decl(
+    first: char,
+    char_iter: &mut Peekable<Chars>,
+    limits: &Limits,
+) -> Result<String, XMLErrorKind> {
+    let unterminated = || XMLErrorKind::TokenizerErr("markup declaration is missing its '>'");
+    let mut raw = String::new();
+    let mut quote: Option<char> = None;
+    let mut in_subset = false;
+    // set while inside a comment or processing instruction of the subset, to what ends it
+    let mut skip_until: Option<&str> = None;
+    let mut c = first;
+    loop {
+        raw.push(c);
+        if raw.len() > limits.max_string_len {
+            return Err(XMLErrorKind::LimitExceeded(Limit::StringLength));
+        }
+        match (skip_until, quote) {
+            (Some(end), _) => {
+                if raw.ends_with(end) {
+                    skip_until = None;
+                }
+            }
+            (None, Some(q)) => {
+                if c == q {
+                    quote = None;
+                }
+            }
+            (None, None) => match c {
+                '"' | '\'' => quote = Some(c),
+                '[' => in_subset = true,
+                ']' => in_subset = false,
+                '>' if in_subset == false => {
+                    raw.pop();
+                    return Ok(raw);
+                }
+                '<' if in_subset && char_iter.clone().take(3).eq("!--".chars()) => {
+                    raw.push_str("!--");
+                    char_iter.nth(2);
+                    skip_until = Some("-->");
+                }
+                '<' if in_subset && char_iter.peek() == Some(&'?') => {
+                    raw.push('?');
+                    char_iter.next();
+                    skip_until = Some("?>");
+                }
+                _ => (),
+            },
+        }
+        c = char_iter.next().ok_or_else(unterminated)?;
+    }
+}
+
+/// # Description
+/// Reads the `name="value"` pairs of an xml declaration
+/// # Comments
+/// - Only `version`, `encoding` and `standalone` are allowed, each at most once. Both quote styles work
+fn parse_pseudo_attributes(data: &str) -> Result<Vec<(String, String)>, XMLErrorKind> {
+    let malformed = || XMLErrorKind::TokenizerErr("malformed xml declaration");
+    let mut attribs: Vec<(String, String)> = Vec::new();
+    let mut rest = data.trim();
+    while rest.is_empty() == false {
+        let equals = rest.find('=').ok_or_else(malformed)?;
+        let name = rest[..equals].trim_end();
+        let value = rest[equals + 1..].trim_start();
+        let quote = value
+            .chars()
+            .next()
+            .filter(|&q| q == '"' || q == '\'')
+            .ok_or_else(malformed)?;
+        let close = value[1..].find(quote).ok_or_else(malformed)? + 1;
+        // the spec fixes both the names and their order
+        let order = ["version", "encoding", "standalone"];
+        let position = order.iter().position(|&known| known == name);
+        let last = attribs
+            .last()
+            .and_then(|(k, _)| order.iter().position(|known| known == k));
+        match (position, last) {
+            (None, _) => {
+                return Err(XMLErrorKind::TokenizerErr(
+                    "unknown attribute in the xml declaration",
+                ))
+            }
+            (Some(position), Some(last)) if position <= last => {
+                return Err(XMLErrorKind::TokenizerErr(
+                    "repeated or out of order attribute in the xml declaration",
+                ))
+            }
+            _ => (),
+        }
+        let val = &value[1..close];
+        if valid_pseudo_attribute(name, val) == false {
+            return Err(XMLErrorKind::TokenizerErr(
+                "invalid version, encoding or standalone in the xml declaration",
+            ));
+        }
+        attribs.push((name.to_string(), val.to_string()));
+        rest = value[close + 1..].trim_start();
+    }
+    Ok(attribs)
+}
+
+/// # Description
+/// Whether `val` is allowed for the declaration's `name` pseudo-attribute
+/// # Comments
+/// - `version` is a `VersionNum` (`1.` and digits), `encoding` an `EncName` (a letter followed by letters,
+/// digits, `.`, `_` and `-`) and `standalone` is `yes` or `no`. None of them can hold a quote, so the
+/// values can be written back out as is
+fn valid_pseudo_attribute(name: &str, val: &str) -> bool {
+    match name {
+        "version" => {
+            let digits = val.strip_prefix("1.").unwrap_or("");
+            digits.is_empty() == false && digits.bytes().all(|b| b.is_ascii_digit())
+        }
+        "encoding" => {
+            let mut chars = val.chars();
+            matches!(chars.next(), Some(c) if c.is_ascii_alphabetic())
+                && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+        }
+        "standalone" => val == "yes" || val == "no",
+        _ => false,
+    }
+}
+
+/// # Description
+/// Decodes the character references in a text run or attribute value, `&lt;` becomes `<`, `&#x41;` becomes `A` and so on
+/// # Comments
+/// - Only the five predefined entities (`&amp;`, `&lt;`, `&gt;`, `&quot;`, `&apos;`) and numeric references
+/// are understood, anything else is an error like the spec asks. The lexer also expands the entities
+/// declared in a document's `<!DOCTYPE ..>`
+/// - Numeric references to characters xml doesn't allow (like `&#0;` or a lone surrogate) are errors too
+pub fn decode_references(raw: &str) -> Result<String, XMLErrorKind> {
+    if raw.contains('&') == false {
+        return Ok(raw.to_string());
+    }
+    let mut decoded = String::with_capacity(raw.len());
+    let mut entity_bytes = 0;
+    let mut entities = EntityExpander {
+        doctype: None,
+        limits: &Limits::none(),
+        open: Vec::new(),
+        entity_bytes: &mut entity_bytes,
+    };
+    entities.expand(raw, &mut decoded)?;
+    Ok(decoded)
+}
+
+/// Expands references recursively, `open` holds the entities currently being expanded
+struct EntityExpander<'a> {
+    doctype: Option<&'a Doctype>,
+    limits: &'a Limits,
+    open: Vec<String>,
+    entity_bytes: &'a mut usize,
+}
+
+impl<'a> EntityExpander<'a> {
+    fn expand(&mut self, raw: &str, out: &mut String) -> Result<(), XMLErrorKind> {
+        let mut rest = raw;
+        while let Some(amp) = rest.find('&') {
+            out.push_str(&rest[..amp]);
+            let semicolon = rest[amp..]
+                .find(';')
+                .ok_or(XMLErrorKind::TokenizerErr("'&' must start a reference ending in ';'"))?;
+            let name = &rest[amp + 1..amp + semicolon];
+            match decode_reference(name)? {
+                Some(c) => out.push(c),
+                None => self.expand_entity(name, out)?,
+            }
+            rest = &rest[amp + semicolon + 1..];
+        }
+        out.push_str(rest);
+        Ok(())
+    }
+
+    fn expand_entity(&mut self, name: &str, out: &mut String) -> Result<(), XMLErrorKind> {
+        let doctype = self.doctype;
+        let replacement = match doctype.and_then(|doctype| doctype.entity(name)) {
+            Some(EntityValue::Internal(text)) => text,
+            Some(EntityValue::External { .. }) => {
+                return Err(XMLErrorKind::TokenizerErr(
+                    "external entity references aren't supported",
+                ))
+            }
+            None => return Err(XMLErrorKind::TokenizerErr("undefined entity reference")),
+        };
+        if self.open.iter().any(|open| open == name) {
+            return Err(XMLErrorKind::TokenizerErr("entity references itself"));
+        }
+        if self.open.len() >= self.limits.max_entity_depth {
+            return Err(XMLErrorKind::LimitExceeded(Limit::EntityDepth));
+        }
+        *self.entity_bytes = self.entity_bytes.saturating_add(replacement.len());
+        if *self.entity_bytes > self.limits.max_entity_expansion {
+            return Err(XMLErrorKind::LimitExceeded(Limit::EntityExpansion));
+        }
+        self.open.push(name.to_string());
+        self.expand(replacement, out)?;
+        self.open.pop();
+        Ok(())
+    }
+}
+
+/// `name` is what sits between `&` and `;`, `None` means it names an entity instead of a character
+fn decode_reference(name: &str) -> Result<Option<char>, XMLErrorKind> {
+    let code_point = match name {
+        "amp" => return Ok(Some('&')),
+        "lt" => return Ok(Some('<')),
+        "gt" => return Ok(Some('>')),
+        "quot" => return Ok(Some('"')),
+        "apos" => return Ok(Some('\'')),
+        _ if name.starts_with("#x") => u32::from_str_radix(&name[2..], 16).ok(),
+        _ if name.starts_with('#') => name[1..].parse::<u32>().ok(),
+        _ => return Ok(None),
+    };
+    code_point
+        .filter(|&c| is_xml_char(c))
+        .and_then(char::from_u32)
+        .map(Some)
+        .ok_or(XMLErrorKind::TokenizerErr("invalid character reference"))
+}
+
+/// the `Char` production of the xml spec
+fn is_xml_char(c: u32) -> bool {
+    matches!(c, 0x9 | 0xA | 0xD | 0x20..=0xD7FF | 0xE000..=0xFFFD | 0x10000..=0x10FFFF)
+}
+
+/// Escapes `raw` so it can be written as element text, `&` `<` and `>` become references
+pub fn escape_text(raw: &str, out: &mut String) {
+    for c in raw.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// # Description
+/// Escapes `raw` so it can be written between the double quotes of an attribute value
+/// # Comments
+/// - Tabs and newlines are written as numeric references too, otherwise a parser that follows the spec
+/// would normalize them into spaces
+pub fn escape_attribute(raw: &str, out: &mut String) {
+    for c in raw.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '"' => out.push_str("&quot;"),
+            '\t' => out.push_str("&#9;"),
+            '\n' => out.push_str("&#10;"),
+            '\r' => out.push_str("&#13;"),
+            c => out.push(c),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! RFC 8785 (JSON Canonicalization Scheme) test vectors.
+//! - `DOCUMENTS` are the examples from sections 3.2.2 and 3.2.3 of the RFC plus the `arrays` and
+//! `structures` cases of the reference implementation's test data
+//! - `NUMBERS` is the IEEE 754 table from appendix B, given as raw f64 bits
+use parser_util::json::{canonical::CanonicalError, number::JsonNumber, value::JsonValue};
+
+const DOCUMENTS: &[(&str, &str, &str)] = &[
+    (
+        "arrays",
+        "[\n  56,\n  {\n    \"d\": true,\n    \"10\": null,\n    \"1\": [ ]\n  }\n]",
+        "[56,{\"1\":[],\"10\":null,\"d\":true}]",
+    ),
+    (
+        "values",
+        "{\n  \"numbers\": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],\n  \
+         \"string\": \"\\u20ac$\\u000F\\u000aA'\\u0042\\u0022\\u005c\\\\\\\"\\/\",\n  \
+         \"literals\": [null, true, false]\n}",
+        "{\"literals\":[null,true,false],\"numbers\":[333333333.3333333,1e+30,4.5,0.002,1e-27],\
+         \"string\":\"\u{20ac}$\\u000f\\nA'B\\\"\\\\\\\\\\\"/\"}",
+    ),
+    (
+        "structures",
+        "{\n  \"1\": {\"f\": {\"f\": \"hi\",\"F\": 5} ,\"\\n\": 56.0},\n  \"10\": { },\n  \
+         \"\": \"empty\",\n  \"a\": { },\n  \"111\": [ {\"e\": \"yes\",\"E\": \"no\" } ],\n  \"A\": { }\n}",
+        "{\"\":\"empty\",\"1\":{\"\\n\":56,\"f\":{\"F\":5,\"f\":\"hi\"}},\"10\":{},\
+         \"111\":[{\"E\":\"no\",\"e\":\"yes\"}],\"A\":{},\"a\":{}}",
+    ),
+    (
+        "sorting",
+        "{\n  \"\\u20ac\": \"Euro Sign\",\n  \"\\r\": \"Carriage Return\",\n  \
+         \"\\ufb33\": \"Hebrew Letter Dalet With Dagesh\",\n  \"1\": \"One\",\n  \
+         \"\\ud83d\\ude00\": \"Emoji: Grinning Face\",\n  \"\\u0080\": \"Control\",\n  \
+         \"\\u00f6\": \"Latin Small Letter O With Diaeresis\"\n}",
+        "{\"\\r\":\"Carriage Return\",\"1\":\"One\",\"\u{80}\":\"Control\",\
+         \"\u{f6}\":\"Latin Small Letter O With Diaeresis\",\"\u{20ac}\":\"Euro Sign\",\
+         \"\u{1f600}\":\"Emoji: Grinning Face\",\"\u{fb33}\":\"Hebrew Letter Dalet With Dagesh\"}",
+    ),
+];
+
+const NUMBERS: &[(u64, &str)] = &[
+    (0x0000000000000000, "0"),
+    (0x8000000000000000, "0"),
+    (0x0000000000000001, "5e-324"),
+    (0x8000000000000001, "-5e-324"),
+    (0x7fefffffffffffff, "1.7976931348623157e+308"),
+    (0xffefffffffffffff, "-1.7976931348623157e+308"),
+    (0x4340000000000000, "9007199254740992"),
+    (0xc340000000000000, "-9007199254740992"),
+    (0x4430000000000000, "295147905179352830000"),
+    (0x44b52d02c7e14af5, "9.999999999999997e+22"),
+    (0x44b52d02c7e14af6, "1e+23"),
+    (0x44b52d02c7e14af7, "1.0000000000000001e+23"),
+    (0x444b1ae4d6e2ef4e, "999999999999999700000"),
+    (0x444b1ae4d6e2ef4f, "999999999999999900000"),
+    (0x444b1ae4d6e2ef50, "1e+21"),
+    (0x3eb0c6f7a0b5ed8c, "9.999999999999997e-7"),
+    (0x3eb0c6f7a0b5ed8d, "0.000001"),
+    (0x41b3de4355555553, "333333333.3333332"),
+    (0x41b3de4355555554, "333333333.33333325"),
+    (0x41b3de4355555555, "333333333.3333333"),
+    (0x41b3de4355555556, "333333333.3333334"),
+    (0x41b3de4355555557, "333333333.33333343"),
+    (0xbecbf647612f3696, "-0.0000033333333333333333"),
+    (0x43143ff3c1cb0959, "1424953923781206.2"),
+];
+
+#[test]
+fn canonicalizes_documents() {
+    for (name, input, expected) in DOCUMENTS.iter() {
+        let value = JsonValue::parse(input).unwrap_or_else(|err| panic!("{}: {}", name, err));
+        assert_eq!(value.to_canonical_json().unwrap(), *expected, "{}", name);
+    }
+}
+
+#[test]
+fn canonical_form_is_a_fixed_point() {
+    for (name, _, expected) in DOCUMENTS.iter() {
+        let value = JsonValue::parse(expected).unwrap();
+        assert_eq!(value.to_canonical_json().unwrap(), *expected, "{}", name);
+    }
+}
+
+#[test]
+fn formats_numbers() {
+    for &(bits, expected) in NUMBERS.iter() {
+        let number = JsonNumber::from_f64(f64::from_bits(bits)).unwrap();
+        let text = number.to_ecmascript_string().unwrap();
+        assert_eq!(text, expected, "{:016x}", bits);
+        // the text has to read back as exactly the same double, -0 aside
+        if bits != 0x8000000000000000 {
+            assert_eq!(text.parse::<f64>().unwrap().to_bits(), bits, "{:016x}", bits);
+        }
+    }
+}
+
+#[test]
+fn rejects_what_jcs_cant_represent() {
+    let value = JsonValue::parse("[1e400]").unwrap();
+    match value.to_canonical_json() {
+        Err(CanonicalError::NumberOutOfRange { lexeme }) => assert_eq!(lexeme, "1e400"),
+        other => panic!("expected NumberOutOfRange, got {:?}", other),
+    }
+    let value = JsonValue::parse("{\"a\": 1, \"b\": 2, \"a\": 3}").unwrap();
+    match value.to_canonical_json() {
+        Err(CanonicalError::DuplicateKey { key }) => assert_eq!(key, "a"),
+        other => panic!("expected DuplicateKey, got {:?}", other),
+    }
+}
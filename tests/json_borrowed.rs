@@ -0,0 +1,140 @@
+//! `BorrowedValue` only allocates for text that had to be decoded, and agrees with `JsonValue` on everything
+use std::borrow::Cow;
+
+use parser_util::json::{
+    ast::{JsonAst, ParseOptions},
+    borrowed::BorrowedValue,
+    value::JsonValue,
+};
+
+const DOCUMENT: &str = r#"{
+    "plain": "no escapes here",
+    "escaped": "tab\there é 😀",
+    "key \"quoted\"": [1, -2.50, 1E400, true, null, "", "\\"],
+    "nested": {"deep": [{"x": "y"}]},
+    "dup": 1,
+    "dup": 2
+}"#;
+
+/// Whether `val` points into `text`, which a decoded (`Cow::Owned`) string never does
+fn borrows_from(val: &str, text: &str) -> bool {
+    let range = text.as_ptr() as usize..text.as_ptr() as usize + text.len();
+    range.contains(&(val.as_ptr() as usize))
+}
+
+#[test]
+fn strings_without_escapes_are_borrowed() {
+    let value = BorrowedValue::parse(DOCUMENT).unwrap();
+    let props = value.as_object().unwrap();
+
+    let (key, plain) = &props[0];
+    assert!(borrows_from(key, DOCUMENT));
+    match plain {
+        BorrowedValue::String(val) => {
+            assert!(borrows_from(val, DOCUMENT));
+            assert_eq!(val, "no escapes here");
+        }
+        other => panic!("{:?}", other),
+    }
+
+    let (key, escaped) = &props[1];
+    assert!(borrows_from(key, DOCUMENT));
+    match escaped {
+        BorrowedValue::String(Cow::Owned(val)) => assert_eq!(val, "tab\there é 😀"),
+        other => panic!("{:?}", other),
+    }
+
+    // an escaped key is decoded too, its values are judged on their own
+    let (key, list) = &props[2];
+    assert_eq!(key, &Cow::<str>::Owned(String::from("key \"quoted\"")));
+    let items = list.as_array().unwrap();
+    match (&items[5], &items[6]) {
+        (BorrowedValue::String(empty), BorrowedValue::String(backslash)) => {
+            assert!(borrows_from(empty, DOCUMENT));
+            assert!(matches!(backslash, Cow::Owned(val) if val == "\\"));
+        }
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn numbers_are_borrowed_unless_normalized() {
+    let value = BorrowedValue::parse(DOCUMENT).unwrap();
+    let items = value.get("key \"quoted\"").unwrap().as_array().unwrap();
+    let lexemes: Vec<&str> = items[..3]
+        .iter()
+        .map(|item| item.as_number_str().unwrap())
+        .collect();
+    assert_eq!(lexemes, vec!["1", "-2.50", "1E400"]);
+    for item in items[..3].iter() {
+        match item {
+            BorrowedValue::Number(lexeme) => assert!(borrows_from(lexeme, DOCUMENT)),
+            other => panic!("{:?}", other),
+        }
+    }
+    assert_eq!(items[1].as_f64(), Some(-2.5));
+    assert_eq!(items[2].as_f64(), None);
+
+    let text = "[0x10, 5, .5]";
+    let value = BorrowedValue::parse_with(text, &ParseOptions::json5()).unwrap();
+    let items = value.as_array().unwrap();
+    assert!(matches!(&items[0], BorrowedValue::Number(Cow::Owned(lexeme)) if lexeme == "16"));
+    assert!(matches!(&items[1], BorrowedValue::Number(lexeme) if borrows_from(lexeme, text)));
+    assert!(matches!(&items[2], BorrowedValue::Number(Cow::Owned(lexeme)) if lexeme == "0.5"));
+}
+
+#[test]
+fn into_owned_matches_json_value() {
+    let json5 = "{a: 'single \\' quoted', b: [0xFF, +1, 5.], \\u0063: \"x\", // comment\n}";
+    let cases = [
+        (DOCUMENT, ParseOptions::default()),
+        ("[]", ParseOptions::default()),
+        ("\"top level\"", ParseOptions::default()),
+        ("-0", ParseOptions::default()),
+        ("{\"a\": [1, 2,], /* c */}", ParseOptions::jsonc()),
+        (json5, ParseOptions::json5()),
+    ];
+    for (text, options) in cases.iter() {
+        let borrowed = BorrowedValue::parse_with(text, options).unwrap();
+        let owned = JsonValue::parse_with(text, options).unwrap();
+        assert_eq!(borrowed.clone().into_owned(), owned, "{}", text);
+        assert_eq!(JsonValue::from(borrowed), owned, "{}", text);
+    }
+
+    // the same tree gives the same value either way
+    let mut ast = JsonAst::new();
+    ast.parse(DOCUMENT).unwrap();
+    assert_eq!(
+        BorrowedValue::from_ast(&ast, DOCUMENT).into_owned(),
+        JsonValue::from_ast(&ast, DOCUMENT)
+    );
+}
+
+#[test]
+fn accessors() {
+    let value = BorrowedValue::parse(DOCUMENT).unwrap();
+    assert_eq!(
+        value.get("plain").unwrap().as_str(),
+        Some("no escapes here")
+    );
+    assert_eq!(value.get("dup").unwrap().as_i64(), Some(1));
+    assert_eq!(value.get("dup").unwrap().as_u64(), Some(1));
+    assert!(value.get("missing").is_none());
+    assert!(value.get("plain").unwrap().get("x").is_none());
+
+    let items = value.get("key \"quoted\"").unwrap().as_array().unwrap();
+    assert_eq!(items[0].as_i64(), Some(1));
+    assert_eq!(items[1].as_i64(), None);
+    assert_eq!(items[1].as_u64(), None);
+    assert_eq!(items[3].as_bool(), Some(true));
+    assert!(items[4].is_null());
+    assert!(items[3].as_str().is_none());
+    assert!(items[0].as_array().is_none());
+    assert!(items[0].as_object().is_none());
+
+    let deep = value.get("nested").unwrap().get("deep").unwrap();
+    assert_eq!(
+        deep.as_array().unwrap()[0].get("x").unwrap().as_str(),
+        Some("y")
+    );
+}
@@ -0,0 +1,108 @@
+//! Errors point at the offending token with a line, a column (counted in chars) and what was expected
+use parser_util::json::{
+    ast::{JsonAst, ParseError},
+    lexer::{JsonLexer, LexerError, Position},
+};
+
+fn parse_err(text: &str) -> ParseError {
+    let mut ast = JsonAst::new();
+    ast.parse(text).unwrap_err()
+}
+
+/// (line, column, expected, found)
+fn summary(err: &ParseError) -> (usize, usize, &str, &str) {
+    let diag = err.diagnostic();
+    (
+        diag.pos.line,
+        diag.pos.column,
+        diag.expected.as_str(),
+        diag.found.as_str(),
+    )
+}
+
+#[test]
+fn parser_errors_have_positions() {
+    let err = parse_err("{\n  \"a\" \"b\"\n}");
+    assert!(matches!(err, ParseError::InvalidToken(_)));
+    assert_eq!(summary(&err), (2, 7, "`:`", "string \"b\""));
+
+    let err = parse_err("[1 2]");
+    assert_eq!(summary(&err), (1, 4, "`,` or `]`", "number `2`"));
+
+    let err = parse_err("[1,\n\t2,]");
+    assert_eq!(summary(&err), (2, 4, "a value", "`]`"));
+}
+
+#[test]
+fn bracket_errors() {
+    let err = parse_err("{\"a\":1]");
+    assert!(matches!(err, ParseError::BracketMismatch(_)));
+    assert_eq!(summary(&err), (1, 7, "`,` or `}`", "`]`"));
+
+    let err = parse_err("{\"a\":1");
+    assert!(matches!(err, ParseError::MissingCloseBracket(_)));
+    assert_eq!(summary(&err), (1, 7, "`,` or `}`", "end of input"));
+
+    let err = parse_err("");
+    assert!(matches!(err, ParseError::InvalidToken(_)));
+    assert_eq!(summary(&err), (1, 1, "a value", "end of input"));
+}
+
+#[test]
+fn lexer_errors_are_wrapped() {
+    let err = parse_err("[\"\\q\"]");
+    assert!(matches!(
+        err,
+        ParseError::LexFailed(LexerError::InvalidString(_))
+    ));
+    assert_eq!(summary(&err), (1, 3, "a valid escape sequence", "`\\q`"));
+
+    let err = parse_err("\"abc");
+    assert!(matches!(
+        err,
+        ParseError::LexFailed(LexerError::InvalidString(_))
+    ));
+    assert_eq!(summary(&err).3, "end of input");
+
+    let err = parse_err("[1] x");
+    assert!(matches!(
+        err,
+        ParseError::LexFailed(LexerError::InvalidIdentifier(_))
+    ));
+    assert_eq!(summary(&err), (1, 5, "a value", "`x`"));
+
+    let mut lexer = JsonLexer::new();
+    let err = lexer.lex("[/* c */ 1]").unwrap_err();
+    assert_eq!(err.diagnostic().pos.column, 2);
+}
+
+#[test]
+fn columns_count_chars_not_bytes() {
+    // `é` is two bytes, `😀` four, but each is one column
+    let err = parse_err("[\"é😀\", tru]");
+    assert_eq!(err.diagnostic().pos.offset, 11);
+    assert_eq!(summary(&err), (1, 8, "a value", "`tru`"));
+
+    let pos = Position::locate("ab\ncé\nd", 7);
+    assert_eq!((pos.line, pos.column), (3, 1));
+    // offsets past the end are clamped
+    assert_eq!(Position::locate("ab", 99).offset, 2);
+}
+
+#[test]
+fn display_and_render() {
+    let text = "{\n  \"a\" \"b\"\n}";
+    let err = parse_err(text);
+    assert_eq!(
+        err.to_string(),
+        "expected `:`, found string \"b\" at line 2, column 7"
+    );
+    let expected = "error: expected `:`, found string \"b\"\n --> line 2, column 7\n  |\n2 |   \"a\" \"b\"\n  |       ^\n";
+    assert_eq!(err.render(text), expected);
+
+    // tabs are kept in the caret line so it lines up with the source
+    let text = "[1,\n\t2,]";
+    assert!(parse_err(text)
+        .render(text)
+        .ends_with("2 | \t2,]\n  | \t  ^\n"));
+}
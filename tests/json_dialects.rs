@@ -0,0 +1,200 @@
+//! JSONC and JSON5, the dialects `ParseOptions` can switch on on top of strict json
+use parser_util::json::{
+    ast::{JsonAst, ParseError, ParseOptions},
+    borrowed::BorrowedValue,
+    value::JsonValue,
+};
+
+fn parse(text: &str, options: &ParseOptions) -> JsonValue {
+    JsonValue::parse_with(text, options).unwrap()
+}
+
+/// The text `JsonAst` writes back out, everything the dialect added is gone
+fn rewrite(text: &str, options: &ParseOptions) -> String {
+    let mut ast = JsonAst::new();
+    ast.parse_with(text, options).unwrap();
+    ast.to_json(text)
+}
+
+#[test]
+fn jsonc() {
+    let text = "// settings\n{\n  \"a\": 1, /* inline */ \"b\": [1, 2,],\n  \"c\": {\"d\": true,}, // trailing\n}\n/* end */";
+    let options = ParseOptions::jsonc();
+    assert_eq!(
+        parse(text, &options),
+        JsonValue::parse(r#"{"a": 1, "b": [1, 2], "c": {"d": true}}"#).unwrap()
+    );
+    assert_eq!(
+        rewrite(text, &options),
+        r#"{"a":1,"b":[1,2],"c":{"d":true}}"#
+    );
+    // a comment is whitespace, not a separator
+    assert!(JsonValue::parse_with("[1 /* , */ 2]", &options).is_err());
+    assert!(JsonValue::parse_with("[1, /* unterminated ]", &options).is_err());
+    // only one trailing comma
+    assert!(JsonValue::parse_with("[1,,]", &options).is_err());
+    assert!(JsonValue::parse_with("{,}", &options).is_err());
+    // JSON5 only syntax
+    for text in ["{a: 1}", "['a']", "0x10", "+1", ".5", "NaN"].iter() {
+        assert!(JsonValue::parse_with(text, &options).is_err(), "{}", text);
+    }
+}
+
+#[test]
+fn strict_rejects_the_extensions() {
+    for text in [
+        "[1, 2,]",
+        "{\"a\": 1,}",
+        "// comment\n1",
+        "[1 /* comment */]",
+        "{a: 1}",
+        "'a'",
+        "0x10",
+        "+1",
+        ".5",
+        "5.",
+        "Infinity",
+        "[\"\\x41\"]",
+    ]
+    .iter()
+    {
+        assert!(JsonValue::parse(text).is_err(), "{}", text);
+        assert!(
+            JsonValue::parse_with(text, &ParseOptions::default()).is_err(),
+            "{}",
+            text
+        );
+    }
+}
+
+#[test]
+fn json5() {
+    // the example from https://spec.json5.org
+    let text = r#"{
+  // comments
+  unquoted: 'and you can quote me on that',
+  singleQuotes: 'I can use "double quotes" here',
+  lineBreaks: "Look, Mom! \
+No \\n's!",
+  hexadecimal: 0xdecaf,
+  leadingDecimalPoint: .8675309, andTrailing: 8675309.,
+  positiveSign: +1,
+  trailingComma: 'in objects', andIn: ['arrays',],
+  "backwardsCompatible": "with JSON",
+}"#;
+    let expected = r#"{"unquoted":"and you can quote me on that","singleQuotes":"I can use \"double quotes\" here","lineBreaks":"Look, Mom! No \\n's!","hexadecimal":912559,"leadingDecimalPoint":0.8675309,"andTrailing":8675309,"positiveSign":1,"trailingComma":"in objects","andIn":["arrays"],"backwardsCompatible":"with JSON"}"#;
+    let options = ParseOptions::json5();
+    assert_eq!(parse(text, &options), JsonValue::parse(expected).unwrap());
+    assert_eq!(rewrite(text, &options), expected);
+}
+
+#[test]
+fn json5_numbers_and_strings() {
+    let options = ParseOptions::json5();
+    let cases = [
+        ("0x10", "16"),
+        ("-0XFF", "-255"),
+        ("0xFFFFFFFFFFFFFFFFFF", "4722366482869645213695"),
+        (".5", "0.5"),
+        ("-.5e1", "-0.5e1"),
+        ("5.", "5"),
+        ("+1.5", "1.5"),
+        // plain json numbers are left alone
+        ("1.50", "1.50"),
+        ("-0", "-0"),
+        ("1E400", "1E400"),
+    ];
+    for (json5, json) in cases.iter() {
+        let value = parse(json5, &options);
+        assert_eq!(value.as_number().unwrap().as_str(), *json, "{}", json5);
+        assert_eq!(rewrite(json5, &options), *json, "{}", json5);
+    }
+    for text in ["0x", "+", ".", "1..2", "0x1.5", "+-1"].iter() {
+        assert!(JsonValue::parse_with(text, &options).is_err(), "{}", text);
+    }
+
+    assert_eq!(
+        parse(r#"['\x41\'', "\v\0", 'a\u00e9']"#, &options),
+        JsonValue::parse(r#"["A'", "\u000b\u0000", "aé"]"#).unwrap()
+    );
+    // JSON5 allows more whitespace, U+00A0 and U+FEFF among others
+    assert_eq!(
+        parse("\u{a0}[1,\u{feff}\u{0b}2]\u{2028}", &options),
+        JsonValue::parse("[1, 2]").unwrap()
+    );
+    // keys can be identifiers, even ones spelling a literal
+    let value = parse("{$a_1: 1, null: 2, Infinity: 3, \\u0062: 4}", &options);
+    let keys: Vec<&str> = value
+        .as_object()
+        .unwrap()
+        .iter()
+        .map(|(key, _)| key.as_str())
+        .collect();
+    assert_eq!(keys, vec!["$a_1", "null", "Infinity", "b"]);
+    // but an identifier that isn't a literal isn't a value
+    assert!(JsonValue::parse_with("[undefined]", &options).is_err());
+    assert!(JsonValue::parse_with("'line\nbreak'", &options).is_err());
+}
+
+#[test]
+fn non_finite_numbers() {
+    let options = ParseOptions::json5();
+    for text in [
+        "Infinity",
+        "-Infinity",
+        "+Infinity",
+        "NaN",
+        "-NaN",
+        "[1, NaN]",
+    ]
+    .iter()
+    {
+        match JsonValue::parse_with(text, &options) {
+            Err(ParseError::InvalidToken(diag)) => {
+                assert_eq!(diag.expected, "a finite number", "{}", text)
+            }
+            other => panic!("{}: {:?}", text, other),
+        }
+        assert!(
+            BorrowedValue::parse_with(text, &options).is_err(),
+            "{}",
+            text
+        );
+    }
+    let err = JsonValue::parse_with("{a: -Infinity}", &options).unwrap_err();
+    assert_eq!(err.diagnostic().pos.column, 5);
+
+    // the error tolerant parser reports them too
+    let mut ast = JsonAst::new();
+    let errors = ast.parse_recovering("[NaN, 1, Infinity]", &options);
+    let offsets: Vec<usize> = errors
+        .iter()
+        .map(|err| err.diagnostic().pos.offset)
+        .collect();
+    assert_eq!(offsets, vec![1, 9]);
+
+    // the lossy mapping has to be asked for
+    let lossy = ParseOptions {
+        non_finite_as_null: true,
+        ..ParseOptions::json5()
+    };
+    let text = "[Infinity, -Infinity, NaN, +NaN, 1]";
+    assert_eq!(
+        parse(text, &lossy),
+        JsonValue::parse("[null, null, null, null, 1]").unwrap()
+    );
+    assert_eq!(rewrite(text, &lossy), "[null,null,null,null,1]");
+    assert!(BorrowedValue::parse_with(text, &lossy)
+        .unwrap()
+        .as_array()
+        .unwrap()[..4]
+        .iter()
+        .all(BorrowedValue::is_null));
+
+    // the flag doesn't make them valid in the other dialects
+    let strict = ParseOptions {
+        non_finite_as_null: true,
+        ..ParseOptions::default()
+    };
+    assert!(JsonValue::parse_with("NaN", &strict).is_err());
+}
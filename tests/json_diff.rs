@@ -0,0 +1,289 @@
+//! `diff_with(..)` turned into a patch has to take the old document to the new one, checked on
+//! randomly generated pairs of documents with both positional and keyed arrays
+use parser_util::json::{
+    diff::{diff, diff_with, to_patch, Change, DiffOptions},
+    number::JsonNumber,
+    value::JsonValue,
+};
+
+fn parse(text: &str) -> JsonValue {
+    JsonValue::parse(text).unwrap()
+}
+
+/// xorshift, so every run sees the same documents
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+}
+
+const KEYS: [&str; 6] = ["a", "b", "c", "d/e", "f~g", ""];
+
+fn scalar(rng: &mut Rng) -> JsonValue {
+    match rng.below(5) {
+        0 => JsonValue::Null,
+        1 => JsonValue::Bool(rng.chance(50)),
+        2 => JsonValue::Number(JsonNumber::from(rng.below(10) as i64 - 3)),
+        _ => JsonValue::String(KEYS[rng.below(KEYS.len())].to_string()),
+    }
+}
+
+fn value(rng: &mut Rng, depth: usize) -> JsonValue {
+    if depth == 0 {
+        return scalar(rng);
+    }
+    match rng.below(4) {
+        0 => scalar(rng),
+        1 => JsonValue::Array((0..rng.below(5)).map(|_| value(rng, depth - 1)).collect()),
+        _ => {
+            let mut props = Vec::new();
+            for key in KEYS.iter() {
+                if rng.chance(50) {
+                    props.push((key.to_string(), value(rng, depth - 1)));
+                }
+            }
+            JsonValue::Object(props)
+        }
+    }
+}
+
+/// A copy of `old` with some of its members and elements removed, added, replaced or reordered
+fn mutate(rng: &mut Rng, old: &JsonValue, depth: usize) -> JsonValue {
+    if rng.chance(10) {
+        return value(rng, depth);
+    }
+    match old {
+        JsonValue::Object(props) => {
+            let mut kept = Vec::new();
+            for (key, val) in props.iter() {
+                if rng.chance(80) {
+                    kept.push((key.clone(), mutate(rng, val, depth.saturating_sub(1))));
+                }
+            }
+            for key in KEYS.iter() {
+                if rng.chance(20) && kept.iter().all(|(other, _)| other != key) {
+                    kept.push((key.to_string(), value(rng, depth.saturating_sub(1))));
+                }
+            }
+            JsonValue::Object(kept)
+        }
+        JsonValue::Array(items) => {
+            let mut kept = Vec::new();
+            for item in items.iter() {
+                if rng.chance(80) {
+                    kept.push(mutate(rng, item, depth.saturating_sub(1)));
+                }
+            }
+            for _ in 0..rng.below(3) {
+                let at = rng.below(kept.len() + 1);
+                kept.insert(at, value(rng, depth.saturating_sub(1)));
+            }
+            if kept.len() > 1 && rng.chance(30) {
+                let (i, j) = (rng.below(kept.len()), rng.below(kept.len()));
+                kept.swap(i, j);
+            }
+            JsonValue::Array(kept)
+        }
+        _ if rng.chance(50) => scalar(rng),
+        _ => old.clone(),
+    }
+}
+
+/// An array of objects told apart by their `id`, with `children` that are keyed the same way
+fn records(rng: &mut Rng, depth: usize, next_id: &mut i64) -> JsonValue {
+    let items = (0..rng.below(6))
+        .map(|_| record(rng, depth, next_id))
+        .collect();
+    JsonValue::Array(items)
+}
+
+fn record(rng: &mut Rng, depth: usize, next_id: &mut i64) -> JsonValue {
+    *next_id += 1;
+    let mut props = vec![
+        (
+            "id".to_string(),
+            JsonValue::Number(JsonNumber::from(*next_id)),
+        ),
+        ("value".to_string(), value(rng, 1)),
+    ];
+    if depth > 0 && rng.chance(50) {
+        props.push(("children".to_string(), records(rng, depth - 1, next_id)));
+    }
+    JsonValue::Object(props)
+}
+
+/// Drops, adds, edits and shuffles records, children included
+fn mutate_records(rng: &mut Rng, old: &JsonValue, next_id: &mut i64) -> JsonValue {
+    let mut items: Vec<JsonValue> = Vec::new();
+    for item in old.as_array().unwrap().iter() {
+        if rng.chance(20) {
+            continue;
+        }
+        let mut props = item.as_object().unwrap().clone();
+        for (key, val) in props.iter_mut() {
+            match key.as_str() {
+                "value" if rng.chance(40) => *val = mutate(rng, val, 1),
+                "children" => *val = mutate_records(rng, val, next_id),
+                _ => (),
+            }
+        }
+        items.push(JsonValue::Object(props));
+    }
+    for _ in 0..rng.below(3) {
+        let at = rng.below(items.len() + 1);
+        items.insert(at, record(rng, 0, next_id));
+    }
+    for i in (1..items.len()).rev() {
+        if rng.chance(40) {
+            let j = rng.below(i + 1);
+            items.swap(i, j);
+        }
+    }
+    JsonValue::Array(items)
+}
+
+/// Applies the patch made from `diff_with(old, new, options)` to `old` and checks that it gives `new`
+fn check_round_trip(old: &JsonValue, new: &JsonValue, options: &DiffOptions) {
+    let changes = diff_with(old, new, options);
+    let mut patched = old.clone();
+    patched
+        .apply_operations(&to_patch(&changes))
+        .unwrap_or_else(|err| panic!("{:?}\n{} -> {}", err, old.to_json(), new.to_json()));
+    // member order isn't part of the diff, so compare canonical forms
+    assert_eq!(
+        patched.to_canonical_json().unwrap(),
+        new.to_canonical_json().unwrap(),
+        "\n{} -> {}\n{:?}",
+        old.to_json(),
+        new.to_json(),
+        changes
+    );
+    if old.to_canonical_json().unwrap() == new.to_canonical_json().unwrap() {
+        assert!(changes.is_empty(), "{:?}", changes);
+    }
+}
+
+#[test]
+fn positional_diffs_round_trip() {
+    let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+    for _ in 0..2000 {
+        let old = value(&mut rng, 4);
+        let new = mutate(&mut rng, &old, 4);
+        check_round_trip(&old, &new, &DiffOptions::default());
+        check_round_trip(&new, &old, &DiffOptions::default());
+    }
+}
+
+#[test]
+fn keyed_diffs_round_trip() {
+    let mut rng = Rng(0xD1B5_4A32_D192_ED03);
+    let options = DiffOptions::array_key("id");
+    let mut moves = 0;
+    for _ in 0..2000 {
+        let mut next_id = 0;
+        let old = records(&mut rng, 2, &mut next_id);
+        let new = mutate_records(&mut rng, &old, &mut next_id);
+        check_round_trip(&old, &new, &options);
+        check_round_trip(&new, &old, &options);
+        // the same pairs have to work when compared by position too
+        check_round_trip(&old, &new, &DiffOptions::default());
+        moves += diff_with(&old, &new, &options)
+            .iter()
+            .filter(|change| matches!(change, Change::Moved { .. }))
+            .count();
+    }
+    assert!(moves > 0);
+
+    // random documents, where only some arrays qualify for matching by key
+    for _ in 0..1000 {
+        let old = value(&mut rng, 4);
+        let new = mutate(&mut rng, &old, 4);
+        check_round_trip(&old, &new, &options);
+    }
+}
+
+#[test]
+fn reordered_keyed_elements_are_moved() {
+    let options = DiffOptions::array_key("id");
+    let old = parse(r#"{"list": [{"id": 1}, {"id": 2}, {"id": 3, "v": "x"}]}"#);
+
+    // rotating the last element to the front is a single move
+    let new = parse(r#"{"list": [{"id": 3, "v": "x"}, {"id": 1}, {"id": 2}]}"#);
+    assert_eq!(
+        diff_with(&old, &new, &options),
+        vec![Change::Moved {
+            from: "/list/2".to_string(),
+            path: "/list/0".to_string(),
+        }]
+    );
+    assert_eq!(
+        diff_with(&old, &new, &options)[0].to_string(),
+        "> \"/list/2\" -> \"/list/0\""
+    );
+    // by position the same reorder changes every element instead
+    assert!(diff(&old, &new).iter().all(|change| matches!(
+        change,
+        Change::Added { .. } | Change::Removed { .. } | Change::Changed { .. }
+    )));
+
+    // a moved element is compared at its new position
+    let new = parse(r#"{"list": [{"id": 2}, {"id": 3, "v": "y"}, {"id": 1}]}"#);
+    assert_eq!(
+        diff_with(&old, &new, &options),
+        vec![
+            Change::Moved {
+                from: "/list/1".to_string(),
+                path: "/list/0".to_string(),
+            },
+            Change::Moved {
+                from: "/list/2".to_string(),
+                path: "/list/1".to_string(),
+            },
+            Change::Changed {
+                path: "/list/1/v".to_string(),
+                old: parse("\"x\""),
+                new: parse("\"y\""),
+            },
+        ]
+    );
+
+    // moves are counted after removals and additions have shifted things around
+    let new = parse(r#"{"list": [{"id": 4}, {"id": 3, "v": "x"}, {"id": 1}]}"#);
+    assert_eq!(
+        diff_with(&old, &new, &options),
+        vec![
+            Change::Removed {
+                path: "/list/1".to_string(),
+                old: parse(r#"{"id": 2}"#),
+            },
+            Change::Added {
+                path: "/list/0".to_string(),
+                value: parse(r#"{"id": 4}"#),
+            },
+            Change::Moved {
+                from: "/list/2".to_string(),
+                path: "/list/1".to_string(),
+            },
+        ]
+    );
+    check_round_trip(&old, &new, &options);
+
+    // elements that only keep their relative order aren't moved
+    let new = parse(r#"{"list": [{"id": 0}, {"id": 1}, {"id": 3, "v": "x"}]}"#);
+    assert!(diff_with(&old, &new, &options)
+        .iter()
+        .all(|change| matches!(change, Change::Moved { .. }) == false));
+}
@@ -0,0 +1,172 @@
+//! What each `DuplicateKeys` policy does with a property name that shows up more than once
+use parser_util::json::{
+    ast::{DuplicateKeys, JsonAst, ParseError, ParseOptions},
+    borrowed::BorrowedValue,
+    value::JsonValue,
+};
+
+const DOCUMENT: &str = "{\"a\": 1, \"b\": 2,\n \"a\": 3, \"c\": {\"a\": 4}, \"a\": 5}";
+
+fn options(duplicate_keys: DuplicateKeys) -> ParseOptions {
+    ParseOptions {
+        duplicate_keys,
+        ..ParseOptions::default()
+    }
+}
+
+fn parse(text: &str, policy: DuplicateKeys) -> JsonValue {
+    JsonValue::parse_with(text, &options(policy)).unwrap()
+}
+
+/// Property names of an object in document order
+fn keys(value: &JsonValue) -> Vec<&str> {
+    value
+        .as_object()
+        .unwrap()
+        .iter()
+        .map(|(key, _)| key.as_str())
+        .collect()
+}
+
+#[test]
+fn keep_all() {
+    let value = parse(DOCUMENT, DuplicateKeys::KeepAll);
+    assert_eq!(value, JsonValue::parse(DOCUMENT).unwrap());
+    assert_eq!(keys(&value), vec!["a", "b", "a", "c", "a"]);
+    let all: Vec<i64> = value.get_all("a").map(|v| v.as_i64().unwrap()).collect();
+    assert_eq!(all, vec![1, 3, 5]);
+    // `get` finds the first one, the nested `a` belongs to another object
+    assert_eq!(value.get("a").unwrap().as_i64(), Some(1));
+    assert_eq!(value.get("c").unwrap().get_all("a").count(), 1);
+
+    // the tree keeps them too
+    let mut ast = JsonAst::new();
+    ast.parse_with(DOCUMENT, &options(DuplicateKeys::KeepAll))
+        .unwrap();
+    assert_eq!(
+        ast.to_json(DOCUMENT),
+        r#"{"a":1,"b":2,"a":3,"c":{"a":4},"a":5}"#
+    );
+}
+
+#[test]
+fn reject() {
+    let err = JsonValue::parse_with(DOCUMENT, &options(DuplicateKeys::Reject)).unwrap_err();
+    match &err {
+        ParseError::DuplicateKey { diag, first } => {
+            // the second `a` is reported, pointing back at the first one
+            assert_eq!((diag.pos.line, diag.pos.column), (2, 2));
+            assert_eq!(diag.pos.offset, 18);
+            assert_eq!((first.line, first.column, first.offset), (1, 2, 1));
+            assert_eq!(diag.expected, "a unique property name");
+            assert_eq!(
+                diag.found,
+                "string \"a\" again (first defined at line 1, column 2)"
+            );
+        }
+        other => panic!("{:?}", other),
+    }
+
+    // the same name in different objects is fine
+    let text = r#"{"a": {"a": 1}, "b": [{"a": 2}, {"a": 3}]}"#;
+    assert_eq!(
+        parse(text, DuplicateKeys::Reject),
+        JsonValue::parse(text).unwrap()
+    );
+    // but a duplicate in a nested object is still caught
+    assert!(JsonValue::parse_with(
+        r#"[{"x": 1}, {"y": {"x": 1, "x": 2}}]"#,
+        &options(DuplicateKeys::Reject)
+    )
+    .is_err());
+
+    // the error tolerant parser reports every duplicate
+    let mut ast = JsonAst::new();
+    let errors = ast.parse_recovering(DOCUMENT, &options(DuplicateKeys::Reject));
+    let offsets: Vec<usize> = errors
+        .iter()
+        .map(|err| err.diagnostic().pos.offset)
+        .collect();
+    assert_eq!(offsets, vec![18, 41]);
+}
+
+#[test]
+fn first_wins() {
+    let value = parse(DOCUMENT, DuplicateKeys::FirstWins);
+    assert_eq!(
+        value,
+        JsonValue::parse(r#"{"a": 1, "b": 2, "c": {"a": 4}}"#).unwrap()
+    );
+
+    // later duplicates still have to be valid
+    let text = r#"{"a": 1, "a": [1, 2}"#;
+    assert!(JsonValue::parse_with(text, &options(DuplicateKeys::FirstWins)).is_err());
+
+    // a dropped container doesn't leak into the tree
+    let text = r#"{"a": 1, "a": {"b": [2, {"c": 3}]}, "d": 4}"#;
+    let mut ast = JsonAst::new();
+    ast.parse_with(text, &options(DuplicateKeys::FirstWins))
+        .unwrap();
+    assert_eq!(ast.to_json(text), r#"{"a":1,"d":4}"#);
+}
+
+#[test]
+fn last_wins() {
+    let value = parse(DOCUMENT, DuplicateKeys::LastWins);
+    // the last value sits where the first property was
+    assert_eq!(keys(&value), vec!["a", "b", "c"]);
+    assert_eq!(
+        value,
+        JsonValue::parse(r#"{"a": 5, "b": 2, "c": {"a": 4}}"#).unwrap()
+    );
+
+    let text = r#"{"x": 0, "a": [1], "y": 0, "a": {"b": 2}}"#;
+    let mut ast = JsonAst::new();
+    ast.parse_with(text, &options(DuplicateKeys::LastWins))
+        .unwrap();
+    assert_eq!(ast.to_json(text), r#"{"x":0,"a":{"b":2},"y":0}"#);
+}
+
+#[test]
+fn escaped_keys_are_the_same_key() {
+    let text = r#"{"a": 1, "\u0061": 2, "\u00e9": 3, "é": 4}"#;
+
+    let value = parse(text, DuplicateKeys::KeepAll);
+    assert_eq!(keys(&value), vec!["a", "a", "é", "é"]);
+    assert_eq!(value.get_all("a").count(), 2);
+
+    let err = JsonValue::parse_with(text, &options(DuplicateKeys::Reject)).unwrap_err();
+    assert!(matches!(err, ParseError::DuplicateKey { .. }));
+    assert_eq!(err.diagnostic().pos.column, 10);
+    assert_eq!(
+        err.diagnostic().found,
+        "string \"\\u0061\" again (first defined at line 1, column 2)"
+    );
+
+    assert_eq!(
+        parse(text, DuplicateKeys::FirstWins),
+        JsonValue::parse(r#"{"a": 1, "é": 3}"#).unwrap()
+    );
+    assert_eq!(
+        parse(text, DuplicateKeys::LastWins),
+        JsonValue::parse(r#"{"a": 2, "é": 4}"#).unwrap()
+    );
+
+    // JSON5 identifiers are decoded as well
+    let json5 = ParseOptions {
+        duplicate_keys: DuplicateKeys::Reject,
+        ..ParseOptions::json5()
+    };
+    for text in ["{a: 1, 'a': 2}", "{a: 1, \\u0061: 2}", "{'a': 1, \"a\": 2}"].iter() {
+        assert!(JsonValue::parse_with(text, &json5).is_err(), "{}", text);
+    }
+    // different spellings that decode to different names aren't duplicates
+    assert!(JsonValue::parse_with("{a: 1, A: 2, 'a ': 3}", &json5).is_ok());
+}
+
+#[test]
+fn borrowed_values_follow_the_policy() {
+    let value = BorrowedValue::parse_with(DOCUMENT, &options(DuplicateKeys::LastWins)).unwrap();
+    assert_eq!(value.into_owned(), parse(DOCUMENT, DuplicateKeys::LastWins));
+    assert!(BorrowedValue::parse_with(DOCUMENT, &options(DuplicateKeys::Reject)).is_err());
+}
@@ -0,0 +1,215 @@
+//! JSON Lines reading and writing
+use std::io::{self, BufRead, BufReader, Cursor, Read};
+
+use parser_util::json::{
+    ast::{ParseError, ParseOptions},
+    ndjson::{BadLinePolicy, NdjsonError, NdjsonReader, NdjsonWriter},
+    value::JsonValue,
+};
+
+fn reader(bytes: &[u8]) -> NdjsonReader<Cursor<Vec<u8>>> {
+    NdjsonReader::new(Cursor::new(bytes.to_vec()))
+}
+
+fn values(text: &str) -> Vec<JsonValue> {
+    text.lines()
+        .map(|line| JsonValue::parse(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn reads_one_value_per_line() {
+    let input = b"{\"a\": 1}\n[1, 2]\n\"three\"\n4\nnull";
+    let read: Vec<JsonValue> = reader(input).map(Result::unwrap).collect();
+    assert_eq!(read, values("{\"a\": 1}\n[1, 2]\n\"three\"\n4\nnull"));
+
+    // a trailing newline doesn't add an empty value
+    let mut lines = reader(b"1\n2\n");
+    assert_eq!(
+        lines.next_value().unwrap(),
+        Some(JsonValue::parse("1").unwrap())
+    );
+    assert_eq!(lines.line_number(), 1);
+    assert_eq!(
+        lines.next_value().unwrap(),
+        Some(JsonValue::parse("2").unwrap())
+    );
+    assert_eq!(lines.line_number(), 2);
+    assert_eq!(lines.next_value().unwrap(), None);
+    assert_eq!(lines.next_value().unwrap(), None);
+    assert_eq!(lines.line_number(), 2);
+
+    assert!(reader(b"").next().is_none());
+}
+
+#[test]
+fn blank_lines_and_crlf() {
+    let input = b"\n1\r\n   \r\n\t\n{\"a\": \"b\"}\r\n\r\n[]\n\n";
+    let mut lines = reader(input);
+    let mut read = Vec::new();
+    while let Some(value) = lines.next_value().unwrap() {
+        read.push((lines.line_number(), value));
+    }
+    assert_eq!(
+        read,
+        vec![
+            (2, JsonValue::parse("1").unwrap()),
+            (5, JsonValue::parse(r#"{"a": "b"}"#).unwrap()),
+            (7, JsonValue::parse("[]").unwrap()),
+        ]
+    );
+    assert_eq!(lines.line_number(), 8);
+    assert!(lines.skipped_lines().is_empty());
+
+    // a `\r` that isn't part of the line ending is whitespace inside the value
+    let read: Vec<JsonValue> = reader(b"[1,\r2]\r\n").map(Result::unwrap).collect();
+    assert_eq!(read, values("[1, 2]"));
+}
+
+#[test]
+fn stop_policy() {
+    let input = b"1\n[1,\n3\n";
+    let mut lines = reader(input);
+    assert_eq!(
+        lines.next_value().unwrap(),
+        Some(JsonValue::parse("1").unwrap())
+    );
+    match lines.next_value() {
+        Err(NdjsonError::Parse { line, error }) => {
+            assert_eq!(line, 2);
+            assert!(matches!(error, ParseError::MissingCloseBracket(_)));
+            // the diagnostic is relative to the line
+            assert_eq!(error.diagnostic().pos.line, 1);
+        }
+        other => panic!("{:?}", other),
+    }
+    // the error ends the iteration, line 3 is never read
+    assert_eq!(lines.next_value().unwrap(), None);
+    assert!(lines.skipped_lines().is_empty());
+
+    let results: Vec<_> = reader(input).collect();
+    assert_eq!(results.len(), 2);
+    assert!(results[0].is_ok());
+    assert_eq!(results[1].as_ref().unwrap_err().line(), 2);
+}
+
+#[test]
+fn skip_policy() {
+    let input = b"1\n[1,\n\n{\"a\" 1}\n4\nnope\n6";
+    let mut lines = reader(input).with_policy(BadLinePolicy::Skip);
+    let mut read = Vec::new();
+    for value in &mut lines {
+        read.push(value.unwrap());
+    }
+    assert_eq!(read, values("1\n4\n6"));
+    assert_eq!(lines.skipped_lines(), &[2, 4, 6]);
+    assert_eq!(lines.line_number(), 7);
+
+    let lines = reader(b"[\n]\n").with_policy(BadLinePolicy::Skip);
+    assert_eq!(lines.count(), 0);
+}
+
+#[test]
+fn invalid_utf8() {
+    let input = b"\"ok\"\n\"\xff\xfe\"\n\"\xc3\xa9\"\n\xe2\x82\n";
+    let mut lines = reader(input);
+    assert!(lines.next_value().unwrap().is_some());
+    let err = lines.next_value().unwrap_err();
+    assert!(matches!(err, NdjsonError::InvalidUtf8 { line: 2 }));
+    assert_eq!(err.to_string(), "line 2 isn't valid utf-8");
+
+    let mut lines = reader(input).with_policy(BadLinePolicy::Skip);
+    let read: Vec<JsonValue> = (&mut lines).map(Result::unwrap).collect();
+    assert_eq!(read, values("\"ok\"\n\"é\""));
+    assert_eq!(lines.skipped_lines(), &[2, 4]);
+}
+
+#[test]
+fn error_messages() {
+    let err = reader(b"1\n\n  [1 2]").nth(1).unwrap().unwrap_err();
+    assert_eq!(err.line(), 3);
+    assert_eq!(
+        err.to_string(),
+        format!("{} on line 3, column 6", err_diagnostic(&err))
+    );
+}
+
+fn err_diagnostic(err: &NdjsonError) -> String {
+    match err {
+        NdjsonError::Parse { error, .. } => error.diagnostic().to_string(),
+        _ => panic!("{:?}", err),
+    }
+}
+
+/// Gets interrupted once, then hands out its bytes and fails when they run out
+struct Flaky {
+    data: Cursor<Vec<u8>>,
+    interrupted: bool,
+}
+
+impl Read for Flaky {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.interrupted == false {
+            self.interrupted = true;
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "again"));
+        }
+        match self.data.read(buf)? {
+            0 => Err(io::Error::new(io::ErrorKind::Other, "disk on fire")),
+            num_read => Ok(num_read),
+        }
+    }
+}
+
+#[test]
+fn io_errors_end_the_iteration() {
+    let flaky = Flaky {
+        data: Cursor::new(b"1\nbad\n".to_vec()),
+        interrupted: false,
+    };
+    let mut lines = NdjsonReader::new(BufReader::new(flaky)).with_policy(BadLinePolicy::Skip);
+    // the interruption is retried
+    assert_eq!(
+        lines.next_value().unwrap(),
+        Some(JsonValue::parse("1").unwrap())
+    );
+    match lines.next_value() {
+        Err(NdjsonError::Io { line, error }) => {
+            assert_eq!(line, 3);
+            assert_eq!(error.to_string(), "disk on fire");
+        }
+        other => panic!("{:?}", other),
+    }
+    // `Skip` doesn't apply to io errors
+    assert_eq!(lines.next_value().unwrap(), None);
+    assert_eq!(lines.skipped_lines(), &[2]);
+}
+
+#[test]
+fn options_apply_to_every_line() {
+    let input = b"{a: 1,} // first\n[0x10, .5]\n";
+    let read: Vec<JsonValue> = reader(input)
+        .with_options(ParseOptions::json5())
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(read, values("{\"a\": 1}\n[16, 0.5]"));
+    assert!(reader(input).next().unwrap().is_err());
+}
+
+#[test]
+fn writer_round_trip() {
+    let written = values("{\"a\": \"line\\nbreak\"}\n[1, 2.50]\n\"x\"\nnull");
+    let mut writer = NdjsonWriter::new(Vec::new());
+    writer.write_all(&written).unwrap();
+    writer.write_value(&JsonValue::Bool(true)).unwrap();
+    writer.flush().unwrap();
+    let bytes = writer.into_inner();
+    assert_eq!(
+        String::from_utf8(bytes.clone()).unwrap(),
+        "{\"a\":\"line\\nbreak\"}\n[1,2.50]\n\"x\"\nnull\ntrue\n"
+    );
+    assert_eq!(Cursor::new(&bytes).lines().count(), 5);
+
+    let read: Vec<JsonValue> = reader(&bytes).map(Result::unwrap).collect();
+    assert_eq!(read[..4], written[..]);
+    assert_eq!(read[4], JsonValue::Bool(true));
+}
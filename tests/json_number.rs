@@ -0,0 +1,185 @@
+//! `JsonNumber` keeps the lexeme and only converts, exactly, when asked to
+use std::cmp::Ordering;
+
+use parser_util::json::{
+    number::{JsonNumber, NumberError},
+    value::JsonValue,
+};
+
+fn num(lexeme: &str) -> JsonNumber {
+    JsonNumber::parse(lexeme).unwrap()
+}
+
+#[test]
+fn parse_follows_the_json_grammar() {
+    for lexeme in ["0", "-0", "12", "1.5", "1e5", "1E+5", "-2.5e-3", "1e400"].iter() {
+        assert_eq!(num(lexeme).as_str(), *lexeme);
+    }
+    for lexeme in ["01", "1.", ".5", "+1", "1e", "0x10", "NaN", "1 ", ""].iter() {
+        assert!(JsonNumber::parse(lexeme).is_none(), "{}", lexeme);
+    }
+}
+
+#[test]
+fn lexemes_survive_parsing() {
+    let value = JsonValue::parse("[9007199254740993, 1.10, 1e400, -0]").unwrap();
+    let lexemes: Vec<&str> = value
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| v.as_number().unwrap().as_str())
+        .collect();
+    assert_eq!(lexemes, vec!["9007199254740993", "1.10", "1e400", "-0"]);
+    assert_eq!(
+        value.as_array().unwrap()[0].as_i64(),
+        Some(9007199254740993)
+    );
+}
+
+#[test]
+fn as_i64_edges() {
+    assert_eq!(num("9223372036854775807").as_i64(), Ok(i64::MAX));
+    assert_eq!(num("-9223372036854775808").as_i64(), Ok(i64::MIN));
+    assert_eq!(
+        num("9223372036854775808").as_i64(),
+        Err(NumberError::OutOfRange)
+    );
+    assert_eq!(
+        num("-9223372036854775809").as_i64(),
+        Err(NumberError::OutOfRange)
+    );
+    // integers written with a fraction or exponent still count
+    assert_eq!(num("1.0").as_i64(), Ok(1));
+    assert_eq!(num("1e2").as_i64(), Ok(100));
+    assert_eq!(num("100e-2").as_i64(), Ok(1));
+    assert_eq!(num("-0").as_i64(), Ok(0));
+    assert_eq!(num("0e999").as_i64(), Ok(0));
+    assert_eq!(num("1.5").as_i64(), Err(NumberError::NotAnInteger));
+    assert_eq!(num("1e-1").as_i64(), Err(NumberError::NotAnInteger));
+    assert_eq!(num("1e400").as_i64(), Err(NumberError::OutOfRange));
+    assert_eq!(
+        num("1e99999999999999999999").as_i64(),
+        Err(NumberError::OutOfRange)
+    );
+}
+
+#[test]
+fn as_u64_edges() {
+    assert_eq!(num("18446744073709551615").as_u64(), Ok(u64::MAX));
+    assert_eq!(
+        num("18446744073709551616").as_u64(),
+        Err(NumberError::OutOfRange)
+    );
+    assert_eq!(num("-0").as_u64(), Ok(0));
+    assert_eq!(num("-0.0").as_u64(), Ok(0));
+    assert_eq!(num("-1").as_u64(), Err(NumberError::OutOfRange));
+    assert_eq!(num("0.5").as_u64(), Err(NumberError::NotAnInteger));
+}
+
+#[test]
+fn as_f64_edges() {
+    assert_eq!(num("0.1").as_f64(), Ok(0.1));
+    assert_eq!(num("-2.5e-3").as_f64(), Ok(-0.0025));
+    assert_eq!(num("1e400").as_f64(), Err(NumberError::OutOfRange));
+    assert_eq!(num("-1e400").as_f64(), Err(NumberError::OutOfRange));
+    // too small rounds to zero instead of failing
+    assert_eq!(num("1e-400").as_f64(), Ok(0.0));
+}
+
+#[test]
+fn to_decimal_string_is_exact() {
+    let cases = [
+        ("1.50e3", "1500"),
+        ("-25E-3", "-0.025"),
+        ("123.456", "123.456"),
+        ("0.000", "0"),
+        ("-0", "0"),
+        ("0e5", "0"),
+        ("1e-5", "0.00001"),
+        ("12345678901234567890123", "12345678901234567890123"),
+        ("9007199254740993e0", "9007199254740993"),
+    ];
+    for (lexeme, decimal) in cases.iter() {
+        assert_eq!(num(lexeme).to_decimal_string().as_deref(), Ok(*decimal));
+    }
+    assert_eq!(
+        num("1e5000").to_decimal_string(),
+        Err(NumberError::OutOfRange)
+    );
+    assert_eq!(
+        num("1e-5000").to_decimal_string(),
+        Err(NumberError::OutOfRange)
+    );
+}
+
+#[test]
+fn from_floats() {
+    assert_eq!(JsonNumber::from_f64(0.1).unwrap().as_str(), "0.1");
+    assert_eq!(JsonNumber::from_f64(1.0).unwrap().as_str(), "1");
+    assert_eq!(JsonNumber::from_f64(-0.0).unwrap().as_str(), "-0");
+    assert_eq!(JsonNumber::from_f64(1e300).unwrap().as_str(), "1e300");
+    assert_eq!(JsonNumber::from_f64(5e-324).unwrap().as_f64(), Ok(5e-324));
+    assert!(JsonNumber::from_f64(f64::NAN).is_none());
+    assert!(JsonNumber::from_f64(f64::INFINITY).is_none());
+    assert!(JsonNumber::from_f64(f64::NEG_INFINITY).is_none());
+
+    assert_eq!(JsonNumber::from_f32(0.1).unwrap().as_str(), "0.1");
+    assert!(JsonNumber::from_f32(f32::NAN).is_none());
+
+    // every float comes back exactly
+    for &val in [
+        0.1,
+        1.0 / 3.0,
+        123456.789,
+        -1e-7,
+        f64::MAX,
+        f64::MIN_POSITIVE,
+    ]
+    .iter()
+    {
+        assert_eq!(JsonNumber::from_f64(val).unwrap().as_f64(), Ok(val));
+    }
+}
+
+#[test]
+fn from_integers() {
+    assert_eq!(JsonNumber::from(i64::MIN).as_i64(), Ok(i64::MIN));
+    assert_eq!(JsonNumber::from(u64::MAX).as_u64(), Ok(u64::MAX));
+    assert_eq!(JsonNumber::from(-7i32).as_str(), "-7");
+    assert_eq!(JsonNumber::from(7u32).to_string(), "7");
+}
+
+#[test]
+fn cmp_value_is_exact() {
+    use Ordering::*;
+    let cases = [
+        // both of these round to the same f64
+        ("9007199254740993", "9007199254740992", Greater),
+        ("9007199254740993", "9007199254740993.0", Equal),
+        ("1.0", "1", Equal),
+        ("100e-2", "1", Equal),
+        ("-0", "0", Equal),
+        ("0e10", "-0.0e-10", Equal),
+        ("1e400", "1E+400", Equal),
+        ("1e400", "9e399", Greater),
+        ("-1e400", "-9e399", Less),
+        ("1e-400", "0", Greater),
+        ("-1e-400", "0", Less),
+        ("0.1", "0.10000000000000001", Less),
+        ("12", "9", Greater),
+        ("-12", "-9", Less),
+        ("123", "12.3e1", Equal),
+        ("1.25", "1.2", Greater),
+        ("1e99999999999999999999", "1e400", Greater),
+    ];
+    for (left, right, ordering) in cases.iter() {
+        assert_eq!(num(left).cmp_value(&num(right)), *ordering, "{} vs {}", left, right);
+        assert_eq!(
+            num(right).cmp_value(&num(left)),
+            ordering.reverse(),
+            "{} vs {}",
+            right,
+            left
+        );
+    }
+}
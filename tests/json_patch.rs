@@ -0,0 +1,345 @@
+//! JSON Patch (RFC 6902) and JSON Merge Patch (RFC 7386), checked against the examples in the
+//! appendices of both RFCs
+use parser_util::json::{
+    patch::{parse_patch, patch_to_value, PatchError},
+    pointer::PointerError,
+    value::JsonValue,
+};
+
+fn parse(text: &str) -> JsonValue {
+    JsonValue::parse(text).unwrap()
+}
+
+/// applies `patch` to `target` and returns the result, or the error along with the untouched target
+fn patched(target: &str, patch: &str) -> Result<JsonValue, (PatchError, JsonValue)> {
+    let mut doc = parse(target);
+    match doc.apply_patch(&parse(patch)) {
+        Ok(()) => Ok(doc),
+        Err(err) => Err((err, doc)),
+    }
+}
+
+#[test]
+fn rfc_6902_appendix_a() {
+    let cases = [
+        // A.1 adding an object member
+        (
+            r#"{"foo": "bar"}"#,
+            r#"[{"op": "add", "path": "/baz", "value": "qux"}]"#,
+            r#"{"foo": "bar", "baz": "qux"}"#,
+        ),
+        // A.2 adding an array element
+        (
+            r#"{"foo": ["bar", "baz"]}"#,
+            r#"[{"op": "add", "path": "/foo/1", "value": "qux"}]"#,
+            r#"{"foo": ["bar", "qux", "baz"]}"#,
+        ),
+        // A.3 removing an object member
+        (
+            r#"{"baz": "qux", "foo": "bar"}"#,
+            r#"[{"op": "remove", "path": "/baz"}]"#,
+            r#"{"foo": "bar"}"#,
+        ),
+        // A.4 removing an array element
+        (
+            r#"{"foo": ["bar", "qux", "baz"]}"#,
+            r#"[{"op": "remove", "path": "/foo/1"}]"#,
+            r#"{"foo": ["bar", "baz"]}"#,
+        ),
+        // A.5 replacing a value
+        (
+            r#"{"baz": "qux", "foo": "bar"}"#,
+            r#"[{"op": "replace", "path": "/baz", "value": "boo"}]"#,
+            r#"{"baz": "boo", "foo": "bar"}"#,
+        ),
+        // A.6 moving a value
+        (
+            r#"{"foo": {"bar": "baz", "waldo": "fred"}, "qux": {"corge": "grault"}}"#,
+            r#"[{"op": "move", "from": "/foo/waldo", "path": "/qux/thud"}]"#,
+            r#"{"foo": {"bar": "baz"}, "qux": {"corge": "grault", "thud": "fred"}}"#,
+        ),
+        // A.7 moving an array element
+        (
+            r#"{"foo": ["all", "grass", "cows", "eat"]}"#,
+            r#"[{"op": "move", "from": "/foo/1", "path": "/foo/3"}]"#,
+            r#"{"foo": ["all", "cows", "eat", "grass"]}"#,
+        ),
+        // A.8 testing a value: success
+        (
+            r#"{"baz": "qux", "foo": ["a", 2, "c"]}"#,
+            r#"[{"op": "test", "path": "/baz", "value": "qux"},
+                {"op": "test", "path": "/foo/1", "value": 2}]"#,
+            r#"{"baz": "qux", "foo": ["a", 2, "c"]}"#,
+        ),
+        // A.10 adding a nested member object
+        (
+            r#"{"foo": "bar"}"#,
+            r#"[{"op": "add", "path": "/child", "value": {"grandchild": {}}}]"#,
+            r#"{"foo": "bar", "child": {"grandchild": {}}}"#,
+        ),
+        // A.11 ignoring unrecognized elements
+        (
+            r#"{"foo": "bar"}"#,
+            r#"[{"op": "add", "path": "/baz", "value": "qux", "xyz": 123}]"#,
+            r#"{"foo": "bar", "baz": "qux"}"#,
+        ),
+        // A.14 ~ escape ordering
+        (
+            r#"{"/": 9, "~1": 10}"#,
+            r#"[{"op": "test", "path": "/~01", "value": 10}]"#,
+            r#"{"/": 9, "~1": 10}"#,
+        ),
+        // A.16 adding an array value
+        (
+            r#"{"foo": ["bar"]}"#,
+            r#"[{"op": "add", "path": "/foo/-", "value": ["abc", "def"]}]"#,
+            r#"{"foo": ["bar", ["abc", "def"]]}"#,
+        ),
+    ];
+    for (target, patch, expected) in cases.iter() {
+        assert_eq!(
+            patched(target, patch).unwrap(),
+            parse(expected),
+            "{}",
+            patch
+        );
+    }
+
+    // A.9 testing a value: error
+    assert_eq!(
+        patched(
+            r#"{"baz": "qux"}"#,
+            r#"[{"op": "test", "path": "/baz", "value": "bar"}]"#
+        )
+        .unwrap_err()
+        .0,
+        PatchError::TestFailed {
+            index: 0,
+            path: String::from("/baz"),
+        }
+    );
+    // A.12 adding to a nonexistent target
+    assert_eq!(
+        patched(
+            r#"{"foo": "bar"}"#,
+            r#"[{"op": "add", "path": "/baz/bat", "value": "qux"}]"#
+        )
+        .unwrap_err()
+        .0,
+        PatchError::Pointer {
+            index: 0,
+            error: PointerError::MissingKey {
+                at: String::new(),
+                segment: String::from("baz"),
+            },
+        }
+    );
+    // A.13 invalid JSON Patch document
+    assert!(matches!(
+        patched(
+            r#"{"foo": "bar"}"#,
+            r#"[{"op": "add", "path": "/baz", "value": "qux", "op": "remove"}]"#
+        ),
+        Err((PatchError::Malformed { index: 0, .. }, _))
+    ));
+    // A.15 comparing strings and numbers
+    assert!(matches!(
+        patched(
+            r#"{"/": 9, "~1": 10}"#,
+            r#"[{"op": "test", "path": "/~01", "value": "10"}]"#
+        ),
+        Err((PatchError::TestFailed { index: 0, .. }, _))
+    ));
+}
+
+#[test]
+fn failed_patches_roll_back() {
+    let target = r#"{"a": [1, 2], "b": {"c": true}}"#;
+    let patch = r#"[
+        {"op": "add", "path": "/a/-", "value": 3},
+        {"op": "remove", "path": "/b/c"},
+        {"op": "test", "path": "/a/0", "value": 99},
+        {"op": "add", "path": "/never", "value": null}
+    ]"#;
+    let (err, doc) = patched(target, patch).unwrap_err();
+    assert_eq!(
+        err,
+        PatchError::TestFailed {
+            index: 2,
+            path: String::from("/a/0"),
+        }
+    );
+    // the first two operations went through on a copy, the document itself is untouched
+    assert_eq!(doc, parse(target));
+
+    let (err, doc) = patched(
+        target,
+        r#"[{"op": "replace", "path": "/b", "value": 1}, {"op": "remove", "path": "/a/5"}]"#,
+    )
+    .unwrap_err();
+    assert!(matches!(err, PatchError::Pointer { index: 1, .. }));
+    assert_eq!(doc, parse(target));
+}
+
+#[test]
+fn move_into_own_child() {
+    let target = r#"{"a": {"b": {}}, "ab": 1}"#;
+    let (err, doc) = patched(
+        target,
+        r#"[{"op": "move", "from": "/a", "path": "/a/b/c"}]"#,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        PatchError::MoveIntoChild {
+            index: 0,
+            from: String::from("/a"),
+            path: String::from("/a/b/c"),
+        }
+    );
+    assert_eq!(doc, parse(target));
+
+    // a sibling that only shares a prefix isn't a child
+    assert_eq!(
+        patched(
+            target,
+            r#"[{"op": "move", "from": "/ab", "path": "/a/b/ab"}]"#
+        )
+        .unwrap(),
+        parse(r#"{"a": {"b": {"ab": 1}}}"#)
+    );
+    // moving a value onto itself is a no-op, but the value still has to exist
+    assert_eq!(
+        patched(target, r#"[{"op": "move", "from": "/a", "path": "/a"}]"#).unwrap(),
+        parse(target)
+    );
+    assert!(patched(target, r#"[{"op": "move", "from": "/x", "path": "/x"}]"#).is_err());
+}
+
+#[test]
+fn other_operations() {
+    let target = r#"{"a": [1, 2], "n": 1}"#;
+    let cases = [
+        // `-` appends, an index equal to the length does too
+        (
+            r#"[{"op": "add", "path": "/a/-", "value": 3}]"#,
+            r#"{"a": [1, 2, 3], "n": 1}"#,
+        ),
+        (
+            r#"[{"op": "add", "path": "/a/2", "value": 3}]"#,
+            r#"{"a": [1, 2, 3], "n": 1}"#,
+        ),
+        // adding an existing member replaces it in place
+        (
+            r#"[{"op": "add", "path": "/n", "value": 2}]"#,
+            r#"{"a": [1, 2], "n": 2}"#,
+        ),
+        (r#"[{"op": "add", "path": "", "value": 5}]"#, "5"),
+        (
+            r#"[{"op": "copy", "from": "/a", "path": "/b"}]"#,
+            r#"{"a": [1, 2], "n": 1, "b": [1, 2]}"#,
+        ),
+        (
+            r#"[{"op": "copy", "from": "/a/0", "path": "/a/0"}]"#,
+            r#"{"a": [1, 1, 2], "n": 1}"#,
+        ),
+        // numbers are compared by value
+        (
+            r#"[{"op": "test", "path": "/a", "value": [1.0, 2e0]}]"#,
+            target,
+        ),
+        (r#"[]"#, target),
+    ];
+    for (patch, expected) in cases.iter() {
+        assert_eq!(
+            patched(target, patch).unwrap(),
+            parse(expected),
+            "{}",
+            patch
+        );
+    }
+
+    let failures = [
+        r#"[{"op": "add", "path": "/a/3", "value": 0}]"#,
+        r#"[{"op": "add", "path": "/a/01", "value": 0}]"#,
+        r#"[{"op": "remove", "path": "/a/-"}]"#,
+        r#"[{"op": "remove", "path": ""}]"#,
+        r#"[{"op": "replace", "path": "/missing", "value": 0}]"#,
+        r#"[{"op": "add", "path": "/n/x", "value": 0}]"#,
+        r#"[{"op": "test", "path": "/n", "value": "1"}]"#,
+    ];
+    for patch in failures.iter() {
+        assert!(patched(target, patch).is_err(), "{}", patch);
+    }
+}
+
+#[test]
+fn malformed_patches() {
+    let malformed = [
+        r#"[1]"#,
+        r#"[{"path": "/a"}]"#,
+        r#"[{"op": 1, "path": "/a"}]"#,
+        r#"[{"op": "frobnicate", "path": "/a"}]"#,
+        r#"[{"op": "add", "path": "/a"}]"#,
+        r#"[{"op": "add", "path": 1, "value": 0}]"#,
+        r#"[{"op": "move", "path": "/a"}]"#,
+    ];
+    for patch in malformed.iter() {
+        assert!(
+            matches!(
+                parse_patch(&parse(patch)),
+                Err(PatchError::Malformed { .. })
+            ),
+            "{}",
+            patch
+        );
+    }
+    assert_eq!(parse_patch(&parse("{}")), Err(PatchError::NotAnArray));
+    assert!(matches!(
+        parse_patch(&parse(r#"[{"op": "remove", "path": "a"}]"#)),
+        Err(PatchError::Pointer { index: 0, .. })
+    ));
+
+    // operations survive a trip through `to_value`
+    let patch = parse(
+        r#"[{"op": "add", "path": "/a", "value": 1}, {"op": "remove", "path": "/a"},
+            {"op": "replace", "path": "/a", "value": 1}, {"op": "move", "from": "/a", "path": "/b"},
+            {"op": "copy", "from": "/a", "path": "/b"}, {"op": "test", "path": "/a", "value": 1}]"#,
+    );
+    let operations = parse_patch(&patch).unwrap();
+    assert_eq!(patch_to_value(&operations), patch);
+}
+
+#[test]
+fn rfc_7386_appendix_a() {
+    let cases = [
+        (r#"{"a":"b"}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+        (r#"{"a":"b"}"#, r#"{"b":"c"}"#, r#"{"a":"b","b":"c"}"#),
+        (r#"{"a":"b"}"#, r#"{"a":null}"#, r#"{}"#),
+        (r#"{"a":"b","b":"c"}"#, r#"{"a":null}"#, r#"{"b":"c"}"#),
+        (r#"{"a":["b"]}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+        (r#"{"a":"c"}"#, r#"{"a":["b"]}"#, r#"{"a":["b"]}"#),
+        (
+            r#"{"a":{"b":"c"}}"#,
+            r#"{"a":{"b":"d","c":null}}"#,
+            r#"{"a":{"b":"d"}}"#,
+        ),
+        (r#"{"a":[{"b":"c"}]}"#, r#"{"a":[1]}"#, r#"{"a":[1]}"#),
+        (r#"["a","b"]"#, r#"["c","d"]"#, r#"["c","d"]"#),
+        (r#"{"a":"b"}"#, r#"["c"]"#, r#"["c"]"#),
+        (r#"{"a":"foo"}"#, r#"null"#, r#"null"#),
+        (r#"{"a":"foo"}"#, r#""bar""#, r#""bar""#),
+        (r#"{"e":null}"#, r#"{"a":1}"#, r#"{"e":null,"a":1}"#),
+        (r#"[1,2]"#, r#"{"a":"b","c":null}"#, r#"{"a":"b"}"#),
+        (
+            r#"{}"#,
+            r#"{"a":{"bb":{"ccc":null}}}"#,
+            r#"{"a":{"bb":{}}}"#,
+        ),
+    ];
+    for (target, patch, expected) in cases.iter() {
+        let mut doc = parse(target);
+        doc.apply_merge_patch(&parse(patch));
+        assert_eq!(doc, parse(expected), "{} + {}", target, patch);
+    }
+}
@@ -0,0 +1,338 @@
+//! RFC 9535 JSONPath, most of the queries and documents come from the examples in the RFC
+use parser_util::json::{path::JsonPath, value::JsonValue};
+
+const STORE: &str = r#"{ "store": {
+    "book": [
+      { "category": "reference", "author": "Nigel Rees",
+        "title": "Sayings of the Century", "price": 8.95 },
+      { "category": "fiction", "author": "Evelyn Waugh",
+        "title": "Sword of Honour", "price": 12.99 },
+      { "category": "fiction", "author": "Herman Melville",
+        "title": "Moby Dick", "isbn": "0-553-21311-3", "price": 8.99 },
+      { "category": "fiction", "author": "J. R. R. Tolkien",
+        "title": "The Lord of the Rings", "isbn": "0-395-19395-8", "price": 22.99 }
+    ],
+    "bicycle": { "color": "red", "price": 399 }
+  }
+}"#;
+
+/// the document from RFC 9535 section 2.3.5.3
+const FILTER_DOCUMENT: &str = r#"{
+  "a": [3, 5, 1, 2, 4, 6, {"b": "j"}, {"b": "k"}, {"b": {}}, {"b": "kilo"}],
+  "o": {"p": 1, "q": 2, "r": 3, "s": 5, "t": {"u": 6}},
+  "e": "f"
+}"#;
+
+/// the normalized paths of every node `query` selects from `document`
+fn paths(document: &str, query: &str) -> Vec<String> {
+    let value = JsonValue::parse(document).unwrap();
+    let nodes = value
+        .query(query)
+        .unwrap_or_else(|err| panic!("{}: {}", query, err));
+    nodes.iter().map(|node| node.normalized_path()).collect()
+}
+
+fn check(document: &str, cases: &[(&str, &[&str])]) {
+    for (query, expected) in cases.iter() {
+        assert_eq!(paths(document, query), *expected, "{}", query);
+    }
+}
+
+#[test]
+fn selectors() {
+    let authors: &[&str] = &[
+        "$['store']['book'][0]['author']",
+        "$['store']['book'][1]['author']",
+        "$['store']['book'][2]['author']",
+        "$['store']['book'][3]['author']",
+    ];
+    check(
+        STORE,
+        &[
+            ("$", &["$"]),
+            ("$.store.book[*].author", authors),
+            ("$..author", authors),
+            (
+                "$['store'][\"bicycle\"].color",
+                &["$['store']['bicycle']['color']"],
+            ),
+            (
+                "$.store.*",
+                &["$['store']['book']", "$['store']['bicycle']"],
+            ),
+            (
+                "$.store..price",
+                &[
+                    "$['store']['book'][0]['price']",
+                    "$['store']['book'][1]['price']",
+                    "$['store']['book'][2]['price']",
+                    "$['store']['book'][3]['price']",
+                    "$['store']['bicycle']['price']",
+                ],
+            ),
+            ("$..book[2]", &["$['store']['book'][2]"]),
+            ("$..book[-1]", &["$['store']['book'][3]"]),
+            (
+                "$..book[0,1]",
+                &["$['store']['book'][0]", "$['store']['book'][1]"],
+            ),
+            (
+                "$..book[:2]",
+                &["$['store']['book'][0]", "$['store']['book'][1]"],
+            ),
+            ("$.store.book[4]", &[]),
+            ("$.store.book[-5]", &[]),
+            ("$.store.missing", &[]),
+            // names don't select from arrays and indices don't select from objects
+            ("$.store.book.author", &[]),
+            ("$.store[0]", &[]),
+        ],
+    );
+    assert_eq!(paths(STORE, "$..*").len(), 27);
+    check(
+        "[[1, 2], [3]]",
+        &[("$..[0]", &["$[0]", "$[0][0]", "$[1][0]"])],
+    );
+}
+
+#[test]
+fn slices() {
+    let letters = r#"["a", "b", "c", "d", "e", "f", "g"]"#;
+    check(
+        letters,
+        &[
+            ("$[1:3]", &["$[1]", "$[2]"]),
+            ("$[5:]", &["$[5]", "$[6]"]),
+            ("$[1:5:2]", &["$[1]", "$[3]"]),
+            ("$[5:1:-2]", &["$[5]", "$[3]"]),
+            (
+                "$[::-1]",
+                &["$[6]", "$[5]", "$[4]", "$[3]", "$[2]", "$[1]", "$[0]"],
+            ),
+            ("$[-2:]", &["$[5]", "$[6]"]),
+            ("$[:-5]", &["$[0]", "$[1]"]),
+            ("$[ 1 : 2 ]", &["$[1]"]),
+            ("$[::0]", &[]),
+            ("$[10:]", &[]),
+            ("$[3:1]", &[]),
+        ],
+    );
+}
+
+#[test]
+fn filters() {
+    check(
+        FILTER_DOCUMENT,
+        &[
+            ("$.a[?@.b == 'kilo']", &["$['a'][9]"]),
+            ("$.a[?(@.b == 'kilo')]", &["$['a'][9]"]),
+            ("$.a[?@>3.5]", &["$['a'][1]", "$['a'][4]", "$['a'][5]"]),
+            (
+                "$.a[?@.b]",
+                &["$['a'][6]", "$['a'][7]", "$['a'][8]", "$['a'][9]"],
+            ),
+            ("$[?@.*]", &["$['a']", "$['o']"]),
+            ("$[?@[?@.b]]", &["$['a']"]),
+            (
+                "$.o[?@<3, ?@<3]",
+                &["$['o']['p']", "$['o']['q']", "$['o']['p']", "$['o']['q']"],
+            ),
+            ("$.a[?@<2 || @.b == \"k\"]", &["$['a'][2]", "$['a'][7]"]),
+            ("$.o[?@>1 && @<4]", &["$['o']['q']", "$['o']['r']"]),
+            ("$.o[?@.u || @.x]", &["$['o']['t']"]),
+            (
+                "$.a[?@.b == $.x]",
+                &[
+                    "$['a'][0]",
+                    "$['a'][1]",
+                    "$['a'][2]",
+                    "$['a'][3]",
+                    "$['a'][4]",
+                    "$['a'][5]",
+                ],
+            ),
+            ("$.a[?!@.b && @ >= 5]", &["$['a'][1]", "$['a'][5]"]),
+            ("$.a[?!(@ < 6 || @.b)]", &["$['a'][5]"]),
+            ("$.a[?@.b == $.a[8].b]", &["$['a'][8]"]),
+            ("$.a[?@.b > 'j']", &["$['a'][7]", "$['a'][9]"]),
+            // a string and a number are never ordered
+            ("$.a[?@ < 'z']", &[]),
+            ("$[?@ == 'f']", &["$['e']"]),
+        ],
+    );
+    assert_eq!(paths(FILTER_DOCUMENT, "$.a[?@ == @]").len(), 10);
+    // objects compare by their members, not by member order
+    check(
+        r#"{"x": [{"a": 1, "b": [2]}, {"b": [2], "a": 1.0}, {"a": 1}], "y": {"b": [2], "a": 1}}"#,
+        &[("$.x[?@ == $.y]", &["$['x'][0]", "$['x'][1]"])],
+    );
+}
+
+#[test]
+fn numbers_compare_exactly() {
+    let document = "[9007199254740992, 9007199254740993, 1e400, 1E400, 100e-2, -0, 0.0, -1e400]";
+    check(
+        document,
+        &[
+            ("$[?@ == 9007199254740993]", &["$[1]"]),
+            ("$[?@ == 9007199254740992]", &["$[0]"]),
+            ("$[?@ > 9007199254740992]", &["$[1]", "$[2]", "$[3]"]),
+            ("$[?@ == 1e400]", &["$[2]", "$[3]"]),
+            ("$[?@ < -1e399]", &["$[7]"]),
+            ("$[?@ == 1]", &["$[4]"]),
+            ("$[?@ == 0]", &["$[5]", "$[6]"]),
+        ],
+    );
+}
+
+#[test]
+fn functions() {
+    check(
+        r#"{"items": ["abc", [1, 2], {"a": 1}, 5, "é😀"]}"#,
+        &[
+            // length counts characters, not bytes
+            (
+                "$.items[?length(@) == 2]",
+                &["$['items'][1]", "$['items'][4]"],
+            ),
+            ("$.items[?length(@) == 1]", &["$['items'][2]"]),
+            ("$.items[?length(@) > 100]", &[]),
+        ],
+    );
+    check(
+        r#"{"x": [1, 2], "y": {"a": 1}, "z": "s"}"#,
+        &[
+            ("$[?count(@.*) == 2]", &["$['x']"]),
+            ("$[?count(@.*) == 0]", &["$['z']"]),
+        ],
+    );
+    // value() is Nothing unless the query selects exactly one node
+    check(
+        r#"{"p": {"c": 1}, "q": {"c": 1, "d": {"c": 1}}, "r": {}}"#,
+        &[("$[?value(@..c) == 1]", &["$['p']"])],
+    );
+}
+
+#[cfg(feature = "extras")]
+#[test]
+fn regex_functions() {
+    check(
+        FILTER_DOCUMENT,
+        &[
+            ("$.a[?match(@.b, '[jk]')]", &["$['a'][6]", "$['a'][7]"]),
+            (
+                "$.a[?search(@.b, '[jk]')]",
+                &["$['a'][6]", "$['a'][7]", "$['a'][9]"],
+            ),
+            ("$.a[?match(@.b, 'k.*')]", &["$['a'][7]", "$['a'][9]"]),
+            // only strings can match
+            ("$.a[?search(@, '.')]", &[]),
+        ],
+    );
+    // I-Regexp has no anchors, `^` and `$` are plain characters outside a class
+    let document = r#"["a$b", "ab", "x^y", "a\rb", "^"]"#;
+    check(
+        document,
+        &[
+            ("$[?match(@, 'a$b')]", &["$[0]"]),
+            ("$[?search(@, '^y')]", &["$[2]"]),
+            ("$[?search(@, '^a')]", &[]),
+            ("$[?match(@, '[^a]')]", &["$[4]"]),
+            // `.` matches neither `\n` nor `\r`
+            ("$[?match(@, 'a.b')]", &["$[0]"]),
+            ("$[?match(@, 'a\\\\.b')]", &[]),
+        ],
+    );
+    // a pattern that doesn't compile just doesn't match
+    check(document, &[("$[?search(@, '(')]", &[])]);
+}
+
+#[test]
+fn normalized_paths_and_pointers() {
+    let value =
+        JsonValue::parse(r#"{"it's": [0, {"tab\tkey": 1, "\u0001": 2, "a/b~": 3}]}"#).unwrap();
+    let nodes = value.query("$..*").unwrap();
+    let normalized: Vec<String> = nodes.iter().map(|node| node.normalized_path()).collect();
+    assert_eq!(
+        normalized,
+        vec![
+            "$['it\\'s']",
+            "$['it\\'s'][0]",
+            "$['it\\'s'][1]",
+            "$['it\\'s'][1]['tab\\tkey']",
+            "$['it\\'s'][1]['\\u0001']",
+            "$['it\\'s'][1]['a/b~']",
+        ]
+    );
+    assert_eq!(nodes[5].pointer(), "/it's/1/a~1b~0");
+    // a normalized path is itself a query that selects the same node
+    for node in nodes.iter() {
+        let again = value.query(&node.normalized_path()).unwrap();
+        assert_eq!(again, vec![node.clone()]);
+    }
+}
+
+#[test]
+fn syntax_errors() {
+    let bad = [
+        "",
+        "store",
+        "@.a",
+        "$.",
+        "$.1",
+        "$[",
+        "$[1",
+        "$['a'",
+        "$['a\\x']",
+        "$[01]",
+        "$[-0]",
+        "$[9007199254740992]",
+        "$[1:2:3:4]",
+        "$.a ",
+        "$[?@.a ==]",
+        "$[?(@.a]",
+        "$[?@.a = 1]",
+        "$[?1]",
+        "$[?@.* == 1]",
+        "$[?@..a == 1]",
+        "$[?foo(@)]",
+        "$[?length(@)]",
+        "$[?length(@.*) == 1]",
+        "$[?length(@, @) == 1]",
+        "$[?count(1) == 1]",
+        "$[?count(@.*)]",
+        "$[?match(@, 'a') == true]",
+        "$[?@.a == nil]",
+    ];
+    for query in bad.iter() {
+        assert!(
+            JsonPath::parse(query).is_err(),
+            "{:?} should not parse",
+            query
+        );
+    }
+
+    let err = JsonPath::parse("$[01]").unwrap_err();
+    assert_eq!(
+        (err.offset, err.message.as_str()),
+        (3, "expected `,` or `]`")
+    );
+    let err = JsonPath::parse("$.a[?@.* == 1]").unwrap_err();
+    assert_eq!(err.offset, 5);
+    assert_eq!(
+        err.to_string(),
+        "invalid JSONPath query at offset 5: only singular queries can be compared"
+    );
+
+    // whitespace is fine between tokens
+    for query in [
+        "$ .a",
+        "$[ 'a' , 'b' ]",
+        "$[? @.a == 1 ]",
+        "$[?(@.a)&&(@.b)]",
+    ]
+    .iter()
+    {
+        assert!(JsonPath::parse(query).is_ok(), "{:?} should parse", query);
+    }
+}
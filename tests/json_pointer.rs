@@ -0,0 +1,145 @@
+//! RFC 6901 JSON Pointer, the examples are the ones from section 5 of the RFC
+use parser_util::json::{
+    pointer::{parse_index, parse_pointer, to_pointer, PointerError},
+    value::JsonValue,
+};
+
+const RFC_DOCUMENT: &str = r#"{
+    "foo": ["bar", "baz"],
+    "": 0,
+    "a/b": 1,
+    "c%d": 2,
+    "e^f": 3,
+    "g|h": 4,
+    "i\\j": 5,
+    "k\"l": 6,
+    " ": 7,
+    "m~n": 8
+}"#;
+
+fn document() -> JsonValue {
+    JsonValue::parse(RFC_DOCUMENT).unwrap()
+}
+
+#[test]
+fn rfc_6901_section_5() {
+    let doc = document();
+    assert_eq!(doc.pointer("").unwrap(), &doc);
+    assert_eq!(
+        doc.pointer("/foo").unwrap(),
+        &JsonValue::parse(r#"["bar", "baz"]"#).unwrap()
+    );
+    assert_eq!(doc.pointer("/foo/0").unwrap().as_str(), Some("bar"));
+    let cases = [
+        ("/", 0),
+        ("/a~1b", 1),
+        ("/c%d", 2),
+        ("/e^f", 3),
+        ("/g|h", 4),
+        ("/i\\j", 5),
+        ("/k\"l", 6),
+        ("/ ", 7),
+        ("/m~0n", 8),
+    ];
+    for (pointer, expected) in cases.iter() {
+        assert_eq!(
+            doc.pointer(pointer).unwrap().as_i64(),
+            Some(*expected),
+            "{}",
+            pointer
+        );
+    }
+}
+
+#[test]
+fn escapes() {
+    assert_eq!(parse_pointer("/a~1b/m~0n").unwrap(), vec!["a/b", "m~n"]);
+    // `~01` is `~` followed by `1`, not `/`
+    assert_eq!(parse_pointer("/~01").unwrap(), vec!["~1"]);
+    assert_eq!(parse_pointer("/~10").unwrap(), vec!["/0"]);
+    assert_eq!(parse_pointer("").unwrap(), Vec::<String>::new());
+    assert_eq!(parse_pointer("/").unwrap(), vec![""]);
+    assert_eq!(parse_pointer("//").unwrap(), vec!["", ""]);
+
+    assert_eq!(to_pointer(&["a/b", "m~n", ""]), "/a~1b/m~0n/");
+    for pointer in ["/a~1b/m~0n", "/~01", "/", "", "/x/0/-"].iter() {
+        assert_eq!(to_pointer(&parse_pointer(pointer).unwrap()), *pointer);
+    }
+}
+
+#[test]
+fn array_indices() {
+    assert_eq!(parse_index("0"), Some(0));
+    assert_eq!(parse_index("10"), Some(10));
+    for bad in ["01", "00", "-", "-1", "+1", "1.0", "", " 1", "1e2"].iter() {
+        assert_eq!(parse_index(bad), None, "{}", bad);
+    }
+
+    let doc = document();
+    for bad in ["/foo/01", "/foo/-", "/foo/2", "/foo/-1"].iter() {
+        assert_eq!(
+            doc.pointer(bad),
+            Err(PointerError::InvalidIndex {
+                at: String::from("/foo"),
+                segment: bad[5..].to_string(),
+            })
+        );
+    }
+}
+
+#[test]
+fn error_variants() {
+    let doc = document();
+    assert_eq!(
+        doc.pointer("foo"),
+        Err(PointerError::Syntax {
+            pointer: String::from("foo"),
+            reason: "a non-empty pointer must start with `/`",
+        })
+    );
+    for bad in ["/m~2n", "/m~", "/a/~"].iter() {
+        assert!(
+            matches!(doc.pointer(bad), Err(PointerError::Syntax { .. })),
+            "{}",
+            bad
+        );
+    }
+    assert_eq!(
+        doc.pointer("/missing/x"),
+        Err(PointerError::MissingKey {
+            at: String::new(),
+            segment: String::from("missing"),
+        })
+    );
+    assert_eq!(
+        doc.pointer("/foo/0/x"),
+        Err(PointerError::NotAContainer {
+            at: String::from("/foo/0"),
+            segment: String::from("x"),
+        })
+    );
+    // `at` is escaped again
+    let nested = JsonValue::parse(r#"{"a/b": {"c": 1}}"#).unwrap();
+    assert_eq!(
+        nested.pointer("/a~1b/d"),
+        Err(PointerError::MissingKey {
+            at: String::from("/a~1b"),
+            segment: String::from("d"),
+        })
+    );
+    assert_eq!(
+        nested.pointer("/a~1b/d").unwrap_err().to_string(),
+        "no property \"d\" in the object at \"/a~1b\""
+    );
+}
+
+#[test]
+fn pointer_mut() {
+    let mut doc = document();
+    *doc.pointer_mut("/foo/1").unwrap() = JsonValue::Null;
+    *doc.pointer_mut("/m~0n").unwrap() = JsonValue::Bool(true);
+    assert!(doc.pointer("/foo/1").unwrap().is_null());
+    assert_eq!(doc.pointer("/m~0n").unwrap().as_bool(), Some(true));
+    assert!(doc.pointer_mut("/foo/2").is_err());
+    assert!(doc.pointer_mut("/nope").is_err());
+}
@@ -0,0 +1,167 @@
+//! `JsonReader` must produce the same events no matter where the chunk boundaries fall,
+//! including boundaries in the middle of tokens, escapes and multi-byte characters
+use std::io;
+
+use parser_util::json::{
+    ast::JsonAst,
+    number::JsonNumber,
+    reader::{JsonEvent, JsonReader, ReaderError},
+    value::JsonValue,
+};
+
+const DOCUMENT: &str = "{\"名前\": \"東京\\u90fd 😀\", \"esc\": \"\\\"q\\\" \\\\ \\n \\ud83d\\ude00\",\n \"nums\": [0, -12.5e+3, 9007199254740993, 1E400],\n \"lit\": [true, false, null], \"empty\": [{}, []], \"é\": {\"ü\": \"\"}}";
+
+/// hands out at most `step` bytes per `read`, and every other call is `Interrupted`
+struct Trickle<'a> {
+    rest: &'a [u8],
+    step: usize,
+    interrupt: bool,
+}
+
+impl<'a> io::Read for Trickle<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.interrupt = self.interrupt == false;
+        if self.interrupt {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "try again"));
+        }
+        let len = self.step.min(buf.len()).min(self.rest.len());
+        buf[..len].copy_from_slice(&self.rest[..len]);
+        self.rest = &self.rest[len..];
+        Ok(len)
+    }
+}
+
+fn events(reader: JsonReader<impl io::Read>) -> Vec<JsonEvent> {
+    reader.map(|event| event.unwrap()).collect()
+}
+
+fn string(text: &str) -> JsonEvent {
+    JsonEvent::Value(JsonValue::String(text.to_string()))
+}
+
+fn number(lexeme: &str) -> JsonEvent {
+    JsonEvent::Value(JsonValue::Number(JsonNumber::parse(lexeme).unwrap()))
+}
+
+fn key(text: &str) -> JsonEvent {
+    JsonEvent::Key(text.to_string())
+}
+
+#[test]
+fn event_stream() {
+    use JsonEvent::*;
+    let expected = vec![
+        StartObject,
+        key("名前"),
+        string("東京都 😀"),
+        key("esc"),
+        string("\"q\" \\ \n \u{1f600}"),
+        key("nums"),
+        StartArray,
+        number("0"),
+        number("-12.5e+3"),
+        number("9007199254740993"),
+        number("1E400"),
+        EndArray,
+        key("lit"),
+        StartArray,
+        Value(JsonValue::Bool(true)),
+        Value(JsonValue::Bool(false)),
+        Value(JsonValue::Null),
+        EndArray,
+        key("empty"),
+        StartArray,
+        StartObject,
+        EndObject,
+        StartArray,
+        EndArray,
+        EndArray,
+        key("é"),
+        StartObject,
+        key("ü"),
+        string(""),
+        EndObject,
+        EndObject,
+    ];
+    assert_eq!(events(JsonReader::new(DOCUMENT.as_bytes())), expected);
+}
+
+#[test]
+fn every_chunk_size_gives_the_same_events() {
+    let expected = events(JsonReader::new(DOCUMENT.as_bytes()));
+    for chunk_size in 1..=DOCUMENT.len() + 1 {
+        let reader = JsonReader::with_chunk_size(DOCUMENT.as_bytes(), chunk_size);
+        assert_eq!(events(reader), expected, "chunk size {}", chunk_size);
+    }
+}
+
+#[test]
+fn short_and_interrupted_reads() {
+    let expected = events(JsonReader::new(DOCUMENT.as_bytes()));
+    for step in 1..8 {
+        let source = Trickle {
+            rest: DOCUMENT.as_bytes(),
+            step,
+            interrupt: false,
+        };
+        assert_eq!(events(JsonReader::with_chunk_size(source, 5)), expected);
+    }
+}
+
+#[test]
+fn agrees_with_the_tree_parser() {
+    for doc in [
+        "[1, 2",
+        "{\"a\" 1}",
+        "[1,]",
+        "[\"\\x\"]",
+        "01",
+        "[] []",
+        "  42  ",
+    ]
+    .iter()
+    {
+        let mut ast = JsonAst::new();
+        let tree_ok = ast.parse(doc).is_ok();
+        for chunk_size in 1..4 {
+            let reader = JsonReader::with_chunk_size(doc.as_bytes(), chunk_size);
+            let reader_ok = reader.collect::<Result<Vec<_>, _>>().is_ok();
+            assert_eq!(reader_ok, tree_ok, "{:?}", doc);
+        }
+    }
+}
+
+#[test]
+fn errors_point_at_the_same_place_for_every_chunk_size() {
+    let doc = "{\n  \"ключ\": [1, 2 3]\n}";
+    for chunk_size in 1..=doc.len() {
+        let mut reader = JsonReader::with_chunk_size(doc.as_bytes(), chunk_size);
+        let err = loop {
+            match reader.next_event() {
+                Ok(Some(_)) => (),
+                Ok(None) => panic!("accepted a bad document"),
+                Err(err) => break err,
+            }
+        };
+        match err {
+            ReaderError::Syntax(diag) => {
+                assert_eq!((diag.pos.line, diag.pos.column), (2, 17));
+                assert_eq!(diag.found, "number");
+            }
+            err => panic!("unexpected error {}", err),
+        }
+        // the reader is done after an error
+        assert!(reader.next_event().unwrap().is_none());
+    }
+}
+
+#[test]
+fn tracks_depth_and_position() {
+    let mut reader = JsonReader::with_chunk_size("[[\n  1]]".as_bytes(), 2);
+    reader.next_event().unwrap();
+    reader.next_event().unwrap();
+    assert_eq!(reader.depth(), 2);
+    reader.next_event().unwrap();
+    assert_eq!((reader.position().line, reader.position().column), (2, 4));
+    assert_eq!(reader.position().offset, 6);
+}
@@ -0,0 +1,271 @@
+//! The error tolerant lexer and parser: every problem is reported and the tree keeps what was valid
+use parser_util::{
+    json::{
+        ast::{DuplicateKeys, JsonAst, ParseError, ParseOptions},
+        lexer::{Dialect, JsToken, JsonLexer, LexerError},
+        value::JsonValue,
+    },
+    limits::{Limit, Limits},
+};
+
+/// (column, expected, found) of every error along with the compact json of the recovered tree
+fn recover(text: &str, options: &ParseOptions) -> (Vec<(usize, String, String)>, String) {
+    let mut ast = JsonAst::new();
+    let errors = ast.parse_recovering(text, options);
+    let summary = errors
+        .iter()
+        .map(|err| {
+            let diag = err.diagnostic();
+            (diag.pos.column, diag.expected.clone(), diag.found.clone())
+        })
+        .collect();
+    (summary, ast.to_json(text))
+}
+
+fn error(column: usize, expected: &str, found: &str) -> (usize, String, String) {
+    (column, expected.to_string(), found.to_string())
+}
+
+#[test]
+fn valid_input_has_no_errors() {
+    let text = r#"{"a": [1, 2.5, {"b": null}], "c": "é"}"#;
+    let mut ast = JsonAst::new();
+    assert!(ast
+        .parse_recovering(text, &ParseOptions::default())
+        .is_empty());
+    assert_eq!(
+        JsonValue::from_ast(&ast, text),
+        JsonValue::parse(text).unwrap()
+    );
+}
+
+#[test]
+fn independent_errors_are_reported_in_order() {
+    let text = r#"{"a": 1, "b": "bad\q", "c": 01x, "d": [1 2], "e": tru, "f": [3,,4]}"#;
+    let (errors, tree) = recover(text, &ParseOptions::default());
+    assert_eq!(
+        errors,
+        vec![
+            error(19, "a valid escape sequence", "`\\q`"),
+            error(29, "a number", "`01x`"),
+            error(42, "`,` or `]`", "number `2`"),
+            error(51, "a value", "`tru`"),
+            error(64, "a value", "`,`"),
+        ]
+    );
+    // the broken values become `null`, everything around them is kept
+    assert_eq!(
+        tree,
+        r#"{"a":1,"b":null,"c":null,"d":[1,2],"e":null,"f":[3,null,4]}"#
+    );
+
+    // the strict parser stops at the first one
+    let mut ast = JsonAst::new();
+    let err = ast.parse(text).unwrap_err();
+    assert!(matches!(
+        err,
+        ParseError::LexFailed(LexerError::InvalidString(_))
+    ));
+    assert_eq!(err.diagnostic().pos.column, 19);
+
+    // errors from the lexer and the parser are merged by position
+    let (errors, _) = recover("[1 2, @, 3 4]", &ParseOptions::default());
+    let columns: Vec<usize> = errors.iter().map(|err| err.0).collect();
+    assert_eq!(columns, vec![4, 7, 12]);
+}
+
+#[test]
+fn bad_strings_resync() {
+    let options = ParseOptions::default();
+    // a bad escape skips to the closing quote
+    let (errors, tree) = recover(r#"["a\x\"b", "ok", "\u12"]"#, &options);
+    assert_eq!(
+        errors,
+        vec![
+            error(4, "a valid escape sequence", "`\\x`"),
+            error(19, "four hex digits after `\\u`", "`\\u`"),
+        ]
+    );
+    assert_eq!(tree, r#"[null,"ok",null]"#);
+
+    // an unterminated string ends with its line
+    let (errors, tree) = recover("{\"a\": \"open\n, \"b\": 2}", &options);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(tree, r#"{"a":null,"b":2}"#);
+
+    // or with the text
+    let (errors, tree) = recover(r#"[1, "open, 2]"#, &options);
+    assert_eq!(
+        errors,
+        vec![
+            error(5, "`\"` to close the string", "end of input"),
+            error(14, "`,` or `]`", "end of input"),
+        ]
+    );
+    assert_eq!(tree, "[1,null]");
+}
+
+#[test]
+fn bad_numbers_resync() {
+    let (errors, tree) = recover(
+        "[01, -, 1.e5, 2.5.1, 1x, 0x10, -0]",
+        &ParseOptions::default(),
+    );
+    let found: Vec<&str> = errors.iter().map(|err| err.2.as_str()).collect();
+    assert_eq!(
+        found,
+        vec!["`01`", "`-`", "`1.e5`", "`2.5.1`", "`1x`", "`0x10`"]
+    );
+    assert!(errors.iter().all(|err| err.1 == "a number"));
+    assert_eq!(tree, "[null,null,null,null,null,null,-0]");
+
+    // a bad number is one error, not a number followed by junk
+    let mut ast = JsonAst::new();
+    let err = ast.parse("[012]").unwrap_err();
+    assert!(matches!(
+        err,
+        ParseError::LexFailed(LexerError::InvalidNumber(_))
+    ));
+    assert_eq!(err.diagnostic().found, "`012`");
+}
+
+#[test]
+fn missing_commas_and_colons() {
+    let options = ParseOptions::default();
+    let (errors, tree) = recover(r#"{"a": 1 "b": 2, "c" 3, "d": [1 2 3]}"#, &options);
+    assert_eq!(
+        errors,
+        vec![
+            error(9, "`,` or `}`", "string \"b\""),
+            error(21, "`:`", "number `3`"),
+            error(32, "`,` or `]`", "number `2`"),
+            error(34, "`,` or `]`", "number `3`"),
+        ]
+    );
+    assert_eq!(tree, r#"{"a":1,"b":2,"c":3,"d":[1,2,3]}"#);
+
+    // missing values and names get placeholders so properties keep their shape
+    let (errors, tree) = recover(r#"{"a":, : 2, "c", 4: 5}"#, &options);
+    assert_eq!(
+        errors,
+        vec![
+            error(6, "a value", "`,`"),
+            error(8, "a property name", "`:`"),
+            error(16, "`:`", "`,`"),
+            error(18, "a property name", "number `4`"),
+        ]
+    );
+    assert_eq!(tree, r#"{"a":null,"":2,"c":null,"":5}"#);
+}
+
+#[test]
+fn mismatched_brackets_resync() {
+    let options = ParseOptions::default();
+    // the `]` closes the inner array and the object left open inside it
+    let (errors, tree) = recover(r#"[[{"a": 1], 2]"#, &options);
+    assert_eq!(errors, vec![error(10, "`,` or `}`", "`]`")]);
+    assert_eq!(tree, r#"[[{"a":1}],2]"#);
+
+    // a close bracket that matches nothing is skipped
+    let (errors, tree) = recover(r#"{"a": [1, 2]], "b": 3}"#, &options);
+    assert_eq!(errors, vec![error(13, "`,` or `}`", "`]`")]);
+    assert_eq!(tree, r#"{"a":[1,2],"b":3}"#);
+
+    // containers left open at the end are closed
+    let (errors, tree) = recover(r#"{"a": [1, {"b": 2}"#, &options);
+    assert_eq!(errors, vec![error(19, "`,` or `]`", "end of input")]);
+    assert_eq!(tree, r#"{"a":[1,{"b":2}]}"#);
+
+    // only the first thing after the top level value is reported
+    let (errors, tree) = recover(r#"{"a": 1}} {"b": 2}"#, &options);
+    assert_eq!(errors, vec![error(9, "end of input", "`}`")]);
+    assert_eq!(tree, r#"{"a":1}"#);
+}
+
+#[test]
+fn parser_errors_are_collected_too() {
+    let options = ParseOptions {
+        duplicate_keys: DuplicateKeys::Reject,
+        ..ParseOptions::json5()
+    };
+    let mut ast = JsonAst::new();
+    let errors = ast.parse_recovering("{a: 1, a: NaN, b: [nope, 2], 'c\\q': 3}", &options);
+    let kinds: Vec<&str> = errors
+        .iter()
+        .map(|err| match err {
+            ParseError::DuplicateKey { .. } => "duplicate",
+            ParseError::InvalidToken(_) => "token",
+            ParseError::LexFailed(_) => "lexer",
+            _ => "other",
+        })
+        .collect();
+    assert_eq!(kinds, vec!["duplicate", "token", "token"]);
+    assert_eq!(
+        ast.to_json("{a: 1, a: NaN, b: [nope, 2], 'c\\q': 3}"),
+        r#"{"a":1,"a":null,"b":[null,2],"cq":3}"#
+    );
+}
+
+#[test]
+fn lexer_recovery() {
+    let text = r#"["a\x", 01, @, "ok", tru, "b"#;
+    let mut lexer = JsonLexer::new();
+    let errors = lexer.lex_recovering(text, Dialect::Strict, &Limits::default());
+    let offsets: Vec<usize> = errors
+        .iter()
+        .map(|err| err.diagnostic().pos.offset)
+        .collect();
+    assert_eq!(offsets, vec![3, 8, 12, 21, 26]);
+    assert!(matches!(errors[0], LexerError::InvalidString(_)));
+    assert!(matches!(errors[1], LexerError::InvalidNumber(_)));
+    assert!(matches!(errors[2], LexerError::InvalidIdentifier(_)));
+
+    // every bad token leaves an `Error` in its place, the good ones are still there
+    let kinds: Vec<&str> = lexer
+        .get_tok_stream()
+        .iter()
+        .map(|tok| match tok {
+            JsToken::Error { .. } => "error",
+            JsToken::String { .. } => "string",
+            JsToken::Open(_) => "open",
+            JsToken::Comma => "comma",
+            _ => "other",
+        })
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            "open", "error", "comma", "error", "comma", "error", "comma", "string", "comma",
+            "error", "comma", "error"
+        ]
+    );
+    let error_text: Vec<&str> = lexer
+        .get_tok_stream()
+        .iter()
+        .filter_map(|tok| match *tok {
+            JsToken::Error { lbound, ubound } => Some(&text[lbound as usize..ubound as usize]),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(error_text, vec!["\"a\\x\"", "01", "@", "tru", "\"b"]);
+
+    // an unterminated comment swallows the rest
+    let errors = lexer.lex_recovering("[1, /* 2, @]", Dialect::Jsonc, &Limits::default());
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(errors[0], LexerError::InvalidComment(_)));
+
+    // a limit isn't recovered from
+    let limits = Limits {
+        max_string_len: 2,
+        ..Limits::default()
+    };
+    let errors = lexer.lex_recovering(r#"[@, "long", @]"#, Dialect::Strict, &limits);
+    assert_eq!(errors.len(), 2);
+    assert!(matches!(
+        errors[1],
+        LexerError::LimitExceeded {
+            limit: Limit::StringLength,
+            ..
+        }
+    ));
+}
@@ -0,0 +1,92 @@
+//! parse -> serialize -> parse must always give back an identical tree
+use parser_util::json::{ast::JsonAst, value::JsonValue};
+
+const DOCUMENTS: &[&str] = &[
+    "null",
+    "true",
+    "-12.5e+3",
+    "\"lonely string\"",
+    "[]",
+    "{}",
+    "[[], {}, [[]], {\"\": {}}]",
+    "[1, 2.0, -0, 1e400, 9007199254740993, 0.000001]",
+    "{\"a\":{\"b\":[1,{\"c\":[[],{}]}]},\"d\":null, \"e\": false}",
+    "[\"\\\"quoted\\\"\", \"back\\\\slash\", \"\\/\", \"\\b\\f\\n\\r\\t\", \"\\u0000\\u001f\"]",
+    "[\"\\u0041\\u00e9\\u4e2d\\ud83d\\ude00\", \"\u{e9}\u{4e2d}\u{1f600}\"]",
+    "{\"dup\": 1, \"dup\": 2}",
+    "{\n  \"servers\": [\n    {\"host\": \"a.example\", \"port\": 8080},\n    {\"host\": \"b.example\", \"port\": 8081}\n  ]\n}",
+];
+
+fn parse(text: &String) -> (JsonAst, JsonValue) {
+    let mut ast = JsonAst::new();
+    if let Err(err) = ast.parse(text) {
+        panic!("failed to parse {:?}\n{}", text, err.render(text));
+    }
+    let value = JsonValue::from_ast(&ast, text);
+    (ast, value)
+}
+
+fn assert_round_trip(serialize: impl Fn(&JsonAst, &String) -> String) {
+    for &doc in DOCUMENTS {
+        let original_text = doc.to_string();
+        let (original_ast, original_value) = parse(&original_text);
+
+        let serialized = serialize(&original_ast, &original_text);
+        let (reparsed_ast, reparsed_value) = parse(&serialized);
+
+        assert_eq!(original_value, reparsed_value, "tree changed for {:?}", doc);
+        // serializing again must be stable
+        assert_eq!(serialized, serialize(&reparsed_ast, &serialized));
+    }
+}
+
+#[test]
+fn compact_round_trip() {
+    assert_round_trip(|ast, text| ast.to_json(text));
+}
+
+#[test]
+fn pretty_round_trip() {
+    for indent in 0..5 {
+        assert_round_trip(|ast, text| ast.to_json_pretty(text, indent));
+    }
+}
+
+#[test]
+fn value_round_trip() {
+    for &doc in DOCUMENTS {
+        let (_, value) = parse(&doc.to_string());
+        assert_eq!(value, parse(&value.to_json()).1);
+        assert_eq!(value, parse(&value.to_json_pretty(2)).1);
+    }
+}
+
+#[test]
+fn compact_output() {
+    let text = String::from("{ \"a\" : [ 1 , 2.50 , \"x\\u0041\\/\" ] , \"b\" : { } }");
+    let (ast, _) = parse(&text);
+    assert_eq!(ast.to_json(&text), "{\"a\":[1,2.50,\"xA/\"],\"b\":{}}");
+}
+
+#[test]
+fn pretty_output() {
+    let text = String::from("{\"a\":[1,[]],\"b\":{\"c\":null}}");
+    let (ast, _) = parse(&text);
+    let expected = "{\n  \"a\": [\n    1,\n    []\n  ],\n  \"b\": {\n    \"c\": null\n  }\n}";
+    assert_eq!(ast.to_json_pretty(&text, 2), expected);
+}
+
+#[test]
+fn escapes_control_characters() {
+    let value = JsonValue::String(String::from("tab\there \"quote\" \\ \u{1}"));
+    assert_eq!(value.to_json(), "\"tab\\there \\\"quote\\\" \\\\ \\u0001\"");
+}
+
+#[test]
+fn writes_to_io_write() {
+    let text = String::from("[true,false,null]");
+    let (ast, _) = parse(&text);
+    let mut out: Vec<u8> = Vec::new();
+    ast.write_json(&text, &mut out).unwrap();
+    assert_eq!(out, text.as_bytes());
+}
@@ -0,0 +1,395 @@
+//! The JSON Schema validator, every violation is checked along with where it points in the
+//! instance and in the schema
+use parser_util::json::{
+    schema::{JsonSchema, SchemaError},
+    value::JsonValue,
+};
+
+fn parse(text: &str) -> JsonValue {
+    JsonValue::parse(text).unwrap()
+}
+
+fn schema(text: &str) -> JsonSchema {
+    JsonSchema::new(parse(text)).unwrap_or_else(|err| panic!("{}: {}", text, err))
+}
+
+/// (instance path, schema path) of every violation, in the order they were reported
+fn violations(schema_text: &str, instance: &str) -> Vec<(String, String)> {
+    match schema(schema_text).validate(&parse(instance)) {
+        Ok(()) => Vec::new(),
+        Err(violations) => violations
+            .into_iter()
+            .map(|v| (v.instance_path, v.schema_path))
+            .collect(),
+    }
+}
+
+fn at(instance_path: &str, schema_path: &str) -> (String, String) {
+    (instance_path.to_string(), schema_path.to_string())
+}
+
+/// `valid` and `invalid` are instances that must pass and fail `schema_text`
+fn check(schema_text: &str, valid: &[&str], invalid: &[&str]) {
+    let compiled = schema(schema_text);
+    for instance in valid.iter() {
+        assert!(
+            compiled.is_valid(&parse(instance)),
+            "{} should accept {}",
+            schema_text,
+            instance
+        );
+    }
+    for instance in invalid.iter() {
+        assert!(
+            compiled.is_valid(&parse(instance)) == false,
+            "{} should reject {}",
+            schema_text,
+            instance
+        );
+    }
+}
+
+#[test]
+fn types() {
+    check(
+        r#"{"type": "integer"}"#,
+        &["1", "-0", "1.0", "10e2"],
+        &["1.5", "\"1\"", "null"],
+    );
+    check(
+        r#"{"type": "number"}"#,
+        &["1", "1.5", "1e400"],
+        &["true", "[]"],
+    );
+    check(
+        r#"{"type": ["string", "null"]}"#,
+        &["\"a\"", "null"],
+        &["0", "{}"],
+    );
+    check(r#"{"type": "object"}"#, &["{}"], &["[]"]);
+    check("true", &["1", "null", "{}"], &[]);
+    check("false", &[], &["1", "null", "{}"]);
+
+    let err = schema(r#"{"type": "integer"}"#)
+        .validate(&parse("1.5"))
+        .unwrap_err();
+    assert_eq!(err[0].message, "expected type \"integer\", found number");
+    assert_eq!(
+        err[0].to_string(),
+        "\"\": expected type \"integer\", found number (schema keyword \"/type\")"
+    );
+}
+
+#[test]
+fn required_enum_and_const() {
+    let schema_text = r#"{"required": ["a", "b", "c"]}"#;
+    assert_eq!(
+        violations(schema_text, r#"{"a": 1}"#),
+        vec![at("", "/required"), at("", "/required")]
+    );
+    let err = schema(schema_text)
+        .validate(&parse(r#"{"a": 1, "b": 2}"#))
+        .unwrap_err();
+    assert_eq!(err[0].message, "missing required property \"c\"");
+    // only objects can be missing properties
+    check(schema_text, &["[]", "1"], &[]);
+
+    check(
+        r#"{"enum": [1, "a", [1, 2], {"x": null}]}"#,
+        &["1", "1.0", "\"a\"", "[1, 2.0]", "{\"x\": null}"],
+        &["2", "\"A\"", "[2, 1]", "{\"x\": 0}", "null"],
+    );
+    check(
+        r#"{"const": {"a": [1], "b": 2}}"#,
+        &[r#"{"b": 2, "a": [1.0]}"#],
+        &[r#"{"a": [1]}"#, r#"{"a": [1], "b": 2, "c": 3}"#],
+    );
+    // numbers compare exactly, even the ones an f64 can't tell apart
+    check(
+        r#"{"const": 9007199254740993}"#,
+        &["9007199254740993", "9007199254740993.0"],
+        &["9007199254740992"],
+    );
+}
+
+#[test]
+fn numeric_bounds() {
+    check(
+        r#"{"minimum": 1, "maximum": 9007199254740992}"#,
+        &["1", "1.0", "9007199254740992", "\"strings are ignored\""],
+        &["0.999", "9007199254740993", "1e400"],
+    );
+    check(
+        r#"{"exclusiveMinimum": 0, "exclusiveMaximum": 1e400}"#,
+        &["1e-400", "9e399"],
+        &["0", "-0", "1e400"],
+    );
+    check(r#"{"multipleOf": 0.5}"#, &["1", "1.5", "-2.0"], &["1.25"]);
+    check(r#"{"multipleOf": 3}"#, &["9", "0"], &["10"]);
+    // checked exactly, operands an f64 can't hold or tell apart included
+    check(
+        r#"{"multipleOf": 3}"#,
+        &["3e30", "123456789012345678901234567890", "-9e400"],
+        &["1e20", "1e400", "123456789012345678901234567891", "4.5"],
+    );
+    check(
+        r#"{"multipleOf": 0.1}"#,
+        &["0.3", "1e400", "-2.5"],
+        &["1.0000000001", "0.30000000000000004", "1e-400"],
+    );
+    check(
+        r#"{"multipleOf": 1e-31}"#,
+        &["1e-30", "3e-31", "0.5"],
+        &["1e-32", "1.5e-31"],
+    );
+    check(
+        r#"{"multipleOf": 2.5e300}"#,
+        &["5e300", "7.5e400", "0"],
+        &["1e300", "2.5e299", "1"],
+    );
+    // even divisors that round to 0 as a float
+    check(r#"{"multipleOf": 1e-400}"#, &["1", "3e-400"], &["1e-401"]);
+    // a divisor with more digits than can be checked isn't silently accepted
+    let err = schema(r#"{"multipleOf": 1.2345678901234567890123}"#)
+        .validate(&parse("2.4691357802469135780246"))
+        .unwrap_err();
+    assert_eq!(
+        err[0].message,
+        "can't be checked against multipleOf 1.2345678901234567890123, it has too many digits"
+    );
+
+    let err = schema(r#"{"maximum": 3}"#)
+        .validate(&parse("4"))
+        .unwrap_err();
+    assert_eq!(err[0].message, "must be <= 3");
+}
+
+#[test]
+fn lengths_and_counts() {
+    // lengths count code points, not bytes
+    check(
+        r#"{"minLength": 2, "maxLength": 2}"#,
+        &["\"é😀\"", "\"ab\""],
+        &["\"a\"", "\"abc\""],
+    );
+    check(
+        r#"{"minItems": 1, "maxItems": 2}"#,
+        &["[1]", "[1, 2]"],
+        &["[]", "[1, 2, 3]"],
+    );
+    check(
+        r#"{"minProperties": 1, "maxProperties": 1}"#,
+        &["{\"a\": 1}"],
+        &["{}", "{\"a\": 1, \"b\": 2}"],
+    );
+    check(
+        r#"{"uniqueItems": true}"#,
+        &["[1, \"1\", [1]]", "[]"],
+        &["[1, 1.0]", r#"[{"a": 1, "b": 2}, {"b": 2, "a": 1}]"#],
+    );
+    check(
+        r#"{"contains": {"type": "string"}, "minContains": 2, "maxContains": 3}"#,
+        &[r#"["a", 1, "b"]"#],
+        &[r#"["a", 1]"#, r#"["a", "b", "c", "d"]"#],
+    );
+}
+
+#[cfg(feature = "extras")]
+#[test]
+fn patterns() {
+    // patterns aren't anchored
+    check(r#"{"pattern": "b+"}"#, &["\"abbc\"", "1"], &["\"ac\""]);
+    check(r#"{"pattern": "^a+$"}"#, &["\"aaa\""], &["\"aab\""]);
+    assert_eq!(
+        violations(
+            r#"{"patternProperties": {"^x-": {"type": "string"}}, "additionalProperties": false}"#,
+            r#"{"x-a": "ok", "x-b": 1, "y": "no"}"#,
+        ),
+        vec![
+            at("/x-b", "/patternProperties/^x-/type"),
+            at("/y", "/additionalProperties"),
+        ]
+    );
+}
+
+#[cfg(feature = "extras")]
+#[test]
+fn patterns_reached_through_ref() {
+    // `foo` isn't a keyword, so its pattern is only reachable through the `$ref`
+    let schema_text = r##"{"$ref": "#/foo", "foo": {"pattern": "a+"}}"##;
+    check(schema_text, &["\"xaay\""], &["\"b\""]);
+    assert_eq!(
+        violations(schema_text, "\"b\""),
+        vec![at("", "/$ref/pattern")]
+    );
+
+    let schema_text = r##"{
+        "properties": {"p": {"$ref": "#/defs/names"}},
+        "defs": {"names": {"patternProperties": {"^[a-z]+$": {"type": "integer"}}}}
+    }"##;
+    assert_eq!(
+        violations(schema_text, r#"{"p": {"abc": 1, "def": "x", "G": "y"}}"#),
+        vec![at(
+            "/p/def",
+            "/properties/p/$ref/patternProperties/^[a-z]+$/type"
+        )]
+    );
+
+    // whatever a `$ref` points to gets checked like any other subschema
+    assert_eq!(
+        JsonSchema::new(parse(r##"{"$ref": "#/foo", "foo": {"pattern": "("}}"##))
+            .err()
+            .map(|err| err.pointer),
+        Some(String::from("/foo/pattern"))
+    );
+}
+
+#[test]
+fn applicators() {
+    let schema_text = r#"{"allOf": [{"minimum": 0}, {"maximum": 10}]}"#;
+    assert_eq!(
+        violations(schema_text, "11"),
+        vec![at("", "/allOf/1/maximum")]
+    );
+    assert_eq!(
+        violations(schema_text, "-1"),
+        vec![at("", "/allOf/0/minimum")]
+    );
+
+    let schema_text = r#"{"anyOf": [{"type": "string"}, {"type": "null"}]}"#;
+    check(schema_text, &["\"a\"", "null"], &["1"]);
+    assert_eq!(violations(schema_text, "1"), vec![at("", "/anyOf")]);
+
+    let schema_text = r#"{"oneOf": [{"type": "integer"}, {"minimum": 0}]}"#;
+    check(schema_text, &["-1", "0.5"], &["5", "-0.5"]);
+    let err = schema(schema_text).validate(&parse("5")).unwrap_err();
+    assert_eq!(err[0].schema_path, "/oneOf");
+    assert_eq!(err[0].message, "must match exactly one schema, matched 2");
+
+    check(r#"{"not": {"type": "string"}}"#, &["1"], &["\"a\""]);
+    let schema_text =
+        r#"{"if": {"type": "string"}, "then": {"minLength": 2}, "else": {"minimum": 5}}"#;
+    check(schema_text, &["\"ab\"", "5"], &["\"a\"", "4"]);
+    assert_eq!(
+        violations(schema_text, "\"a\""),
+        vec![at("", "/then/minLength")]
+    );
+    assert_eq!(violations(schema_text, "4"), vec![at("", "/else/minimum")]);
+
+    let schema_text = r#"{"prefixItems": [{"type": "string"}], "items": {"type": "integer"}}"#;
+    assert_eq!(
+        violations(schema_text, r#"["a", 1, "b", 2, 2.5]"#),
+        vec![at("/2", "/items/type"), at("/4", "/items/type")]
+    );
+    assert_eq!(
+        violations(schema_text, "[1]"),
+        vec![at("/0", "/prefixItems/0/type")]
+    );
+    assert_eq!(
+        violations(
+            r#"{"propertyNames": {"maxLength": 1}}"#,
+            r#"{"a": 1, "bc": 2}"#
+        ),
+        vec![at("/bc", "/propertyNames/maxLength")]
+    );
+}
+
+#[test]
+fn refs_and_pointers() {
+    let schema_text = r##"{
+        "$defs": {"positive": {"exclusiveMinimum": 0}},
+        "properties": {
+            "a": {"$ref": "#/$defs/positive"},
+            "list": {"items": {"$ref": "#/$defs/positive"}},
+            "x/y~": {"type": "string"}
+        }
+    }"##;
+    assert_eq!(
+        violations(
+            schema_text,
+            r#"{"a": -1, "list": [1, 0, 2, -3], "x/y~": 1}"#
+        ),
+        vec![
+            at("/a", "/properties/a/$ref/exclusiveMinimum"),
+            at("/list/1", "/properties/list/items/$ref/exclusiveMinimum"),
+            at("/list/3", "/properties/list/items/$ref/exclusiveMinimum"),
+            at("/x~1y~0", "/properties/x~1y~0/type"),
+        ]
+    );
+
+    // anchors and percent-encoded fragments
+    check(
+        r##"{"$defs": {"s": {"$anchor": "str", "type": "string"}}, "items": {"$ref": "#str"}}"##,
+        &["[\"a\"]"],
+        &["[1]"],
+    );
+    check(
+        r##"{"$defs": {"a b": {"type": "null"}}, "$ref": "#/$defs/a%20b"}"##,
+        &["null"],
+        &["0"],
+    );
+
+    // recursion through the root
+    let tree = r##"{"required": ["name"], "properties": {"child": {"$ref": "#"}}}"##;
+    assert_eq!(
+        violations(
+            tree,
+            r#"{"name": "a", "child": {"name": "b", "child": {}}}"#
+        ),
+        vec![at(
+            "/child/child",
+            "/properties/child/$ref/properties/child/$ref/required"
+        )]
+    );
+    // a `$ref` cycle that never reaches the instance stops instead of overflowing the stack
+    assert!(schema(r##"{"$ref": "#"}"##).is_valid(&parse("1")) == false);
+}
+
+#[test]
+fn every_violation_is_reported() {
+    let schema_text = r#"{
+        "type": "object",
+        "required": ["a"],
+        "minProperties": 3,
+        "properties": {"b": {"type": "string", "minLength": 2}}
+    }"#;
+    assert_eq!(
+        violations(schema_text, r#"{"b": 1}"#),
+        vec![
+            at("", "/required"),
+            at("", "/minProperties"),
+            at("/b", "/properties/b/type"),
+        ]
+    );
+}
+
+#[test]
+fn schema_errors() {
+    let cases = [
+        (r#"{"type": "strung"}"#, "/type"),
+        (r#"{"minimum": "1"}"#, "/minimum"),
+        (r#"{"multipleOf": 0}"#, "/multipleOf"),
+        (r#"{"multipleOf": -1e-400}"#, "/multipleOf"),
+        (r#"{"minLength": -1}"#, "/minLength"),
+        (r#"{"required": [1]}"#, "/required"),
+        (r##"{"$ref": "#/nope"}"##, "/$ref"),
+        (r#"{"$ref": "other.json"}"#, "/$ref"),
+        (r#"{"allOf": []}"#, "/allOf"),
+        (r#"{"properties": {"a": 3}}"#, "/properties/a"),
+        (r#"{"items": {"not": {"type": 1}}}"#, "/items/not/type"),
+        (
+            r##"{"$ref": "#/foo", "foo": {"minimum": "x"}}"##,
+            "/foo/minimum",
+        ),
+        (r##"{"$ref": "#/foo", "foo": 1}"##, "/foo"),
+        ("1", ""),
+    ];
+    for (schema_text, pointer) in cases.iter() {
+        match JsonSchema::new(parse(schema_text)) {
+            Err(SchemaError { pointer: found, .. }) => {
+                assert_eq!(found, *pointer, "{}", schema_text)
+            }
+            Ok(_) => panic!("{} should be rejected", schema_text),
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! Round trips between rust types and json through the serde integration
+#![cfg(feature = "serde")]
+use std::collections::BTreeMap;
+
+use parser_util::json::{self, number::JsonNumber, value::JsonValue};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+enum Shape {
+    Point,
+    Circle(f64),
+    Line(i32, i32),
+    Rect { w: u32, h: u32 },
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Document {
+    id: u64,
+    name: String,
+    ratio: f32,
+    tags: Vec<String>,
+    owner: Option<String>,
+    parent: Option<u32>,
+    shapes: Vec<Shape>,
+    counts: BTreeMap<String, i64>,
+    pair: (char, bool),
+    extra: JsonValue,
+}
+
+fn document() -> Document {
+    let mut counts = BTreeMap::new();
+    counts.insert(String::from("a"), -1);
+    counts.insert(String::from("b"), i64::MAX);
+    Document {
+        id: 7,
+        name: String::from("ünïcode \"quoted\""),
+        ratio: 0.1,
+        tags: vec![String::from("x"), String::new()],
+        owner: Some(String::from("me")),
+        parent: None,
+        shapes: vec![
+            Shape::Point,
+            Shape::Circle(2.5),
+            Shape::Line(-1, 1),
+            Shape::Rect { w: 3, h: 4 },
+        ],
+        counts,
+        pair: ('é', true),
+        extra: JsonValue::parse(r#"{"k": [1, -2, 2.5, "x", null]}"#).unwrap(),
+    }
+}
+
+#[test]
+fn structs_round_trip() {
+    let original = document();
+    let text = json::to_string(&original).unwrap();
+    assert_eq!(json::from_str::<Document>(&text).unwrap(), original);
+
+    let pretty = json::to_string_pretty(&original, 2).unwrap();
+    assert_eq!(json::from_str::<Document>(&pretty).unwrap(), original);
+
+    let value = json::to_value(&original).unwrap();
+    assert_eq!(json::from_value::<Document>(&value).unwrap(), original);
+}
+
+#[test]
+fn shape_of_the_output() {
+    let value = json::to_value(&document()).unwrap();
+    assert_eq!(value.get("parent"), Some(&JsonValue::Null));
+    assert_eq!(
+        value.get("ratio").unwrap().as_number().unwrap().as_str(),
+        "0.1"
+    );
+    // enums are externally tagged
+    assert_eq!(
+        value.get("shapes").unwrap(),
+        &JsonValue::parse(
+            r#"["Point", {"Circle": 2.5}, {"Line": [-1, 1]}, {"Rect": {"w": 3, "h": 4}}]"#
+        )
+        .unwrap()
+    );
+    assert_eq!(
+        value.get("pair").unwrap(),
+        &JsonValue::parse(r#"["é", true]"#).unwrap()
+    );
+}
+
+#[test]
+fn options_and_maps() {
+    assert_eq!(json::to_string(&Some(1)).unwrap(), "1");
+    assert_eq!(json::to_string(&None::<i32>).unwrap(), "null");
+    assert_eq!(json::from_str::<Option<i32>>("null").unwrap(), None);
+    assert_eq!(json::from_str::<Option<i32>>("5").unwrap(), Some(5));
+
+    // non-string keys are written as text and read back from it
+    let mut map = BTreeMap::new();
+    map.insert(1u32, String::from("one"));
+    map.insert(20, String::from("twenty"));
+    let text = json::to_string(&map).unwrap();
+    assert_eq!(text, r#"{"1":"one","20":"twenty"}"#);
+    assert_eq!(json::from_str::<BTreeMap<u32, String>>(&text).unwrap(), map);
+}
+
+#[test]
+fn big_numbers() {
+    for &val in [i128::MIN, i128::MAX, -1, 0].iter() {
+        let text = json::to_string(&val).unwrap();
+        assert_eq!(text, val.to_string());
+        assert_eq!(json::from_str::<i128>(&text).unwrap(), val);
+    }
+    let text = json::to_string(&u128::MAX).unwrap();
+    assert_eq!(json::from_str::<u128>(&text).unwrap(), u128::MAX);
+    assert!(json::from_str::<u64>(&text).is_err());
+    assert!(json::from_str::<i128>("1.5").is_err());
+
+    assert!(json::to_string(&f64::NAN).is_err());
+    assert!(json::to_string(&f64::INFINITY).is_err());
+}
+
+#[test]
+fn json_values_keep_their_lexemes() {
+    let text = r#"[1.10, 1e2, 1E400, -1e400, -0, 0.1, 18446744073709551616, 123456789012345678901234567890, -170141183460469231731687303715884105729, 5e-324]"#;
+    let value = JsonValue::parse(text).unwrap();
+    assert_eq!(json::to_value(&value).unwrap(), value);
+    assert_eq!(json::to_string(&value).unwrap(), text.replace(", ", ","));
+
+    // reading a `JsonValue` back doesn't round anything either
+    assert_eq!(json::from_str::<JsonValue>(text).unwrap(), value);
+    assert_eq!(json::from_value::<JsonValue>(&value).unwrap(), value);
+    let mut original = document();
+    original.extra = value.clone();
+    let round_trip = json::from_str::<Document>(&json::to_string(&original).unwrap()).unwrap();
+    assert_eq!(round_trip.extra, value);
+    assert_eq!(
+        json::from_value::<Document>(&json::to_value(&original).unwrap()).unwrap(),
+        original
+    );
+
+    // typed fields still get the closest primitive, and strings that look like numbers stay strings
+    assert_eq!(
+        json::from_str::<Vec<f64>>("[1.10, 1e2]").unwrap(),
+        vec![1.1, 100.0]
+    );
+    assert_eq!(json::from_str::<Option<f32>>("0.50").unwrap(), Some(0.5));
+    assert_eq!(
+        json::from_str::<JsonValue>(r#""1.10""#).unwrap(),
+        JsonValue::String(String::from("1.10"))
+    );
+
+    // the lexeme survives when the value is nested in something else too
+    let mut wrapper = BTreeMap::new();
+    wrapper.insert("n", JsonValue::Number(JsonNumber::parse("1.50").unwrap()));
+    assert_eq!(json::to_string(&wrapper).unwrap(), r#"{"n":1.50}"#);
+}
+
+#[test]
+fn deserialize_errors() {
+    #[derive(Deserialize, Debug)]
+    struct Small {
+        #[allow(dead_code)]
+        a: u8,
+    }
+    assert!(json::from_str::<Small>(r#"{"a": 300}"#).is_err());
+    assert!(json::from_str::<Small>(r#"{"b": 3}"#).is_err());
+    assert!(matches!(
+        json::from_str::<Small>(r#"{"a": 3"#),
+        Err(json::SerdeError::Parse(_))
+    ));
+    assert!(json::from_str::<Shape>(r#"{"Circle": 1, "Point": null}"#).is_err());
+    assert!(json::from_str::<Shape>(r#""Hexagon""#).is_err());
+}
@@ -0,0 +1,128 @@
+//! Spans handed out by the lexer are byte offsets, they must stay valid no matter
+//! how many multi-byte characters come before them
+use parser_util::json::{
+    ast::JsonAst,
+    lexer::{JsToken, JsonLexer},
+    value::JsonValue,
+};
+
+const CJK: &str = "{\"名前\": \"東京都\", \"数\": [1, 2.5], \"キー\": {\"内側\": \"値\"}}";
+const EMOJI: &str = "[\"😀🎉\", {\"🔑\": \"🚀 launch\"}, 42, \"👩‍👩‍👧\"]";
+const COMBINING: &str =
+    "{\"e\u{301}cole\": \"cafe\u{301}\", \"n\u{303}\": [\"a\u{30a}\u{301}\", -7]}";
+
+fn lex(text: &str) -> Vec<JsToken> {
+    let mut lexer = JsonLexer::new();
+    assert!(lexer.lex(text).is_ok());
+    lexer.get_tok_stream().clone()
+}
+
+#[test]
+fn string_spans_slice_on_char_boundaries() {
+    for &text in &[CJK, EMOJI, COMBINING] {
+        for tok in lex(text) {
+            match tok {
+                JsToken::String { lbound, ubound } => {
+                    assert_eq!(&text[lbound as usize..lbound as usize + 1], "\"");
+                    assert_eq!(&text[ubound as usize..ubound as usize + 1], "\"");
+                }
+                JsToken::Number { lbound, ubound } => {
+                    let lexeme = &text[lbound as usize..ubound as usize];
+                    assert!(
+                        lexeme.parse::<f64>().is_ok(),
+                        "bad number span {:?}",
+                        lexeme
+                    );
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+#[test]
+fn token_offsets_point_at_tokens() {
+    let mut lexer = JsonLexer::new();
+    lexer.lex(EMOJI).ok().unwrap();
+    for (tok, &offset) in lexer.get_tok_stream().iter().zip(lexer.get_tok_offsets()) {
+        let first = EMOJI[offset..].chars().next().unwrap();
+        match tok {
+            JsToken::Open(c) | JsToken::Close(c) => assert_eq!(first, *c),
+            JsToken::Comma => assert_eq!(first, ','),
+            JsToken::String { .. } => assert_eq!(first, '"'),
+            JsToken::Number { .. } => assert!(first.is_ascii_digit()),
+            _ => (),
+        }
+    }
+}
+
+#[test]
+fn decodes_cjk() {
+    let value = JsonValue::parse(CJK).ok().unwrap();
+    assert_eq!(value.get("名前").and_then(|v| v.as_str()), Some("東京都"));
+    assert_eq!(
+        value.get("数").unwrap().as_array().unwrap()[1].as_f64(),
+        Some(2.5)
+    );
+    let inner = value.get("キー").unwrap();
+    assert_eq!(inner.get("内側").and_then(|v| v.as_str()), Some("値"));
+}
+
+#[test]
+fn decodes_emoji() {
+    let value = JsonValue::parse(EMOJI).ok().unwrap();
+    let items = value.as_array().unwrap();
+    assert_eq!(items[0].as_str(), Some("😀🎉"));
+    assert_eq!(
+        items[1].get("🔑").and_then(|v| v.as_str()),
+        Some("🚀 launch")
+    );
+    assert_eq!(items[2].as_i64(), Some(42));
+    assert_eq!(items[3].as_str(), Some("👩‍👩‍👧"));
+}
+
+#[test]
+fn decodes_combining_marks() {
+    let value = JsonValue::parse(COMBINING).ok().unwrap();
+    assert_eq!(
+        value.get("e\u{301}cole").and_then(|v| v.as_str()),
+        Some("cafe\u{301}")
+    );
+    let list = value.get("n\u{303}").unwrap().as_array().unwrap();
+    assert_eq!(list[0].as_str(), Some("a\u{30a}\u{301}"));
+    assert_eq!(list[1].as_i64(), Some(-7));
+}
+
+#[test]
+fn escaped_and_raw_text_agree() {
+    let escaped = JsonValue::parse("[\"\\u6771\\u4eac\", \"\\ud83d\\ude00\", \"e\\u0301\"]")
+        .ok()
+        .unwrap();
+    let raw = JsonValue::parse("[\"東京\", \"😀\", \"e\u{301}\"]")
+        .ok()
+        .unwrap();
+    assert_eq!(escaped, raw);
+}
+
+#[test]
+fn serializes_multibyte_text() {
+    for &text in &[CJK, EMOJI, COMBINING] {
+        let mut ast = JsonAst::new();
+        ast.parse(text).ok().unwrap();
+        let compact = ast.to_json(text);
+        assert_eq!(
+            JsonValue::parse(&compact).ok().unwrap(),
+            JsonValue::parse(text).ok().unwrap()
+        );
+    }
+}
+
+#[test]
+fn error_columns_count_chars() {
+    let text = "{\"東京\": \"😀\" \"x\"}";
+    let mut ast = JsonAst::new();
+    let err = ast.parse(text).err().unwrap();
+    let pos = err.diagnostic().pos;
+    assert_eq!(&text[pos.offset..pos.offset + 3], "\"x\"");
+    assert_eq!((pos.line, pos.column), (1, 12));
+}
@@ -0,0 +1,128 @@
+//! `JsonValue` accessors and the conversions between `JsonValue` and `JsonAst`
+use parser_util::json::{ast::JsonAst, number::JsonNumber, value::JsonValue};
+
+const DOCUMENT: &str = r#"{
+    "name": "server \"one\"",
+    "port": 8080,
+    "ratio": 0.25,
+    "enabled": true,
+    "backup": null,
+    "tags": ["a", "b"],
+    "nested": {"deep": [1, {"x": -3}]},
+    "dup": 1,
+    "dup": 2
+}"#;
+
+fn parse(text: &str) -> JsonValue {
+    JsonValue::parse(text).unwrap()
+}
+
+#[test]
+fn typed_accessors() {
+    let value = parse(DOCUMENT);
+    assert_eq!(value.get("name").unwrap().as_str(), Some("server \"one\""));
+    assert_eq!(value.get("port").unwrap().as_i64(), Some(8080));
+    assert_eq!(value.get("port").unwrap().as_u64(), Some(8080));
+    assert_eq!(value.get("ratio").unwrap().as_f64(), Some(0.25));
+    assert_eq!(value.get("enabled").unwrap().as_bool(), Some(true));
+    assert!(value.get("backup").unwrap().is_null());
+    assert_eq!(value.get("tags").unwrap().as_array().unwrap().len(), 2);
+    assert_eq!(value.as_object().unwrap().len(), 9);
+
+    let deep = value.get("nested").unwrap().get("deep").unwrap();
+    assert_eq!(
+        deep.as_array().unwrap()[1].get("x").unwrap().as_i64(),
+        Some(-3)
+    );
+    assert_eq!(
+        deep.as_array().unwrap()[1].get("x").unwrap().as_number(),
+        Some(&JsonNumber::from(-3))
+    );
+}
+
+#[test]
+fn accessors_on_the_wrong_type() {
+    let value = parse(DOCUMENT);
+    let name = value.get("name").unwrap();
+    assert_eq!(name.as_i64(), None);
+    assert_eq!(name.as_bool(), None);
+    assert_eq!(name.as_array(), None);
+    assert_eq!(name.as_object(), None);
+    assert_eq!(name.get("anything"), None);
+    assert_eq!(name.get_all("anything").count(), 0);
+    assert!(name.is_null() == false);
+    assert_eq!(value.get("port").unwrap().as_str(), None);
+    // fractions and negatives don't fit in integer accessors
+    assert_eq!(value.get("ratio").unwrap().as_i64(), None);
+    assert_eq!(parse("-1").as_u64(), None);
+    assert_eq!(value.get("missing"), None);
+}
+
+#[test]
+fn duplicate_keys() {
+    let value = parse(DOCUMENT);
+    assert_eq!(value.get("dup").unwrap().as_i64(), Some(1));
+    let all: Vec<i64> = value.get_all("dup").map(|v| v.as_i64().unwrap()).collect();
+    assert_eq!(all, vec![1, 2]);
+}
+
+#[test]
+fn get_mut_edits_in_place() {
+    let mut value = parse(DOCUMENT);
+    *value.get_mut("port").unwrap() = JsonValue::Number(JsonNumber::from(9090));
+    *value.get_mut("dup").unwrap() = JsonValue::Null;
+    assert_eq!(value.get("port").unwrap().as_i64(), Some(9090));
+    // only the first duplicate is touched
+    assert_eq!(value.get_all("dup").filter(|v| v.is_null()).count(), 1);
+    assert!(value.get_mut("missing").is_none());
+    assert!(JsonValue::Null.get_mut("port").is_none());
+}
+
+#[test]
+fn strings_are_decoded() {
+    let value = parse(r#"["A\n\t\"\\\/", "😀", "café"]"#);
+    let items = value.as_array().unwrap();
+    assert_eq!(items[0].as_str(), Some("A\n\t\"\\/"));
+    assert_eq!(items[1].as_str(), Some("\u{1f600}"));
+    assert_eq!(items[2].as_str(), Some("caf\u{e9}"));
+}
+
+#[test]
+fn from_ast_matches_parse() {
+    let mut ast = JsonAst::new();
+    ast.parse(DOCUMENT).unwrap();
+    assert_eq!(JsonValue::from_ast(&ast, DOCUMENT), parse(DOCUMENT));
+}
+
+#[test]
+fn to_ast_round_trip() {
+    for text in [
+        DOCUMENT,
+        "null",
+        "[]",
+        "{}",
+        "\"tab\\t\"",
+        "[1e400, -0, {\"\": []}]",
+    ]
+    .iter()
+    {
+        let value = parse(text);
+        let (ast, raw_text) = value.to_ast();
+        assert_eq!(JsonValue::from_ast(&ast, &raw_text), value, "{}", text);
+        // the text `to_ast` hands back is compact json on its own
+        assert_eq!(parse(&raw_text), value);
+    }
+}
+
+#[test]
+fn builds_by_hand() {
+    let value = JsonValue::Object(vec![
+        (String::from("a"), JsonValue::Bool(false)),
+        (
+            String::from("b"),
+            JsonValue::Array(vec![JsonValue::Null, JsonValue::String(String::from("x"))]),
+        ),
+    ]);
+    let (_, raw_text) = value.to_ast();
+    assert_eq!(raw_text, r#"{"a":false,"b":[null,"x"]}"#);
+}
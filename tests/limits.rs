@@ -0,0 +1,254 @@
+//! Every `Limit` for every parser that enforces it, right at the limit and one past it
+use std::io;
+
+use parser_util::{
+    json::{
+        ast::{ParseError, ParseOptions},
+        reader::{JsonReader, ReaderError},
+        value::JsonValue,
+    },
+    limits::{Limit, Limits},
+    xml::{ast::XmlParser, XMLErrorKind},
+};
+
+/// Which limit `JsonValue::parse_with(..)` ran into, if any
+fn json_limit(text: &str, limits: Limits) -> Option<Limit> {
+    let options = ParseOptions {
+        limits,
+        ..ParseOptions::default()
+    };
+    match JsonValue::parse_with(text, &options) {
+        Ok(_) => None,
+        Err(ParseError::LimitExceeded { limit, .. }) => Some(limit),
+        Err(err) => panic!("{}: {:?}", text, err),
+    }
+}
+
+/// Which limit a `JsonReader` ran into, read in tiny chunks so tokens span several of them
+fn reader_limit(text: &str, limits: Limits) -> Option<Limit> {
+    let reader = JsonReader::with_chunk_size(io::Cursor::new(text), 3).with_limits(limits);
+    for event in reader {
+        match event {
+            Ok(_) => (),
+            Err(ReaderError::LimitExceeded { limit, .. }) => return Some(limit),
+            Err(err) => panic!("{}: {:?}", text, err),
+        }
+    }
+    None
+}
+
+/// Which limit `XmlParser::parse_with_limits(..)` ran into, if any
+fn xml_limit(text: &str, limits: Limits) -> Option<Limit> {
+    let mut parser = XmlParser::new();
+    match parser.parse_with_limits(text, &limits) {
+        Ok(_) => None,
+        Err(XMLErrorKind::LimitExceeded(limit)) => Some(limit),
+        Err(err) => panic!("{}: {:?}", text, err),
+    }
+}
+
+/// Checks that `text` passes with `at_limit` and fails with `limit` one below it, in every json parser
+fn json_boundary(text: &str, limit: Limit, at_limit: Limits, over: Limits) {
+    assert_eq!(json_limit(text, at_limit), None, "{}", text);
+    assert_eq!(reader_limit(text, at_limit), None, "{}", text);
+    assert_eq!(json_limit(text, over), Some(limit), "{}", text);
+    assert_eq!(reader_limit(text, over), Some(limit), "{}", text);
+}
+
+fn xml_boundary(text: &str, limit: Limit, at_limit: Limits, over: Limits) {
+    assert_eq!(xml_limit(text, at_limit), None, "{}", text);
+    assert_eq!(xml_limit(text, over), Some(limit), "{}", text);
+}
+
+#[test]
+fn json_depth() {
+    let depth = |max_depth| Limits {
+        max_depth,
+        ..Limits::none()
+    };
+    json_boundary(r#"[[1], {"a": 2}]"#, Limit::Depth, depth(2), depth(1));
+    json_boundary("[]", Limit::Depth, depth(1), depth(0));
+    assert_eq!(json_limit("1", depth(0)), None);
+
+    let options = ParseOptions {
+        limits: depth(1),
+        ..ParseOptions::default()
+    };
+    let err = JsonValue::parse_with("[\n [1]]", &options).unwrap_err();
+    let diag = err.diagnostic();
+    assert_eq!((diag.pos.line, diag.pos.column), (2, 2));
+    assert_eq!(diag.expected, "at most 1 levels of nesting");
+}
+
+#[test]
+fn json_document_bytes() {
+    let bytes = |max_document_bytes| Limits {
+        max_document_bytes,
+        ..Limits::none()
+    };
+    let text = r#"{"a": [1, 2]} "#;
+    json_boundary(text, Limit::DocumentBytes, bytes(14), bytes(13));
+
+    let options = ParseOptions {
+        limits: bytes(13),
+        ..ParseOptions::default()
+    };
+    let err = JsonValue::parse_with(text, &options).unwrap_err();
+    assert_eq!(err.diagnostic().found, "a document of 14 bytes");
+    assert_eq!(err.diagnostic().pos.offset, 13);
+    let err = JsonReader::new(io::Cursor::new(text))
+        .with_limits(bytes(13))
+        .find_map(Result::err)
+        .unwrap();
+    assert_eq!(
+        err.to_string(),
+        "expected at most 13 bytes, found a longer document at line 1, column 14"
+    );
+}
+
+#[test]
+fn json_tokens() {
+    let tokens = |max_tokens| Limits {
+        max_tokens,
+        ..Limits::none()
+    };
+    // `[`, `1`, `,`, `"a"`, `:`... every bracket and separator counts
+    json_boundary(r#"[1, "a", true]"#, Limit::Tokens, tokens(7), tokens(6));
+    json_boundary(r#"{"a": null}"#, Limit::Tokens, tokens(5), tokens(4));
+}
+
+#[test]
+fn json_string_length() {
+    let len = |max_string_len| Limits {
+        max_string_len,
+        ..Limits::none()
+    };
+    // strings, property names and numbers all count
+    json_boundary(r#"{"value": "12345"}"#, Limit::StringLength, len(5), len(4));
+    json_boundary(r#"[1, "abc", 12345]"#, Limit::StringLength, len(5), len(4));
+    // escapes are measured the way they are written
+    json_boundary(r#"["\u00e9"]"#, Limit::StringLength, len(6), len(5));
+
+    // a huge string is rejected without being buffered whole
+    let huge = format!("[\"{}\"]", "x".repeat(100_000));
+    let reader = JsonReader::with_chunk_size(io::Cursor::new(huge), 64).with_limits(len(10));
+    let err = reader.last().unwrap().unwrap_err();
+    assert!(matches!(
+        err,
+        ReaderError::LimitExceeded {
+            limit: Limit::StringLength,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn json_ignores_the_xml_only_limits() {
+    let limits = Limits {
+        max_attributes: 0,
+        max_entity_depth: 0,
+        max_entity_expansion: 0,
+        ..Limits::none()
+    };
+    let text = r#"{"a": [1, {"b": "&amp;"}]}"#;
+    assert_eq!(json_limit(text, limits), None);
+    assert_eq!(reader_limit(text, limits), None);
+}
+
+#[test]
+fn xml_depth() {
+    let depth = |max_depth| Limits {
+        max_depth,
+        ..Limits::none()
+    };
+    // empty elements are as deep as any other
+    let text = "<a><b><c/></b><d>text</d></a>";
+    xml_boundary(text, Limit::Depth, depth(3), depth(2));
+    xml_boundary("<a/>", Limit::Depth, depth(1), depth(0));
+}
+
+#[test]
+fn xml_document_bytes() {
+    let bytes = |max_document_bytes| Limits {
+        max_document_bytes,
+        ..Limits::none()
+    };
+    xml_boundary("<a>text</a>", Limit::DocumentBytes, bytes(11), bytes(10));
+}
+
+#[test]
+fn xml_tokens() {
+    let tokens = |max_tokens| Limits {
+        max_tokens,
+        ..Limits::none()
+    };
+    // `<a>`, `<b/>`, `text` and `</a>`
+    xml_boundary("<a><b/>text</a>", Limit::Tokens, tokens(4), tokens(3));
+}
+
+#[test]
+fn xml_string_length() {
+    let len = |max_string_len| Limits {
+        max_string_len,
+        ..Limits::none()
+    };
+    xml_boundary("<a>text</a>", Limit::StringLength, len(4), len(3));
+    xml_boundary("<a x=\"value\"/>", Limit::StringLength, len(5), len(4));
+    xml_boundary("<tag5></tag5>", Limit::StringLength, len(4), len(3));
+}
+
+#[test]
+fn xml_attributes() {
+    let attributes = |max_attributes| Limits {
+        max_attributes,
+        ..Limits::none()
+    };
+    let text = "<a x=\"1\" y=\"2\"><b z=\"3\"/></a>";
+    xml_boundary(text, Limit::Attributes, attributes(2), attributes(1));
+}
+
+#[test]
+fn xml_entity_depth() {
+    let depth = |max_entity_depth| Limits {
+        max_entity_depth,
+        ..Limits::none()
+    };
+    let text =
+        "<!DOCTYPE a [<!ENTITY e1 \"&e2;\"><!ENTITY e2 \"&e3;\"><!ENTITY e3 \"x\">]><a>&e1;</a>";
+    xml_boundary(text, Limit::EntityDepth, depth(3), depth(2));
+    // attribute values are expanded the same way
+    let text = "<!DOCTYPE a [<!ENTITY e1 \"&e2;\"><!ENTITY e2 \"x\">]><a v=\"&e1;\"/>";
+    xml_boundary(text, Limit::EntityDepth, depth(2), depth(1));
+}
+
+#[test]
+fn xml_entity_expansion() {
+    let expansion = |max_entity_expansion| Limits {
+        max_entity_expansion,
+        ..Limits::none()
+    };
+    // every expansion counts, nested ones included: 4 + 4 + 1 bytes
+    let text =
+        "<!DOCTYPE a [<!ENTITY e1 \"&e2;\"><!ENTITY e2 \"&e3;\"><!ENTITY e3 \"x\">]><a>&e1;</a>";
+    xml_boundary(text, Limit::EntityExpansion, expansion(9), expansion(8));
+
+    // 24 bytes for `lol3`, 4 * 20 for `lol2` and 16 * 3 for `lol`
+    let text = "<!DOCTYPE lolz [<!ENTITY lol \"lol\"><!ENTITY lol2 \"&lol;&lol;&lol;&lol;\"><!ENTITY lol3 \"&lol2;&lol2;&lol2;&lol2;\">]><lolz>&lol3;</lolz>";
+    xml_boundary(text, Limit::EntityExpansion, expansion(152), expansion(151));
+
+    // the default limits stop a billion laughs, nested just shallow enough to get past `max_entity_depth`
+    let mut text = String::from("<!DOCTYPE lolz [<!ENTITY lol0 \"lol\">");
+    for level in 1..8 {
+        let refs = format!("&lol{};", level - 1).repeat(10);
+        text.push_str(&format!("<!ENTITY lol{} \"{}\">", level, refs));
+    }
+    text.push_str("]><lolz>&lol7;</lolz>");
+    assert_eq!(
+        xml_limit(&text, Limits::default()),
+        Some(Limit::EntityExpansion)
+    );
+    assert_eq!(
+        xml_limit(&text, Limits::untrusted()),
+        Some(Limit::EntityExpansion)
+    );
+}
@@ -0,0 +1,177 @@
+//! Comments end at the first `-->`, are kept as `Comment` nodes wherever they show up and are written
+//! back out in place, unless `strip_comments` drops them
+use parser_util::xml::{
+    ast::{XmlAst, XmlParseOptions, XmlParser},
+    lexer::XmlTokenKind,
+    XMLErrorKind,
+};
+
+fn parse_with(text: &str, strip_comments: bool) -> Result<XmlAst, XMLErrorKind> {
+    let options = XmlParseOptions {
+        strip_comments,
+        ..XmlParseOptions::default()
+    };
+    let mut parser = XmlParser::new();
+    parser.parse_with(text, &options)?;
+    Ok(parser.into_ast())
+}
+
+fn parse(text: &str) -> Result<XmlAst, XMLErrorKind> {
+    parse_with(text, false)
+}
+
+/// `comment:<text>` for comments, `text:<text>` for text and the tag name for elements
+fn describe(ast: &XmlAst, nodes: &[u32]) -> Vec<String> {
+    nodes
+        .iter()
+        .map(|&node| {
+            let token = ast[node].data.as_ref().unwrap();
+            match token.token_kind {
+                XmlTokenKind::Comment => format!("comment:{}", token.content),
+                XmlTokenKind::ContentTag => format!("text:{}", token.content),
+                XmlTokenKind::Declaration => "declaration".to_string(),
+                XmlTokenKind::ProcessingInstruction => format!("pi:{}", token.content),
+                _ => token.content.clone(),
+            }
+        })
+        .collect()
+}
+
+/// The text of the only comment in `<a>..</a>`
+fn comment(inner: &str) -> Result<String, XMLErrorKind> {
+    let ast = parse(&format!("<a>{}</a>", inner))?;
+    let root = ast.ast.root_list[0];
+    let token = ast[ast[root].children[0]].data.as_ref().unwrap();
+    assert!(token.token_kind == XmlTokenKind::Comment);
+    Ok(token.content.clone())
+}
+
+#[test]
+fn comments_end_at_the_first_close() {
+    assert_eq!(comment("<!-- plain -->").unwrap(), " plain ");
+    assert_eq!(comment("<!---->").unwrap(), "");
+    assert_eq!(comment("<!-- - -->").unwrap(), " - ");
+    assert_eq!(comment("<!-- a -> b-->").unwrap(), " a -> b");
+    assert_eq!(comment("<!--x->-->").unwrap(), "x->");
+    // markup and references inside are just text
+    assert_eq!(
+        comment("<!-- <b attr=\"1\"> &amp; &bogus; ]]> -->").unwrap(),
+        " <b attr=\"1\"> &amp; &bogus; ]]> "
+    );
+    assert_eq!(
+        comment("<!--\nline one\nline two\n-->").unwrap(),
+        "\nline one\nline two\n"
+    );
+
+    // the first `-->` closes it, what follows is content again
+    let ast = parse("<a><!-- one -->two<!-- three --></a>").unwrap();
+    let root = ast.ast.root_list[0];
+    assert_eq!(
+        describe(&ast, &ast[root].children),
+        vec!["comment: one ", "text:two", "comment: three "]
+    );
+}
+
+#[test]
+fn double_hyphens_are_rejected() {
+    for doc in [
+        "<a><!-- a -- b --></a>",
+        "<a><!-- ends with a hyphen ---></a>",
+        "<a><!----></a><!-- -- -->",
+        "<a><!-----></a>",
+    ]
+    .iter()
+    {
+        assert!(
+            matches!(parse(doc), Err(XMLErrorKind::TokenizerErr(_))),
+            "{}",
+            doc
+        );
+    }
+    // and so are comments that never end
+    for doc in [
+        "<a><!-- open</a>",
+        "<a/><!-- open",
+        "<a/><!-- open --",
+        "<a/><!---",
+    ]
+    .iter()
+    {
+        assert!(parse(doc).is_err(), "{}", doc);
+    }
+}
+
+#[test]
+fn comments_everywhere() {
+    let doc = "<?xml version=\"1.0\"?><!-- before --><a><!-- first child --><b>x<!-- in b -->y</b><!-- between --><c/><!-- last child --></a><!-- after --><?pi?><!-- end -->";
+    let ast = parse(doc).unwrap();
+    assert_eq!(
+        describe(&ast, &ast.prolog),
+        vec!["declaration", "comment: before "]
+    );
+    assert_eq!(
+        describe(&ast, &ast.epilog),
+        vec!["comment: after ", "pi:pi", "comment: end "]
+    );
+    let root = ast.ast.root_list[0];
+    assert_eq!(
+        describe(&ast, &ast[root].children),
+        vec![
+            "comment: first child ",
+            "b",
+            "comment: between ",
+            "c",
+            "comment: last child "
+        ]
+    );
+    let b = ast[root].children[1];
+    assert_eq!(
+        describe(&ast, &ast[b].children),
+        vec!["text:x", "comment: in b ", "text:y"]
+    );
+    // comments aren't part of the text
+    assert_eq!(ast.inner_text(b), "xy");
+
+    // a document that's only comments still needs a root
+    assert!(parse("<!-- nothing -->").is_err());
+    assert!(parse("<!-- nothing --><a/>").is_ok());
+}
+
+#[test]
+fn strip_comments() {
+    let doc = "<!-- before --><a><!-- first child --><b>x<!-- in b -->y</b><!----></a><!-- after --><?pi?>";
+    let ast = parse_with(doc, true).unwrap();
+    assert!(ast.prolog.is_empty());
+    assert_eq!(describe(&ast, &ast.epilog), vec!["pi:pi"]);
+    let root = ast.ast.root_list[0];
+    assert_eq!(describe(&ast, &ast[root].children), vec!["b"]);
+    let b = ast[root].children[0];
+    assert_eq!(describe(&ast, &ast[b].children), vec!["text:x", "text:y"]);
+    assert_eq!(ast.inner_text(root), "xy");
+    assert_eq!(ast.to_xml(), "<a><b>xy</b></a><?pi?>");
+
+    // stripping doesn't make bad comments acceptable
+    assert!(parse_with("<a><!-- a -- b --></a>", true).is_err());
+    // or add a root that isn't there
+    assert!(parse_with("<!-- nothing -->", true).is_err());
+}
+
+#[test]
+fn to_xml_keeps_comments_in_place() {
+    for doc in [
+        "<!-- before --><a><!-- first --><b>x<!-- in b -->y</b><!----><c/></a><!-- after -->",
+        "<a>text<!-- a > b & c --></a>",
+        "<a><!--\n  multi\n  line\n--></a>",
+    ]
+    .iter()
+    {
+        let ast = parse(doc).unwrap();
+        assert_eq!(ast.to_xml(), *doc);
+        // and parsing the output again gives the same document
+        assert_eq!(parse(&ast.to_xml()).unwrap().to_xml(), *doc);
+    }
+
+    // trimming leaves the comment text alone
+    let ast = parse("<a>\n  <!-- keep my spacing -->\n  <b>x</b>\n</a>").unwrap();
+    assert_eq!(ast.to_xml_trim(), "<a><!-- keep my spacing --><b>x</b></a>");
+}
@@ -0,0 +1,375 @@
+//! `<!DOCTYPE ..>` parsing and the expansion of the entities it declares
+use parser_util::{
+    limits::{Limit, Limits},
+    xml::{
+        ast::{XmlAst, XmlParser},
+        dtd::{AttDef, AttDefault, Doctype, EntityValue, ExternalId, MarkupDecl},
+        lexer::XmlLexer,
+        XMLErrorKind,
+    },
+};
+
+fn parse(text: &str) -> Result<XmlAst, XMLErrorKind> {
+    let mut parser = XmlParser::new();
+    parser.parse(&text.to_string())?;
+    Ok(parser.into_ast())
+}
+
+fn doctype(text: &str) -> Doctype {
+    parse(text).unwrap().doctype.unwrap()
+}
+
+/// the root's text and the value of its `v` attribute
+fn expanded(subset: &str, body: &str) -> Result<(String, Option<String>), XMLErrorKind> {
+    let ast = parse(&format!("<!DOCTYPE a [{}]>{}", subset, body))?;
+    let root = ast.ast.root_list[0];
+    let attrib = ast[root].data.as_ref().unwrap().get_attrib("v").cloned();
+    Ok((ast.inner_text(root), attrib))
+}
+
+#[test]
+fn markup_declarations() {
+    let doc = r#"<!DOCTYPE book [
+  <!ELEMENT book (title, chapter+)>
+  <!ELEMENT title (#PCDATA)>
+  <!ELEMENT br EMPTY>
+  <!ATTLIST chapter
+      id ID #REQUIRED
+      lang CDATA #IMPLIED
+      kind (intro|body) "body"
+      version CDATA #FIXED "1.0">
+  <!ENTITY author "Jane &amp; John">
+  <!ENTITY % common "INCLUDE">
+  <!ENTITY cover SYSTEM "cover.png" NDATA png>
+  <!NOTATION png PUBLIC "image/png">
+  <!-- a comment with a > in it -->
+  <?pi with > too?>
+  %common;
+]><book/>"#;
+    let expected = Doctype {
+        name: "book".to_string(),
+        external_id: None,
+        declarations: vec![
+            MarkupDecl::Element {
+                name: "book".to_string(),
+                content_spec: "(title, chapter+)".to_string(),
+            },
+            MarkupDecl::Element {
+                name: "title".to_string(),
+                content_spec: "(#PCDATA)".to_string(),
+            },
+            MarkupDecl::Element {
+                name: "br".to_string(),
+                content_spec: "EMPTY".to_string(),
+            },
+            MarkupDecl::AttList {
+                element: "chapter".to_string(),
+                attributes: vec![
+                    AttDef {
+                        name: "id".to_string(),
+                        att_type: "ID".to_string(),
+                        default: AttDefault::Required,
+                    },
+                    AttDef {
+                        name: "lang".to_string(),
+                        att_type: "CDATA".to_string(),
+                        default: AttDefault::Implied,
+                    },
+                    AttDef {
+                        name: "kind".to_string(),
+                        att_type: "(intro|body)".to_string(),
+                        default: AttDefault::Value("body".to_string()),
+                    },
+                    AttDef {
+                        name: "version".to_string(),
+                        att_type: "CDATA".to_string(),
+                        default: AttDefault::Fixed("1.0".to_string()),
+                    },
+                ],
+            },
+            MarkupDecl::Entity {
+                name: "author".to_string(),
+                parameter: false,
+                value: EntityValue::Internal("Jane &amp; John".to_string()),
+            },
+            MarkupDecl::Entity {
+                name: "common".to_string(),
+                parameter: true,
+                value: EntityValue::Internal("INCLUDE".to_string()),
+            },
+            MarkupDecl::Entity {
+                name: "cover".to_string(),
+                parameter: false,
+                value: EntityValue::External {
+                    id: ExternalId::System("cover.png".to_string()),
+                    notation: Some("png".to_string()),
+                },
+            },
+            MarkupDecl::Notation {
+                name: "png".to_string(),
+                id: ExternalId::Public {
+                    public: "image/png".to_string(),
+                    system: None,
+                },
+            },
+        ],
+    };
+    assert_eq!(doctype(doc), expected);
+    // parameter entities aren't general entities
+    assert_eq!(
+        expected.entity("author"),
+        Some(&EntityValue::Internal("Jane &amp; John".to_string()))
+    );
+    assert_eq!(expected.entity("common"), None);
+
+    for doc in [
+        "<!DOCTYPE a [<!ELEMENT>]><a/>",
+        "<!DOCTYPE a [<!ATTLIST a x CDATA>]><a/>",
+        "<!DOCTYPE a [<!ENTITY e>]><a/>",
+        "<!DOCTYPE a [<!ENTITY e \"unterminated>]><a/>",
+        "<!DOCTYPE a [<!BOGUS a>]><a/>",
+        "<!DOCTYPE a [text]><a/>",
+        "<!DOCTYPE a [<!ELEMENT a EMPTY>",
+        "<!DOCTYPE><a/>",
+        "<!DOCTYPE a junk><a/>",
+    ]
+    .iter()
+    {
+        assert!(parse(doc).is_err(), "{}", doc);
+    }
+}
+
+#[test]
+fn external_ids() {
+    assert_eq!(
+        doctype("<!DOCTYPE html SYSTEM \"about:legacy-compat\"><html/>").external_id,
+        Some(ExternalId::System("about:legacy-compat".to_string()))
+    );
+    let html = doctype(
+        "<!DOCTYPE html PUBLIC '-//W3C//DTD XHTML 1.0 Strict//EN' \"http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd\"><html/>",
+    );
+    assert_eq!(html.name, "html");
+    assert_eq!(
+        html.external_id,
+        Some(ExternalId::Public {
+            public: "-//W3C//DTD XHTML 1.0 Strict//EN".to_string(),
+            system: Some("http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd".to_string()),
+        })
+    );
+    // the external subset is never fetched, so the internal one still works next to it
+    let both = doctype("<!DOCTYPE a SYSTEM \"a.dtd\" [<!ENTITY e \"x\">]><a>&e;</a>");
+    assert_eq!(
+        both.external_id,
+        Some(ExternalId::System("a.dtd".to_string()))
+    );
+    assert_eq!(both.declarations.len(), 1);
+
+    // only a notation may leave out the system literal
+    assert!(parse("<!DOCTYPE a PUBLIC \"id\"><a/>").is_err());
+    assert!(parse("<!DOCTYPE a [<!ENTITY e PUBLIC \"id\">]><a/>").is_err());
+    assert!(parse("<!DOCTYPE a SYSTEM><a/>").is_err());
+    let entity = doctype("<!DOCTYPE a [<!ENTITY e PUBLIC \"id\" \"e.xml\">]><a/>");
+    assert_eq!(
+        entity.entity("e"),
+        Some(&EntityValue::External {
+            id: ExternalId::Public {
+                public: "id".to_string(),
+                system: Some("e.xml".to_string()),
+            },
+            notation: None,
+        })
+    );
+}
+
+#[test]
+fn entities_expand_in_text_and_attributes() {
+    let subset = "<!ENTITY name \"World\"><!ENTITY greeting \"Hello, &name;!\"><!ENTITY amp2 \"&#38;&amp;\">";
+    assert_eq!(
+        expanded(
+            subset,
+            "<a v=\"&greeting; &amp2;\">&greeting; &lt;&amp2;</a>"
+        )
+        .unwrap(),
+        (
+            "Hello, World! <&&".to_string(),
+            Some("Hello, World! &&".to_string())
+        )
+    );
+
+    // markup in the replacement text stays text
+    let (text, _) = expanded("<!ENTITY b \"<b>bold</b>\">", "<a>&b;</a>").unwrap();
+    assert_eq!(text, "<b>bold</b>");
+    let ast = parse("<!DOCTYPE a [<!ENTITY b \"<b>bold</b>\">]><a>&b;</a>").unwrap();
+    let root = ast.ast.root_list[0];
+    assert_eq!(ast[root].children.len(), 1);
+
+    // the first declaration wins
+    let (text, _) = expanded("<!ENTITY e \"first\"><!ENTITY e \"second\">", "<a>&e;</a>").unwrap();
+    assert_eq!(text, "first");
+
+    for (subset, body) in [
+        ("", "<a>&missing;</a>"),
+        ("<!ENTITY % p \"x\">", "<a>&p;</a>"),
+        ("<!ENTITY ext SYSTEM \"ext.xml\">", "<a>&ext;</a>"),
+        ("<!ENTITY ext SYSTEM \"ext.xml\">", "<a v=\"&ext;\"/>"),
+        ("<!ENTITY e \"&undefined;\">", "<a>&e;</a>"),
+        ("<!ENTITY e \"x\">", "<a>&e</a>"),
+    ]
+    .iter()
+    {
+        assert!(
+            matches!(expanded(subset, body), Err(XMLErrorKind::TokenizerErr(_))),
+            "{}",
+            body
+        );
+    }
+}
+
+#[test]
+fn self_referencing_entities() {
+    for subset in [
+        "<!ENTITY e \"x&e;\">",
+        "<!ENTITY e \"&f;\"><!ENTITY f \"&e;\">",
+        "<!ENTITY e \"&f;&g;\"><!ENTITY f \"ok\"><!ENTITY g \"&e;\">",
+    ]
+    .iter()
+    {
+        for body in ["<a>&e;</a>", "<a v=\"&e;\"/>"].iter() {
+            assert!(
+                matches!(
+                    expanded(subset, body),
+                    Err(XMLErrorKind::TokenizerErr("entity references itself"))
+                ),
+                "{} {}",
+                subset,
+                body
+            );
+        }
+        // declaring one is fine as long as it isn't used
+        assert!(expanded(subset, "<a/>").is_ok());
+    }
+    // using the same entity twice side by side isn't recursion
+    let (text, _) = expanded("<!ENTITY e \"x\"><!ENTITY f \"&e;&e;\">", "<a>&f;&e;</a>").unwrap();
+    assert_eq!(text, "xxx");
+}
+
+#[test]
+fn entity_limits() {
+    // `parse(..)` uses `Limits::default()`, so deep nesting and billion laughs are stopped without asking
+    let mut subset = String::from("<!ENTITY e0 \"x\">");
+    for level in 1..=Limits::default().max_entity_depth {
+        subset.push_str(&format!("<!ENTITY e{} \"&e{};\">", level, level - 1));
+    }
+    let deepest = Limits::default().max_entity_depth;
+    assert!(expanded(&subset, &format!("<a>&e{};</a>", deepest - 1)).is_ok());
+    assert!(matches!(
+        expanded(&subset, &format!("<a>&e{};</a>", deepest)),
+        Err(XMLErrorKind::LimitExceeded(Limit::EntityDepth))
+    ));
+    assert!(matches!(
+        expanded(&subset, &format!("<a v=\"&e{};\"/>", deepest)),
+        Err(XMLErrorKind::LimitExceeded(Limit::EntityDepth))
+    ));
+
+    let mut subset = String::from("<!ENTITY lol0 \"lol\">");
+    for level in 1..8 {
+        let refs = format!("&lol{};", level - 1).repeat(10);
+        subset.push_str(&format!("<!ENTITY lol{} \"{}\">", level, refs));
+    }
+    for body in ["<a>&lol7;</a>", "<a v=\"&lol7;\"/>"].iter() {
+        assert!(matches!(
+            expanded(&subset, body),
+            Err(XMLErrorKind::LimitExceeded(Limit::EntityExpansion))
+        ));
+    }
+    // the budget is for the whole document, not for each reference
+    let text = format!("<a>{}</a>", "&lol3;".repeat(200));
+    assert!(expanded(&subset, "<a>&lol3;</a>").is_ok());
+    assert!(matches!(
+        expanded(&subset, &text),
+        Err(XMLErrorKind::LimitExceeded(Limit::EntityExpansion))
+    ));
+}
+
+#[test]
+fn doctypes_round_trip() {
+    for doc in [
+        "<!DOCTYPE a><a/>",
+        "<!DOCTYPE html PUBLIC \"-//W3C//DTD XHTML 1.0 Strict//EN\" \"xhtml1-strict.dtd\"><html/>",
+        "<?xml version=\"1.0\"?>\n<!DOCTYPE note [\n  <!ELEMENT note (#PCDATA)>\n  <!ATTLIST note kind (a|b) \"a\">\n  <!ENTITY gt2 \">>\">\n  <!-- > -->\n]><note kind=\"b\">&gt2;</note>",
+    ]
+    .iter()
+    {
+        let ast = parse(doc).unwrap();
+        let xml = ast.to_xml();
+        let reparsed = parse(&xml).unwrap();
+        assert_eq!(reparsed.doctype, ast.doctype, "{}", doc);
+        assert_eq!(reparsed.to_xml(), xml);
+        // the DOCTYPE itself is written out exactly as it was
+        let end = doc.find("]>").map_or_else(|| doc.find('>').unwrap(), |k| k + 1);
+        let start = doc.find("<!DOCTYPE").unwrap();
+        assert!(xml.contains(&doc[start..=end]), "{}", xml);
+    }
+}
+
+#[test]
+fn doctype_placement() {
+    assert!(parse("<a/><!DOCTYPE a>").is_err());
+    assert!(parse("<a><!DOCTYPE a></a>").is_err());
+    assert!(parse("<!DOCTYPE a><!DOCTYPE a><a/>").is_err());
+    // a document can't end in the middle of a markup declaration
+    for doc in [
+        "<a/><!",
+        "<!",
+        "<a/><!DOCTYPE a",
+        "<!DOCTYPE a [<!ENTITY e \"x\">",
+    ]
+    .iter()
+    {
+        assert!(
+            matches!(parse(doc), Err(XMLErrorKind::TokenizerErr(_))),
+            "{}",
+            doc
+        );
+    }
+}
+
+#[test]
+fn lexers_can_be_reused() {
+    let doc = "<!DOCTYPE a [<!ENTITY e \"12345\">]><a>&e;&e;</a>";
+    let limits = Limits {
+        max_entity_expansion: 10,
+        ..Limits::none()
+    };
+    let mut lexer = XmlLexer::new();
+    let contents = |lexer: &XmlLexer| -> Vec<String> {
+        lexer
+            .tokens
+            .iter()
+            .map(|token| token.as_ref().unwrap().content.clone())
+            .collect()
+    };
+    lexer.lex_with_limits(doc, &limits).unwrap();
+    let tokens = contents(&lexer);
+    for _ in 0..3 {
+        lexer.lex_with_limits(doc, &limits).unwrap();
+        assert_eq!(lexer.doctype.as_ref().unwrap().name, "a");
+        // the tokens of the last document aren't kept around
+        assert_eq!(contents(&lexer), tokens);
+    }
+    // a document without a doctype doesn't keep the last one
+    lexer.lex("<b/>").unwrap();
+    assert!(lexer.doctype.is_none());
+    assert_eq!(contents(&lexer), vec!["b"]);
+
+    // same for a parser, even after a failed parse
+    let mut parser = XmlParser::new();
+    assert!(parser
+        .parse(&"<!DOCTYPE a [<!ENTITY e \"x\">]><a>&nope;</a>".to_string())
+        .is_err());
+    parser.parse_with_limits(doc, &limits).unwrap();
+    assert_eq!(
+        parser.doctype.as_ref().unwrap().entity("e"),
+        Some(&EntityValue::Internal("12345".to_string()))
+    );
+}
@@ -0,0 +1,218 @@
+//! The xml declaration, processing instructions and where they're allowed to go
+use parser_util::xml::{
+    ast::{XmlAst, XmlParseOptions, XmlParser},
+    lexer::{XmlToken, XmlTokenKind},
+    XMLErrorKind,
+};
+
+fn parse(text: &str) -> Result<XmlAst, XMLErrorKind> {
+    let mut parser = XmlParser::new();
+    parser.parse(&text.to_string())?;
+    Ok(parser.into_ast())
+}
+
+fn token(ast: &XmlAst, node: u32) -> &XmlToken {
+    ast[node].data.as_ref().unwrap()
+}
+
+fn kinds(ast: &XmlAst, nodes: &[u32]) -> Vec<&'static str> {
+    nodes
+        .iter()
+        .map(|&node| match token(ast, node).token_kind {
+            XmlTokenKind::Declaration => "declaration",
+            XmlTokenKind::Doctype => "doctype",
+            XmlTokenKind::ProcessingInstruction => "pi",
+            XmlTokenKind::Comment => "comment",
+            _ => "other",
+        })
+        .collect()
+}
+
+#[test]
+fn declaration_values() {
+    let ast = parse("<?xml version=\"1.0\" encoding='UTF-8' standalone=\"yes\"?><a/>").unwrap();
+    let decl = token(&ast, ast.prolog[0]);
+    assert!(decl.token_kind == XmlTokenKind::Declaration);
+    assert_eq!(decl.version(), Some("1.0"));
+    assert_eq!(decl.encoding(), Some("UTF-8"));
+    assert_eq!(decl.standalone(), Some(true));
+
+    let ast = parse("<?xml version = '1.1'  standalone='no' ?><a/>").unwrap();
+    let decl = token(&ast, ast.prolog[0]);
+    assert_eq!(decl.version(), Some("1.1"));
+    assert_eq!(decl.encoding(), None);
+    assert_eq!(decl.standalone(), Some(false));
+
+    let ast = parse("<?xml version=\"1.0\" encoding=\"iso-8859_1.x\"?><a/>").unwrap();
+    assert_eq!(token(&ast, ast.prolog[0]).standalone(), None);
+
+    // a hand built token with a bogus value isn't read as `no`
+    let mut decl = XmlToken::new(XmlTokenKind::Declaration, "xml".to_string());
+    decl.attribs = vec![
+        ("version".to_string(), "1.0".to_string()),
+        ("standalone".to_string(), "maybe".to_string()),
+    ];
+    assert_eq!(decl.standalone(), None);
+    // and only the declaration has them
+    let pi = XmlToken::new(XmlTokenKind::ProcessingInstruction, "xml".to_string());
+    assert_eq!(pi.version(), None);
+}
+
+#[test]
+fn rejects_bad_declarations() {
+    for decl in [
+        "<?xml?>",
+        "<?xml encoding=\"UTF-8\"?>",
+        "<?xml version='1\"0'?>",
+        "<?xml version=\"2.0\"?>",
+        "<?xml version=\"1.\"?>",
+        "<?xml version=\"1.0a\"?>",
+        "<?xml version=\"1.0\" encoding=\"\"?>",
+        "<?xml version=\"1.0\" encoding=\"8bit\"?>",
+        "<?xml version=\"1.0\" encoding=\"UTF 8\"?>",
+        "<?xml version=\"1.0\" standalone=\"maybe\"?>",
+        "<?xml version=\"1.0\" standalone=\"YES\"?>",
+        "<?xml version=\"1.0\" standalone=\"yes\" encoding=\"UTF-8\"?>",
+        "<?xml encoding=\"UTF-8\" version=\"1.0\"?>",
+        "<?xml version=\"1.0\" version=\"1.0\"?>",
+        "<?xml version=\"1.0\" lang=\"en\"?>",
+        "<?xml version=\"1.0?>",
+        "<?xml version=1.0?>",
+    ]
+    .iter()
+    {
+        let doc = format!("{}<a/>", decl);
+        assert!(
+            matches!(parse(&doc), Err(XMLErrorKind::TokenizerErr(_))),
+            "{}",
+            doc
+        );
+    }
+}
+
+#[test]
+fn declaration_must_come_first() {
+    for doc in [
+        "<!-- first --><?xml version=\"1.0\"?><a/>",
+        "<?pi?><?xml version=\"1.0\"?><a/>",
+        "<?xml version=\"1.0\"?><?xml version=\"1.0\"?><a/>",
+        "<a><?xml version=\"1.0\"?></a>",
+        "<a/><?xml version=\"1.0\"?>",
+    ]
+    .iter()
+    {
+        assert!(
+            matches!(parse(doc), Err(XMLErrorKind::ParserErr(_))),
+            "{}",
+            doc
+        );
+    }
+    // the same goes when comments are being stripped
+    let mut parser = XmlParser::new();
+    let options = XmlParseOptions {
+        strip_comments: true,
+        ..XmlParseOptions::default()
+    };
+    assert!(parser
+        .parse_with("<!-- first --><?xml version=\"1.0\"?><a/>", &options)
+        .is_err());
+}
+
+#[test]
+fn prolog_and_epilog() {
+    let doc = "<?xml version=\"1.0\"?><!-- license --><?style href=\"a.css\"?><!DOCTYPE a><a><?inside data?></a><!-- trailer --><?end?>";
+    let ast = parse(doc).unwrap();
+    assert_eq!(
+        kinds(&ast, &ast.prolog),
+        vec!["declaration", "comment", "pi", "doctype"]
+    );
+    assert_eq!(kinds(&ast, &ast.epilog), vec!["comment", "pi"]);
+    // an instruction inside the root is a regular child
+    let root = ast.ast.root_list[0];
+    assert_eq!(kinds(&ast, &ast[root].children), vec!["pi"]);
+    assert_eq!(ast.to_xml(), doc);
+    assert_eq!(parse(&ast.to_xml_trim()).unwrap().to_xml(), doc);
+
+    // no prolog or epilog at all
+    let ast = parse("<a/>").unwrap();
+    assert!(ast.prolog.is_empty() && ast.epilog.is_empty());
+    assert!(parse("<?xml version=\"1.0\"?><!-- no root -->").is_err());
+}
+
+#[test]
+fn processing_instructions_round_trip() {
+    let doc = "<?xml-stylesheet type=\"text/xsl\" href=\"a.xsl\"?><a><?php echo \"<b>\" & $x; ?><?empty?></a>";
+    let ast = parse(doc).unwrap();
+    // only the exact target `xml` is the declaration
+    let style = token(&ast, ast.prolog[0]);
+    assert!(style.token_kind == XmlTokenKind::ProcessingInstruction);
+    assert_eq!(style.content, "xml-stylesheet");
+    assert_eq!(style.data, "type=\"text/xsl\" href=\"a.xsl\"");
+    assert!(style.attribs.is_empty());
+
+    // the data is kept verbatim, markup and ampersands included
+    let root = ast.ast.root_list[0];
+    let php = token(&ast, ast[root].children[0]);
+    assert_eq!(php.content, "php");
+    assert_eq!(php.data, "echo \"<b>\" & $x; ");
+    assert_eq!(token(&ast, ast[root].children[1]).data, "");
+    assert_eq!(ast.to_xml(), doc);
+
+    for doc in [
+        "<?XML version=\"1.0\"?><a/>",
+        "<a><?xMl data?></a>",
+        "<a><? data?></a>",
+        "<a><?pi data</a>",
+    ]
+    .iter()
+    {
+        assert!(parse(doc).is_err(), "{}", doc);
+    }
+}
+
+#[test]
+fn declarations_are_written_safely() {
+    let doc = "<?xml version='1.0' encoding='UTF-8'?><a/>";
+    let ast = parse(doc).unwrap();
+    assert_eq!(
+        ast.to_xml(),
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?><a/>"
+    );
+
+    // a value the lexer would never produce still can't break out of its quotes
+    let mut ast = parse("<?xml version=\"1.0\"?><a/>").unwrap();
+    let decl = ast.prolog[0];
+    ast[decl].data.as_mut().unwrap().attribs[0].1 = "1\"0".to_string();
+    assert_eq!(ast.to_xml(), "<?xml version='1\"0'?><a/>");
+}
+
+#[test]
+fn parsers_can_be_reused() {
+    let first = "<?xml version=\"1.0\"?><!DOCTYPE a><!-- license --><?style a.css?><a>one</a><!-- trailer --><?end?>";
+    let second = "<b>two</b>";
+    let mut parser = XmlParser::new();
+    parser.parse(&first.to_string()).unwrap();
+    parser.parse(&second.to_string()).unwrap();
+    assert!(parser.prolog.is_empty() && parser.epilog.is_empty());
+    assert!(parser.doctype.is_none());
+    assert_eq!(parser.ast.root_list.len(), 1);
+    let ast = parser.into_ast();
+    assert_eq!(ast.to_xml(), second);
+
+    // a failed parse doesn't leak into the next one either
+    let mut parser = XmlParser::new();
+    parser.parse(&first.to_string()).unwrap();
+    assert!(parser
+        .parse(&"<?xml version=\"1.0\"?><!-- half --><c>".to_string())
+        .is_err());
+    parser.parse(&second.to_string()).unwrap();
+    assert_eq!(parser.into_ast().to_xml(), second);
+
+    // and reparsing the same document gives the same output
+    let mut parser = XmlParser::new();
+    for _ in 0..3 {
+        parser.parse(&first.to_string()).unwrap();
+        assert_eq!((parser.prolog.len(), parser.epilog.len()), (4, 2));
+    }
+    assert_eq!(parser.into_ast().to_xml(), first);
+}
@@ -0,0 +1,125 @@
+//! Character references have to be decoded on parse and escaped again on serialization,
+//! so parse -> to_xml -> parse gives back the same text and attribute values
+use parser_util::xml::{
+    ast::{XmlAst, XmlParser},
+    lexer::{XmlToken, XmlTokenKind},
+    XMLErrorKind,
+};
+
+fn parse(text: &str) -> Result<XmlAst, XMLErrorKind> {
+    let mut parser = XmlParser::new();
+    parser.parse(&text.to_string())?;
+    Ok(parser.into_ast())
+}
+
+fn root(ast: &XmlAst) -> &XmlToken {
+    ast[ast.ast.root_list[0]].data.as_ref().unwrap()
+}
+
+/// the text of the root's first child
+fn text(ast: &XmlAst) -> &str {
+    let child = ast[ast.ast.root_list[0]].children[0];
+    &ast[child].data.as_ref().unwrap().content
+}
+
+#[test]
+fn decodes_predefined_entities() {
+    let ast = parse("<a x=\"1 &lt; 2 &amp;&amp; &quot;q&quot; &apos;s&apos;\">Tom &amp; Jerry &gt; &lt;tag&gt;</a>")
+        .unwrap();
+    assert_eq!(root(&ast).get_attrib("x").unwrap(), "1 < 2 && \"q\" 's'");
+    assert_eq!(text(&ast), "Tom & Jerry > <tag>");
+}
+
+#[test]
+fn decodes_character_references() {
+    let ast = parse("<a x=\"&#65;&#x42;&#X43;\">&#x4e2d;&#128512;&#x9;end</a>");
+    // `&#X..;` isn't a valid reference, only lowercase `x` is
+    assert!(ast.is_err());
+
+    let ast = parse("<a x=\"&#65;&#x42;\">&#x4e2d;&#128512;&#x9;end</a>").unwrap();
+    assert_eq!(root(&ast).get_attrib("x").unwrap(), "AB");
+    assert_eq!(text(&ast), "\u{4e2d}\u{1f600}\tend");
+}
+
+#[test]
+fn rejects_bad_references() {
+    for doc in [
+        "<a>&nbsp;</a>",
+        "<a>fish & chips</a>",
+        "<a>&#0;</a>",
+        "<a>&#xD800;</a>",
+        "<a>&#x110000;</a>",
+        "<a>&#;</a>",
+        "<a x=\"&bogus;\"/>",
+    ]
+    .iter()
+    {
+        assert!(parse(doc).is_err(), "{}", doc);
+    }
+}
+
+#[test]
+fn escapes_on_serialization() {
+    let ast =
+        parse("<a x=\"say &quot;hi&quot; &amp; &lt;go&gt;\">1 &lt; 2 &amp; 3 &gt; 2</a>").unwrap();
+    assert_eq!(
+        ast.to_xml(),
+        "<a x=\"say &quot;hi&quot; &amp; &lt;go>\">1 &lt; 2 &amp; 3 &gt; 2</a>"
+    );
+}
+
+#[test]
+fn values_survive_a_round_trip() {
+    // build the tree by hand so the values start out unescaped
+    let mut ast = parse("<root><item/></root>").unwrap();
+    let tricky = "quotes \" ' and <brackets> & ampersands &amp; tab\tnewline\n";
+    let item = ast[ast.ast.root_list[0]].children[0];
+    ast[item]
+        .data
+        .as_mut()
+        .unwrap()
+        .attribs
+        .push((String::from("v"), tricky.to_string()));
+    let text_node = ast
+        .ast
+        .allocate(XmlToken::new(XmlTokenKind::ContentTag, tricky.to_string()));
+    let root_ptr = ast.ast.root_list[0];
+    ast.ast.add_child(root_ptr, text_node);
+
+    let xml = ast.to_xml();
+    let reparsed = parse(&xml).unwrap();
+    assert_eq!(reparsed.to_xml(), xml);
+    let item = reparsed[reparsed.ast.root_list[0]].children[0];
+    assert_eq!(
+        reparsed[item]
+            .data
+            .as_ref()
+            .unwrap()
+            .get_attrib("v")
+            .unwrap(),
+        tricky
+    );
+    let text_node = reparsed[reparsed.ast.root_list[0]].children[1];
+    assert_eq!(reparsed[text_node].data.as_ref().unwrap().content, tricky);
+}
+
+#[test]
+fn plain_documents_are_unchanged() {
+    let doc = "<config><server host=\"a.example\"/><name>primary</name></config>";
+    assert_eq!(parse(doc).unwrap().to_xml(), doc);
+}
+
+#[test]
+fn cdata_sections_round_trip() {
+    let doc = "<page><script><![CDATA[if (a < b && c) { x = \"&amp;\"; }]]></script></page>";
+    let ast = parse(doc).unwrap();
+    assert_eq!(ast.to_xml(), doc);
+
+    let script = ast[ast.ast.root_list[0]].children[0];
+    let cdata = ast[script].children[0];
+    let token = ast[cdata].data.as_ref().unwrap();
+    assert!(token.token_kind == XmlTokenKind::CData);
+    // references aren't decoded inside CDATA
+    assert_eq!(token.text(), Some("if (a < b && c) { x = \"&amp;\"; }"));
+    assert_eq!(ast.inner_text(ast.ast.root_list[0]), "if (a < b && c) { x = \"&amp;\"; }");
+}